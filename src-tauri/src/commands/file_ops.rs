@@ -1,5 +1,9 @@
+use crate::ffmpeg::expand::{self, Expansion, JobTemplate};
+use crate::ffmpeg::probe::probe_media;
+use crate::utils::template::{self, Collision, TemplateContext};
 use crate::{log_error, FFStudioError, Result};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri_plugin_dialog::DialogExt;
 
@@ -80,3 +84,57 @@ pub fn get_file_info(path: String) -> Result<serde_json::Value> {
 
     Ok(file_info)
 }
+
+/// Copies `source` to `destination`, used to save previews and exports out of the cache.
+#[tauri::command]
+pub fn copy_file(source: String, destination: String) -> Result<()> {
+    if source.is_empty() || destination.is_empty() {
+        return Err(FFStudioError::file_system("Empty path provided"));
+    }
+
+    std::fs::copy(&source, &destination).map_err(|e| {
+        FFStudioError::file_system(format!("Failed to copy {source} to {destination}: {e}"))
+    })?;
+    Ok(())
+}
+
+/// Renders an output path template for `input`, applying the collision policy.
+/// Returns `None` when the output already exists and the policy says to skip it.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub fn render_output_path(
+    template: String,
+    input: String,
+    workflow: Option<String>,
+    counter: Option<u64>,
+    variables: Option<HashMap<String, String>>,
+    collision: Option<Collision>,
+    ffmpeg: Option<String>,
+    env: Option<String>,
+) -> Result<Option<String>> {
+    if template.trim().is_empty() {
+        return Err(FFStudioError::parse("Empty output template provided"));
+    }
+
+    let mut ctx = TemplateContext {
+        input: PathBuf::from(&input),
+        workflow: workflow.unwrap_or_default(),
+        counter: counter.unwrap_or(1),
+        variables: variables.unwrap_or_default(),
+        ..TemplateContext::default()
+    };
+    if let (true, Some(ffmpeg)) = (template::needs_probe(&template), ffmpeg) {
+        ctx.probe = Some(probe_media(&input, &ffmpeg, &env.unwrap_or_default())?);
+    }
+
+    let path = PathBuf::from(template::render(&template, &ctx)?);
+    let resolved =
+        template::resolve_collision(path, &collision.unwrap_or_default(), &HashSet::new());
+    Ok(resolved.map(|p| p.to_string_lossy().to_string()))
+}
+
+/// Expands a command template into concrete jobs, see [`expand::expand_job`].
+#[tauri::command]
+pub async fn expand_job(request: JobTemplate) -> Result<Expansion> {
+    expand::expand_job(&request, &HashSet::new())
+}
@@ -1,8 +1,8 @@
+use crate::ffmpeg::preview_cache::{cache_dir, clear_tmp, PreviewCache};
 use crate::ffmpeg::version::get_mediainfo;
 use crate::utils::filesystem::get_data_dir;
 use crate::workflow::types::MIResponse;
-
-use std::fs;
+use crate::Result;
 
 #[tauri::command]
 pub fn get_mediainfo_request(path: String, ffmpeg: String, env: String) -> MIResponse {
@@ -19,19 +19,9 @@ pub fn get_mediainfo_request(path: String, ffmpeg: String, env: String) -> MIRes
     }
 }
 
+/// Deletes every cached preview and anything else left in `tmp/`.
 #[tauri::command]
-pub async fn delete_cache_request() {
-    let data_path = get_data_dir().unwrap();
-    let tmp_path = data_path.join("tmp");
-    if tmp_path.exists() {
-        for entry in fs::read_dir(tmp_path).unwrap() {
-            let entry = entry.unwrap();
-            let entry_path = entry.path();
-            if entry_path.is_dir() {
-                fs::remove_dir_all(entry_path).unwrap();
-            } else {
-                fs::remove_file(entry_path).unwrap();
-            }
-        }
-    }
+pub async fn delete_cache_request(cache: tauri::State<'_, PreviewCache>) -> Result<()> {
+    cache.clear();
+    clear_tmp(&get_data_dir()?.join("tmp"), &cache_dir()?)
 }
@@ -3,12 +3,15 @@ use tauri::{Emitter, Window};
 use serde_json::json;
 use std::path::PathBuf;
 
-use crate::ffmpeg::parser::parse_ffmpeg;
-use crate::ffmpeg::version::get_ffmpeg_version;
+use crate::ffmpeg::command::FfmpegCommand;
+use crate::ffmpeg::filtergraph::{check_command, CommandIssue};
+use crate::ffmpeg::parser::{parse_ffmpeg, reparse_failed};
+use crate::ffmpeg::version::{get_ffmpeg_version, parse_build};
 
 use crate::utils::filesystem::{get_data_dir, load_nodes, save_nodes};
+use crate::workflow::compat::{check_graph, diff_catalogs, CatalogDiff, MissingCapability};
 use crate::workflow::manager::get_workflow_list;
-use crate::workflow::types::{Node, Response, WorkflowStructure};
+use crate::workflow::types::{Catalog, CatalogReport, Response, WorkflowStructure};
 use crate::{log_error, to_user_message, FFStudioError, Result};
 
 pub fn add_workflow(
@@ -16,7 +19,7 @@ pub fn add_workflow(
     path: String,
     env: String,
     desc: String,
-    nodes: &Vec<Node>,
+    catalog: &Catalog,
     version_data: &[String],
 ) -> Result<()> {
     if name.trim().is_empty() {
@@ -35,7 +38,7 @@ pub fn add_workflow(
     }
 
     // Save nodes to cache
-    let _ = save_nodes(&path, nodes);
+    let _ = save_nodes(&path, &env, catalog);
 
     let data_struct = WorkflowStructure {
         name: name.clone(),
@@ -43,7 +46,9 @@ pub fn add_workflow(
         env,
         desc,
         version: version_data.to_owned(),
+        build: Some(parse_build(version_data)),
         graph: "".to_string(),
+        template: "".to_string(),
     };
 
     let json_data = json!(data_struct).to_string();
@@ -55,8 +60,43 @@ pub fn add_workflow(
     Ok(())
 }
 
+/// Cached catalog for `ffmpeg_path`, parsed and cached on a miss. Categories that failed
+/// are cached as failed: the cache belongs to one build of the binary, so they are only
+/// parsed again by [`refresh_catalog`] or once the binary changes.
+fn load_or_parse_catalog(ffmpeg_path: &str, env: &str) -> Result<Catalog> {
+    match load_nodes(ffmpeg_path, env) {
+        Ok(catalog) => Ok(catalog),
+        Err(_) => {
+            log::info!("Cache miss for FFmpeg path '{ffmpeg_path}', parsing FFmpeg");
+            let catalog = parse_ffmpeg(ffmpeg_path, env)?;
+            let _ = save_nodes(ffmpeg_path, env, &catalog);
+            Ok(catalog)
+        }
+    }
+}
+
+/// [`load_or_parse_catalog`], parsing the categories that failed last time again, e.g.
+/// after a timeout or a killed process.
+fn refresh_catalog(ffmpeg_path: &str, env: &str) -> Result<Catalog> {
+    match load_nodes(ffmpeg_path, env) {
+        Ok(mut catalog) => {
+            if !catalog.report.is_complete() {
+                log::info!("Parsing the failed categories of '{ffmpeg_path}' again");
+                reparse_failed(ffmpeg_path, env, &mut catalog);
+                let _ = save_nodes(ffmpeg_path, env, &catalog);
+            }
+            Ok(catalog)
+        }
+        Err(_) => load_or_parse_catalog(ffmpeg_path, env),
+    }
+}
+
 #[tauri::command]
-pub async fn get_nodes_request(window: Window, ffmpeg_path: String) -> Result<()> {
+pub async fn get_nodes_request(
+    window: Window,
+    ffmpeg_path: String,
+    env: Option<String>,
+) -> Result<()> {
     if ffmpeg_path.trim().is_empty() {
         let error_msg = "FFmpeg path cannot be empty";
         log_error(&FFStudioError::workflow(error_msg), "get_nodes_request");
@@ -64,14 +104,15 @@ pub async fn get_nodes_request(window: Window, ffmpeg_path: String) -> Result<()
         return Err(FFStudioError::workflow(error_msg));
     }
 
-    match load_nodes(&ffmpeg_path) {
-        Ok(nodes) => {
+    let env = env.unwrap_or_default();
+    match load_or_parse_catalog(&ffmpeg_path, &env) {
+        Ok(catalog) => {
             log::info!(
                 "Successfully loaded {} nodes for FFmpeg path: {}",
-                nodes.len(),
+                catalog.nodes.len(),
                 ffmpeg_path
             );
-            let _ = window.emit("get_nodes_listener", nodes);
+            let _ = window.emit("get_nodes_listener", catalog.nodes);
             Ok(())
         }
         Err(e) => {
@@ -83,8 +124,10 @@ pub async fn get_nodes_request(window: Window, ffmpeg_path: String) -> Result<()
     }
 }
 
+/// Loads a workflow with its catalog. With `refresh` the catalog categories that failed
+/// to parse are tried again.
 #[tauri::command]
-pub async fn get_workflow(window: Window, name: String) -> Result<()> {
+pub async fn get_workflow(window: Window, name: String, refresh: Option<bool>) -> Result<()> {
     if name.trim().is_empty() {
         let error_msg = "Workflow name cannot be empty";
         log_error(&FFStudioError::workflow(error_msg), "get_workflow");
@@ -95,6 +138,7 @@ pub async fn get_workflow(window: Window, name: String) -> Result<()> {
                 build: "".to_string(),
                 version: "".to_string(),
                 nodes: Vec::new(),
+                report: CatalogReport::default(),
                 env: "".to_string(),
                 path: "".to_string(),
                 desc: "".to_string(),
@@ -119,6 +163,7 @@ pub async fn get_workflow(window: Window, name: String) -> Result<()> {
                     build: "".to_string(),
                     version: "".to_string(),
                     nodes: Vec::new(),
+                    report: CatalogReport::default(),
                     env: "".to_string(),
                     path: "".to_string(),
                     desc: "".to_string(),
@@ -129,51 +174,41 @@ pub async fn get_workflow(window: Window, name: String) -> Result<()> {
         }
     };
 
-    // Try to load cached nodes first
-    let nodes = match load_nodes(&workflow.path) {
-        Ok(nodes) => {
+    let catalog = if refresh.unwrap_or(false) {
+        refresh_catalog(&workflow.path, &workflow.env)
+    } else {
+        load_or_parse_catalog(&workflow.path, &workflow.env)
+    };
+    let catalog = match catalog {
+        Ok(catalog) => {
             log::info!(
-                "Loaded {} cached nodes for workflow '{}'",
-                nodes.len(),
+                "Loaded {} nodes for workflow '{}'",
+                catalog.nodes.len(),
                 name
             );
-            nodes
+            catalog
         }
-        Err(_) => {
-            log::info!("Cache miss for workflow '{name}', parsing FFmpeg");
-            match parse_ffmpeg(&workflow.path, &workflow.env) {
-                Ok(nodes) => {
-                    let _ = save_nodes(&workflow.path, &nodes);
-                    log::info!(
-                        "Parsed and cached {} nodes for workflow '{}'",
-                        nodes.len(),
-                        name
-                    );
-                    nodes
-                }
-                Err(e) => {
-                    let error = FFStudioError::from(e);
-                    log_error(&error, "parsing FFmpeg for workflow");
-                    let _ = window.emit(
-                        "get_workflow_listener",
-                        Response {
-                            message: format!("Failed to parse FFmpeg: {}", to_user_message(&error)),
-                            build: "".to_string(),
-                            version: "".to_string(),
-                            nodes: Vec::new(),
-                            env: "".to_string(),
-                            path: "".to_string(),
-                            desc: "".to_string(),
-                            graph: "".to_string(),
-                        },
-                    );
-                    return Err(error);
-                }
-            }
+        Err(error) => {
+            log_error(&error, "parsing FFmpeg for workflow");
+            let _ = window.emit(
+                "get_workflow_listener",
+                Response {
+                    message: format!("Failed to parse FFmpeg: {}", to_user_message(&error)),
+                    build: "".to_string(),
+                    version: "".to_string(),
+                    nodes: Vec::new(),
+                    report: CatalogReport::default(),
+                    env: "".to_string(),
+                    path: "".to_string(),
+                    desc: "".to_string(),
+                    graph: "".to_string(),
+                },
+            );
+            return Err(error);
         }
     };
 
-    let response_message = if nodes.is_empty() {
+    let response_message = if catalog.nodes.is_empty() {
         "No FFmpeg nodes found. Try a different FFmpeg path."
     } else {
         "OK"
@@ -185,7 +220,8 @@ pub async fn get_workflow(window: Window, name: String) -> Result<()> {
             message: response_message.to_string(),
             build: workflow.version.get(1).cloned().unwrap_or_default(),
             version: workflow.version.first().cloned().unwrap_or_default(),
-            nodes,
+            nodes: catalog.nodes,
+            report: catalog.report,
             graph: workflow.graph.clone(),
             env: workflow.env.clone(),
             path: workflow.path.clone(),
@@ -215,6 +251,7 @@ pub async fn create_workflow(
                 build: "".to_string(),
                 version: "".to_string(),
                 nodes: Vec::new(),
+                report: CatalogReport::default(),
                 path,
                 desc,
                 graph: "".to_string(),
@@ -234,6 +271,7 @@ pub async fn create_workflow(
                 build: "".to_string(),
                 version: "".to_string(),
                 nodes: Vec::new(),
+                report: CatalogReport::default(),
                 path,
                 desc,
                 graph: "".to_string(),
@@ -259,6 +297,7 @@ pub async fn create_workflow(
                     build: "".to_string(),
                     version: "".to_string(),
                     nodes: Vec::new(),
+                    report: CatalogReport::default(),
                     path,
                     desc,
                     graph: "".to_string(),
@@ -269,47 +308,32 @@ pub async fn create_workflow(
         }
     };
 
-    // Try to load cached nodes first
-    let nodes = match load_nodes(&path) {
-        Ok(nodes) => {
+    let catalog = match load_or_parse_catalog(&path, &env) {
+        Ok(catalog) => {
             log::info!(
-                "Loaded {} cached nodes for workflow '{}'",
-                nodes.len(),
+                "Loaded {} nodes for workflow '{}'",
+                catalog.nodes.len(),
                 name
             );
-            nodes
+            catalog
         }
-        Err(_) => {
-            log::info!("Cache miss for workflow '{name}', parsing FFmpeg");
-            match parse_ffmpeg(&path, &env) {
-                Ok(nodes) => {
-                    let _ = save_nodes(&path, &nodes);
-                    log::info!(
-                        "Parsed and cached {} nodes for workflow '{}'",
-                        nodes.len(),
-                        name
-                    );
-                    nodes
-                }
-                Err(e) => {
-                    let error = FFStudioError::from(e);
-                    log_error(&error, "parsing FFmpeg");
-                    let _ = window.emit(
-                        "create_workflow_listener",
-                        Response {
-                            message: format!("Failed to parse FFmpeg: {}", to_user_message(&error)),
-                            build: "".to_string(),
-                            version: "".to_string(),
-                            nodes: Vec::new(),
-                            path,
-                            desc,
-                            graph: "".to_string(),
-                            env,
-                        },
-                    );
-                    return Err(error);
-                }
-            }
+        Err(error) => {
+            log_error(&error, "parsing FFmpeg");
+            let _ = window.emit(
+                "create_workflow_listener",
+                Response {
+                    message: format!("Failed to parse FFmpeg: {}", to_user_message(&error)),
+                    build: "".to_string(),
+                    version: "".to_string(),
+                    nodes: Vec::new(),
+                    report: CatalogReport::default(),
+                    path,
+                    desc,
+                    graph: "".to_string(),
+                    env,
+                },
+            );
+            return Err(error);
         }
     };
 
@@ -319,7 +343,7 @@ pub async fn create_workflow(
         path.clone(),
         env.clone(),
         desc.clone(),
-        &nodes,
+        &catalog,
         &version_data,
     ) {
         log_error(&e, "saving workflow");
@@ -330,6 +354,7 @@ pub async fn create_workflow(
                 build: "".to_string(),
                 version: "".to_string(),
                 nodes: Vec::new(),
+                report: CatalogReport::default(),
                 path,
                 desc,
                 graph: "".to_string(),
@@ -339,7 +364,7 @@ pub async fn create_workflow(
         return Err(e);
     }
 
-    let response_message = if nodes.is_empty() {
+    let response_message = if catalog.nodes.is_empty() {
         "Workflow created but no FFmpeg nodes found. Try a different FFmpeg path."
     } else {
         "Workflow created successfully"
@@ -351,7 +376,8 @@ pub async fn create_workflow(
             message: response_message.to_string(),
             build: version_data.get(1).cloned().unwrap_or_default(),
             version: version_data.first().cloned().unwrap_or_default(),
-            nodes,
+            nodes: catalog.nodes,
+            report: catalog.report,
             path,
             desc,
             graph: "".to_string(),
@@ -418,6 +444,16 @@ pub async fn edit_workflow(
     let mut workflow: WorkflowStructure = serde_json::from_str(&data)
         .map_err(|e| FFStudioError::json(format!("Failed to parse workflow '{name}': {e}")))?;
 
+    if workflow.path != path || workflow.env != env {
+        match get_ffmpeg_version(&path, &env) {
+            Ok(version) => {
+                workflow.build = Some(parse_build(&version));
+                workflow.version = version;
+            }
+            Err(e) => log::warn!("Failed to refresh FFmpeg version for '{name}': {e}"),
+        }
+    }
+
     workflow.path = path;
     workflow.env = env;
     workflow.desc = desc;
@@ -432,7 +468,12 @@ pub async fn edit_workflow(
 }
 
 #[tauri::command]
-pub async fn save_graph(window: Window, name: String, graph: String) -> Result<()> {
+pub async fn save_graph(
+    window: Window,
+    name: String,
+    graph: String,
+    template: Option<String>,
+) -> Result<()> {
     if name.trim().is_empty() {
         return Err(FFStudioError::workflow("Workflow name cannot be empty"));
     }
@@ -457,6 +498,9 @@ pub async fn save_graph(window: Window, name: String, graph: String) -> Result<(
         .map_err(|e| FFStudioError::json(format!("Failed to parse workflow '{name}': {e}")))?;
 
     workflow.graph = graph;
+    if let Some(template) = template {
+        workflow.template = template;
+    }
 
     let json_data = json!(workflow).to_string();
     std::fs::write(&wf_full_path, json_data).map_err(|e| {
@@ -467,3 +511,57 @@ pub async fn save_graph(window: Window, name: String, graph: String) -> Result<(
     log::info!("Successfully saved graph for workflow: {name}");
     Ok(())
 }
+
+/// Names of workflows whose ffmpeg build matches `query`, e.g. "libfdk_aac" or "nonfree".
+#[tauri::command]
+pub async fn find_workflows_by_build(query: String) -> Result<Vec<String>> {
+    Ok(get_workflow_list()
+        .into_iter()
+        .filter(|wf| wf.ffmpeg_build().matches(&query))
+        .map(|wf| wf.name)
+        .collect())
+}
+
+/// Filters, codecs, formats and options added, removed or changed between two binaries.
+#[tauri::command]
+pub async fn diff_ffmpeg_capabilities(
+    from_path: String,
+    from_env: Option<String>,
+    to_path: String,
+    to_env: Option<String>,
+) -> Result<CatalogDiff> {
+    let from = load_or_parse_catalog(&from_path, &from_env.unwrap_or_default())?;
+    let to = load_or_parse_catalog(&to_path, &to_env.unwrap_or_default())?;
+    Ok(diff_catalogs(&from.nodes, &to.nodes))
+}
+
+/// Nodes and options of workflow `name` that `ffmpeg_path` doesn't provide.
+/// `env` defaults to the workflow's own environment.
+#[tauri::command]
+pub async fn check_workflow_compat(
+    name: String,
+    ffmpeg_path: String,
+    env: Option<String>,
+) -> Result<Vec<MissingCapability>> {
+    let workflow = get_workflow_list()
+        .into_iter()
+        .find(|wf| wf.name == name)
+        .ok_or_else(|| FFStudioError::workflow(format!("Workflow '{name}' not found")))?;
+
+    let env = env.unwrap_or_else(|| workflow.env.clone());
+    let catalog = load_or_parse_catalog(&ffmpeg_path, &env)?;
+    check_graph(&workflow.graph, &catalog.nodes)
+}
+
+/// Parses the filtergraphs of `command` and checks them and the encoder formats against
+/// the catalog of the ffmpeg binary it runs, so mistakes show up before the job is queued.
+#[tauri::command]
+pub async fn validate_command(command: String, env: Option<String>) -> Result<Vec<CommandIssue>> {
+    let cmd = FfmpegCommand::parse(&command).map_err(|e| FFStudioError::parse(e.to_string()))?;
+    let mut catalog = load_or_parse_catalog(&cmd.program, &env.unwrap_or_default())?;
+    // A partial filter list would report filters that exist as unknown
+    if catalog.report.failed().any(|c| c.category == "filters") {
+        catalog.nodes.retain(|n| n.pcategory != "filters");
+    }
+    Ok(check_command(&cmd, &catalog.nodes))
+}
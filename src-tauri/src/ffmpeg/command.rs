@@ -0,0 +1,999 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Options that never take a value: ffmpeg's boolean options and the ones that print
+/// something and exit. Each boolean also has a `-no` form, see [`is_flag`].
+const FLAG_OPTIONS: &[&str] = &[
+    // Global
+    "y",
+    "n",
+    "hide_banner",
+    "stats",
+    "stdin",
+    "benchmark",
+    "benchmark_all",
+    "report",
+    "ignore_unknown",
+    "copy_unknown",
+    "recast_media",
+    "debug_ts",
+    "xerror",
+    "dump",
+    "hex",
+    "vstats",
+    "qphist",
+    "print_graphs",
+    // Per input or output
+    "an",
+    "vn",
+    "sn",
+    "dn",
+    "shortest",
+    "re",
+    "copyts",
+    "start_at_zero",
+    "accurate_seek",
+    "seek_timestamp",
+    "find_stream_info",
+    "autorotate",
+    "autoscale",
+    "display_hflip",
+    "display_vflip",
+    "bitexact",
+    "copyinkf",
+    "fix_sub_duration",
+    "fix_sub_duration_heartbeat",
+    "force_fps",
+    "psnr",
+    // Print and exit
+    "L",
+    "version",
+    "buildconf",
+    "formats",
+    "muxers",
+    "demuxers",
+    "devices",
+    "codecs",
+    "decoders",
+    "encoders",
+    "bsfs",
+    "protocols",
+    "filters",
+    "pix_fmts",
+    "layouts",
+    "sample_fmts",
+    "dispositions",
+    "colors",
+    "hwaccels",
+];
+
+/// Whether `name` (without dash or stream specifier) never takes a value, including the
+/// `-no` form ffmpeg accepts for every boolean, e.g. `-nostdin` or `-noautorotate`.
+fn is_flag(name: &str) -> bool {
+    FLAG_OPTIONS.contains(&name)
+        || name
+            .strip_prefix("no")
+            .is_some_and(|rest| FLAG_OPTIONS.contains(&rest))
+}
+
+/// Options that apply to the whole run rather than to the next input or output.
+const GLOBAL_OPTIONS: &[&str] = &[
+    "y",
+    "n",
+    "hide_banner",
+    "loglevel",
+    "recast_media",
+    "print_graphs",
+    "print_graphs_file",
+    "v",
+    "report",
+    "stats",
+    "nostats",
+    "stats_period",
+    "progress",
+    "nostdin",
+    "stdin",
+    "benchmark",
+    "benchmark_all",
+    "timelimit",
+    "dump",
+    "hex",
+    "ignore_unknown",
+    "copy_unknown",
+    "debug_ts",
+    "xerror",
+    "abort_on",
+    "max_error_rate",
+    "filter_threads",
+    "filter_complex_threads",
+    "filter_complex_script",
+    "init_hw_device",
+    "filter_hw_device",
+    "sdp_file",
+    "vstats",
+    "vstats_file",
+    "vstats_version",
+    "cpuflags",
+    "cpucount",
+    "max_alloc",
+];
+
+/// Whether an option that isn't in [`FLAG_OPTIONS`] takes `next` as its value.
+///
+/// Private codec, format and filter options can't all be listed, so an option followed by
+/// another option, or by nothing, is taken as a boolean. Values that only look like
+/// options, `-` or negative numbers, are still taken.
+fn takes_value(base: &str, next: Option<&String>) -> bool {
+    !is_flag(base) && next.is_some_and(|next| !is_option(next))
+}
+
+/// The part of a command a global option or filtergraph was written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Part {
+    Input(usize),
+    Output(usize),
+    Trailing,
+}
+
+/// Where a global option or filtergraph was written when that isn't where
+/// [`FfmpegCommand::to_args`] would put it on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub part: Part,
+    /// Index of the option of `part` it came before, the number of options if it came
+    /// right before `-i` or the output URL
+    pub option: usize,
+    /// Among the options anchored at the same place
+    pub order: usize,
+}
+
+/// One `-flag [value]` pair. `flag` keeps the dash and any stream specifier, e.g. `-c:v`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CmdOption {
+    pub flag: String,
+    pub value: Option<String>,
+    /// Only set on parsed global options and filtergraphs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<Anchor>,
+}
+
+impl CmdOption {
+    pub fn new(flag: &str, value: Option<&str>) -> Self {
+        Self {
+            flag: flag.to_string(),
+            value: value.map(str::to_string),
+            anchor: None,
+        }
+    }
+
+    /// The flag without the dash and stream specifier, `-c:v` -> `c`.
+    pub fn name(&self) -> &str {
+        let name = self.flag.trim_start_matches('-');
+        name.split(':').next().unwrap_or(name)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CmdInput {
+    pub options: Vec<CmdOption>,
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CmdOutput {
+    pub options: Vec<CmdOption>,
+    pub url: String,
+}
+
+impl CmdOutput {
+    /// Values of every `-map` given to this output.
+    pub fn maps(&self) -> impl Iterator<Item = &str> {
+        self.options
+            .iter()
+            .filter(|o| o.name() == "map")
+            .filter_map(|o| o.value.as_deref())
+    }
+}
+
+/// An ffmpeg invocation split into global options, inputs, the complex filtergraph and
+/// outputs.
+///
+/// Parsed global options and filtergraphs remember where they were written, so a parsed
+/// command serializes to the arguments it was parsed from. Ones added later go to their
+/// canonical place: globals first, graphs after the inputs. Inputs are always written
+/// before outputs, as ffmpeg opens them all first anyway.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FfmpegCommand {
+    pub program: String,
+    pub globals: Vec<CmdOption>,
+    pub inputs: Vec<CmdInput>,
+    /// Every `-filter_complex`/`-lavfi` graph, in order, with the flag it was given with
+    pub filter_complex: Vec<CmdOption>,
+    pub outputs: Vec<CmdOutput>,
+    /// Options after the last output. ffmpeg ignores them, they are only kept so
+    /// nothing is lost.
+    pub trailing: Vec<CmdOption>,
+}
+
+impl FfmpegCommand {
+    /// Parses a full command line, program included.
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut args = split_command_line(line)?.into_iter();
+        let program = args.next().ok_or_else(|| anyhow!("Empty command"))?;
+        Self::from_args(program, args)
+    }
+
+    /// Parses `args` for a known `program`, which may contain spaces.
+    pub fn parse_args(program: &str, args: &str) -> Result<Self> {
+        Self::from_args(program.to_string(), split_command_line(args)?)
+    }
+
+    pub fn from_args(program: String, args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cmd = FfmpegCommand {
+            program,
+            ..Self::default()
+        };
+        let mut pending: Vec<CmdOption> = Vec::new();
+        // Globals and graphs written among `pending`: (is graph, index, pending length)
+        let mut unplaced: Vec<(bool, usize, usize)> = Vec::new();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            if !is_option(&arg) {
+                cmd.place(&mut unplaced, Part::Output(cmd.outputs.len()));
+                cmd.outputs.push(CmdOutput {
+                    options: std::mem::take(&mut pending),
+                    url: arg,
+                });
+                continue;
+            }
+
+            let name = arg.trim_start_matches('-');
+            let base = name.split(':').next().unwrap_or(name);
+            match name {
+                "i" => {
+                    let url = args.next().ok_or_else(|| anyhow!("Missing value for -i"))?;
+                    cmd.place(&mut unplaced, Part::Input(cmd.inputs.len()));
+                    cmd.inputs.push(CmdInput {
+                        options: std::mem::take(&mut pending),
+                        url,
+                    });
+                }
+                "filter_complex" | "lavfi" => {
+                    let graph = args
+                        .next()
+                        .ok_or_else(|| anyhow!("Missing value for {arg}"))?;
+                    unplaced.push((true, cmd.filter_complex.len(), pending.len()));
+                    cmd.filter_complex.push(CmdOption {
+                        flag: arg,
+                        value: Some(graph),
+                        anchor: None,
+                    });
+                }
+                _ => {
+                    let negated = base.strip_prefix("no").filter(|_| is_flag(base));
+                    let is_global = GLOBAL_OPTIONS.contains(&base)
+                        || negated.is_some_and(|rest| GLOBAL_OPTIONS.contains(&rest));
+                    let option = CmdOption {
+                        value: if takes_value(base, args.peek()) {
+                            args.next()
+                        } else {
+                            None
+                        },
+                        flag: arg,
+                        anchor: None,
+                    };
+                    let leading = cmd.inputs.is_empty()
+                        && cmd.outputs.is_empty()
+                        && cmd.filter_complex.is_empty()
+                        && pending.is_empty();
+                    if !is_global {
+                        pending.push(option);
+                    } else if leading {
+                        cmd.globals.push(option);
+                    } else {
+                        unplaced.push((false, cmd.globals.len(), pending.len()));
+                        cmd.globals.push(option);
+                    }
+                }
+            }
+        }
+
+        cmd.place(&mut unplaced, Part::Trailing);
+        cmd.trailing = pending;
+        Ok(cmd)
+    }
+
+    /// Anchors the globals and graphs in `unplaced` to `part`, which the pending options
+    /// turned out to belong to.
+    fn place(&mut self, unplaced: &mut Vec<(bool, usize, usize)>, part: Part) {
+        let mut order = 0;
+        let mut previous = None;
+        for (is_graph, index, option) in unplaced.drain(..) {
+            order = if previous == Some(option) {
+                order + 1
+            } else {
+                0
+            };
+            previous = Some(option);
+            let target = if is_graph {
+                &mut self.filter_complex[index]
+            } else {
+                &mut self.globals[index]
+            };
+            target.anchor = Some(Anchor {
+                part,
+                option,
+                order,
+            });
+        }
+    }
+
+    /// Arguments without the program. Globals and graphs go back where they were parsed
+    /// from, or to their canonical place if that part of the command is gone.
+    pub fn to_args(&self) -> Vec<String> {
+        // (section, part index, option index, anchored, order among anchored) -> arguments
+        type Key = (u8, usize, usize, bool, usize);
+        let mut items: Vec<(Key, Vec<String>)> = Vec::new();
+        let option_args = |o: &CmdOption| std::iter::once(o.flag.clone()).chain(o.value.clone());
+        let mut push_part = |section: u8, index: usize, options: &[CmdOption], url: Vec<String>| {
+            for (i, o) in options.iter().enumerate() {
+                items.push(((section, index, i, false, 0), option_args(o).collect()));
+            }
+            items.push(((section, index, options.len(), false, 0), url));
+        };
+
+        for (index, input) in self.inputs.iter().enumerate() {
+            push_part(
+                1,
+                index,
+                &input.options,
+                vec!["-i".to_string(), input.url.clone()],
+            );
+        }
+        for (index, output) in self.outputs.iter().enumerate() {
+            push_part(3, index, &output.options, vec![output.url.clone()]);
+        }
+        push_part(4, 0, &self.trailing, Vec::new());
+
+        // Canonical place: globals first, graphs after the inputs
+        let place = |anchor: &Option<Anchor>, canonical: Key| -> Key {
+            let Some(anchor) = anchor else {
+                return canonical;
+            };
+            let (section, index, options) = match anchor.part {
+                Part::Input(i) => match self.inputs.get(i) {
+                    Some(input) => (1, i, input.options.len()),
+                    None => return canonical,
+                },
+                Part::Output(i) => match self.outputs.get(i) {
+                    Some(output) => (3, i, output.options.len()),
+                    None => (4, 0, self.trailing.len()),
+                },
+                Part::Trailing => (4, 0, self.trailing.len()),
+            };
+            (
+                section,
+                index,
+                anchor.option.min(options),
+                true,
+                anchor.order,
+            )
+        };
+        for (i, o) in self.globals.iter().enumerate() {
+            let key = place(&o.anchor, (0, 0, 0, true, i));
+            items.push((key, option_args(o).collect()));
+        }
+        for (i, o) in self.filter_complex.iter().enumerate() {
+            let key = place(&o.anchor, (2, 0, 0, true, i));
+            items.push((key, option_args(o).collect()));
+        }
+
+        // Anchored items sort before the option they were written before
+        items.sort_by_key(|(key, _)| (key.0, key.1, key.2, !key.3, key.4));
+        items.into_iter().flat_map(|(_, args)| args).collect()
+    }
+
+    /// Text of every `-filter_complex`/`-lavfi` graph.
+    pub fn graphs(&self) -> impl Iterator<Item = &str> {
+        self.filter_complex
+            .iter()
+            .filter_map(|o| o.value.as_deref())
+    }
+
+    /// Sets a global option, replacing the value if the flag is already present.
+    pub fn set_global(&mut self, flag: &str, value: Option<&str>) {
+        if let Some(existing) = self.globals.iter_mut().find(|o| o.flag == flag) {
+            existing.value = value.map(str::to_string);
+        } else {
+            self.globals.push(CmdOption::new(flag, value));
+        }
+    }
+}
+
+impl fmt::Display for FfmpegCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = std::iter::once(&self.program)
+            .chain(self.to_args().iter())
+            .map(|a| quote_arg(a))
+            .collect::<Vec<_>>()
+            .join(" ");
+        f.write_str(&line)
+    }
+}
+
+/// Parses an ffmpeg duration, `[-][HH:]MM:SS[.m...]` or `[-]S+[.m...][s|ms|us]`, into
+/// seconds.
+pub fn parse_time(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let seconds = if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+        parts.iter().try_fold(0.0, |acc, part| {
+            part.parse::<f64>().ok().map(|v| acc * 60.0 + v)
+        })?
+    } else if let Some(ms) = value.strip_suffix("ms") {
+        ms.parse::<f64>().ok()? / 1e3
+    } else if let Some(us) = value.strip_suffix("us") {
+        us.parse::<f64>().ok()? / 1e6
+    } else {
+        value
+            .strip_suffix('s')
+            .unwrap_or(value)
+            .parse::<f64>()
+            .ok()?
+    };
+    seconds
+        .is_finite()
+        .then_some(if negative { -seconds } else { seconds })
+}
+
+/// Seconds as an ffmpeg duration with microsecond precision, `1.500000` -> `1.5`.
+pub fn format_time(seconds: f64) -> String {
+    let text = format!("{seconds:.6}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `-flag`, but not `-` (stdin/stdout) or a negative number such as `-0.5`.
+fn is_option(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-')
+        && chars
+            .next()
+            .is_some_and(|c| !c.is_ascii_digit() && c != '.')
+}
+
+/// Splits a command line into arguments.
+///
+/// Whitespace separates arguments unless quoted. Single quotes are literal. Inside and
+/// outside double quotes backslashes are literal unless they precede a `"`, as on Windows,
+/// so `"C:\Videos\in.mp4"` and `scale=w=iw\,h=ih` survive untouched.
+pub fn split_command_line(line: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut in_double = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut count = 1;
+                while chars.peek() == Some(&'\\') {
+                    chars.next();
+                    count += 1;
+                }
+                in_arg = true;
+                if chars.peek() == Some(&'"') {
+                    current.extend(std::iter::repeat('\\').take(count / 2));
+                    if count % 2 == 1 {
+                        chars.next();
+                        current.push('"');
+                    }
+                } else {
+                    current.extend(std::iter::repeat('\\').take(count));
+                }
+            }
+            '"' => {
+                in_double = !in_double;
+                in_arg = true;
+            }
+            '\'' if !in_double => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => bail!("Unterminated single quote in: {line}"),
+                    }
+                }
+            }
+            c if c.is_whitespace() && !in_double => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_double {
+        bail!("Unterminated double quote in: {line}");
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Quotes `arg` so [`split_command_line`] gives it back unchanged.
+pub fn quote_arg(arg: &str) -> String {
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || c == '"' || c == '\'');
+    if !needs_quotes {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat('\\').take(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            c => {
+                quoted.extend(std::iter::repeat('\\').take(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.extend(std::iter::repeat('\\').take(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Program followed by the global flags.
+    fn globals(cmd: &FfmpegCommand) -> Vec<&str> {
+        std::iter::once(cmd.program.as_str())
+            .chain(cmd.globals.iter().map(|o| o.flag.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn split_plain_and_quoted() {
+        assert_eq!(
+            split_command_line(r#"ffmpeg -i "my file.mp4" -vf 'scale=1280:-2' out.mp4"#).unwrap(),
+            [
+                "ffmpeg",
+                "-i",
+                "my file.mp4",
+                "-vf",
+                "scale=1280:-2",
+                "out.mp4"
+            ]
+        );
+        assert_eq!(split_command_line("  a \t b\n").unwrap(), ["a", "b"]);
+        assert_eq!(
+            split_command_line(r#"-metadata title="""#).unwrap(),
+            ["-metadata", "title="]
+        );
+        assert_eq!(split_command_line(r#""""#).unwrap(), [""]);
+        assert!(split_command_line("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_keeps_backslashes() {
+        assert_eq!(
+            split_command_line(r#"-i "C:\Videos\in.mp4" -vf scale=w=iw\,h=ih C:\out\"#).unwrap(),
+            [
+                "-i",
+                r"C:\Videos\in.mp4",
+                "-vf",
+                r"scale=w=iw\,h=ih",
+                r"C:\out\"
+            ]
+        );
+        assert_eq!(split_command_line(r#"a\"b"#).unwrap(), [r#"a"b"#]);
+        assert_eq!(split_command_line(r#""a\\" b"#).unwrap(), [r"a\", "b"]);
+    }
+
+    #[test]
+    fn split_rejects_unterminated_quotes() {
+        assert!(split_command_line(r#"-i "open.mp4"#).is_err());
+        assert!(split_command_line("-vf 'scale").is_err());
+    }
+
+    #[test]
+    fn quote_only_when_needed() {
+        assert_eq!(quote_arg("out.mp4"), "out.mp4");
+        assert_eq!(quote_arg(r"C:\Videos\in.mp4"), r"C:\Videos\in.mp4");
+        assert_eq!(quote_arg(""), r#""""#);
+        assert_eq!(quote_arg("my file.mp4"), r#""my file.mp4""#);
+        assert_eq!(quote_arg(r"C:\My Videos\"), r#""C:\My Videos\\""#);
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        for arg in [
+            "",
+            " ",
+            "plain",
+            "my file.mp4",
+            r"C:\My Videos\in.mp4",
+            r"C:\My Videos\",
+            r"\\server\share\a b.mp4",
+            r#"say "hi""#,
+            r#"back\"slash"#,
+            r#"trailing\\"#,
+            "it's",
+            "drawtext=text='%{pts}':fontsize=24",
+            "multi\nline",
+            "-",
+            "pipe:1",
+        ] {
+            let line = format!("a {} b", quote_arg(arg));
+            assert_eq!(
+                split_command_line(&line).unwrap(),
+                ["a", arg, "b"],
+                "{arg:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_sorts_command_parts() {
+        let cmd = FfmpegCommand::parse(
+            "ffmpeg -ss 5 -i in.mp4 -y -i logo.png -filter_complex [0][1]overlay -c:v libx264 \
+             -an out.mp4 -f null -",
+        )
+        .unwrap();
+        assert_eq!(globals(&cmd), ["ffmpeg", "-y"]);
+        assert_eq!(cmd.inputs.len(), 2);
+        assert_eq!(cmd.inputs[0].options, [CmdOption::new("-ss", Some("5"))]);
+        assert_eq!(cmd.inputs[1].url, "logo.png");
+        assert_eq!(cmd.graphs().collect::<Vec<_>>(), ["[0][1]overlay"]);
+        assert_eq!(cmd.outputs.len(), 2);
+        assert_eq!(
+            cmd.outputs[0].options,
+            [
+                CmdOption::new("-c:v", Some("libx264")),
+                CmdOption::new("-an", None)
+            ]
+        );
+        assert_eq!(cmd.outputs[1].url, "-");
+        assert_eq!(cmd.outputs[1].options, [CmdOption::new("-f", Some("null"))]);
+    }
+
+    #[test]
+    fn parse_negative_numbers_and_pipes() {
+        let cmd =
+            FfmpegCommand::parse("ffmpeg -itsoffset -1.5 -i pipe:0 -ss -.5 -af volume=-3dB pipe:1")
+                .unwrap();
+        assert_eq!(
+            cmd.inputs[0].options,
+            [CmdOption::new("-itsoffset", Some("-1.5"))]
+        );
+        assert_eq!(cmd.inputs[0].url, "pipe:0");
+        assert_eq!(
+            cmd.outputs[0].options,
+            [
+                CmdOption::new("-ss", Some("-.5")),
+                CmdOption::new("-af", Some("volume=-3dB"))
+            ]
+        );
+        assert_eq!(cmd.outputs[0].url, "pipe:1");
+    }
+
+    #[test]
+    fn parse_flags_and_negated_flags() {
+        let cmd = FfmpegCommand::parse(
+            "ffmpeg -nostdin -hide_banner -noautorotate -i in.mp4 -nostats -shortest out.mp4",
+        )
+        .unwrap();
+        assert_eq!(
+            globals(&cmd),
+            ["ffmpeg", "-nostdin", "-hide_banner", "-nostats"]
+        );
+        assert_eq!(
+            cmd.inputs[0].options,
+            [CmdOption::new("-noautorotate", None)]
+        );
+        assert_eq!(cmd.inputs[0].url, "in.mp4");
+        assert_eq!(cmd.outputs[0].options, [CmdOption::new("-shortest", None)]);
+        assert_eq!(cmd.outputs[0].url, "out.mp4");
+    }
+
+    #[test]
+    fn parse_keeps_every_filter_graph() {
+        let cmd = FfmpegCommand::parse(
+            "ffmpeg -i a.mp4 -filter_complex [0:v]split[x][y] -lavfi [0:a]anull out.mp4",
+        )
+        .unwrap();
+        assert_eq!(
+            cmd.graphs().collect::<Vec<_>>(),
+            ["[0:v]split[x][y]", "[0:a]anull"]
+        );
+        assert_eq!(cmd.filter_complex[1].flag, "-lavfi");
+        assert!(cmd.to_args().contains(&"-lavfi".to_string()));
+    }
+
+    #[test]
+    fn parse_windows_paths() {
+        let cmd = FfmpegCommand::parse(
+            r#""C:\Program Files\ffmpeg\bin\ffmpeg.exe" -i "D:\My Videos\in.mkv" D:\out\clip.mp4"#,
+        )
+        .unwrap();
+        assert_eq!(cmd.program, r"C:\Program Files\ffmpeg\bin\ffmpeg.exe");
+        assert_eq!(cmd.inputs[0].url, r"D:\My Videos\in.mkv");
+        assert_eq!(cmd.outputs[0].url, r"D:\out\clip.mp4");
+
+        let cmd =
+            FfmpegCommand::parse_args(r"C:\Program Files\ffmpeg.exe", "-i in.mp4 out.mp4").unwrap();
+        assert_eq!(cmd.program, r"C:\Program Files\ffmpeg.exe");
+        assert_eq!(cmd.inputs[0].url, "in.mp4");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(FfmpegCommand::parse("").is_err());
+        assert!(FfmpegCommand::parse("ffmpeg -i").is_err());
+        assert!(FfmpegCommand::parse("ffmpeg -i in.mp4 -filter_complex").is_err());
+    }
+
+    #[test]
+    fn trailing_options_are_kept() {
+        let cmd = FfmpegCommand::parse("ffmpeg -i in.mp4 out.mp4 -c:v copy").unwrap();
+        assert_eq!(cmd.trailing, [CmdOption::new("-c:v", Some("copy"))]);
+        assert_eq!(cmd.to_args().last().map(String::as_str), Some("copy"));
+    }
+
+    #[test]
+    fn to_string_is_stable() {
+        for line in [
+            "ffmpeg -i in.mp4 out.mp4",
+            "ffmpeg -ss 00:01:00 -i \"my in.mp4\" -y -vf \"scale=1280:-2,fps=30\" -c:a aac out.mp4",
+            "ffmpeg -i in.mp4 -filter_complex \"[0:v]split[a][b]\" -map [a] a.mp4 -map [b] b.mp4",
+            "ffmpeg -nostdin -i pipe:0 -f s16le -ar 8000 pipe:1",
+            "ffmpeg -itsoffset -2 -i in.mp4 -metadata \"title=\" -f null -",
+            r#""C:\Program Files\ffmpeg.exe" -i "C:\My Videos\in.mp4" "C:\My Videos\out\\""#,
+            "ffmpeg -i in.mp4 -vf \"drawtext=text='it''s %{pts}'\" out.mp4 -c copy",
+        ] {
+            let cmd = FfmpegCommand::parse(line).unwrap();
+            let text = cmd.to_string();
+            let reparsed = FfmpegCommand::parse(&text).unwrap();
+            assert_eq!(reparsed, cmd, "{line}");
+            assert_eq!(reparsed.to_string(), text, "{line}");
+        }
+    }
+
+    #[test]
+    fn canonical_order_round_trips_arguments() {
+        let line =
+            "ffmpeg -y -ss 1 -i in.mp4 -filter_complex [0:v]null[v] -map [v] -c:v libx264 out.mp4";
+        let cmd = FfmpegCommand::parse(line).unwrap();
+        assert_eq!(cmd.to_string(), line);
+    }
+
+    #[test]
+    fn parse_keeps_globals_and_graphs_in_place() {
+        let line = "ffmpeg -ss 1 -y -i in.mp4 -lavfi [0:v]null[v] -map [v] -v error -c:v libx264 \
+                    out.mp4 -nostats";
+        let cmd = FfmpegCommand::parse(line).unwrap();
+        assert_eq!(globals(&cmd), ["ffmpeg", "-y", "-v", "-nostats"]);
+        assert_eq!(
+            cmd.globals.iter().map(|o| o.anchor).collect::<Vec<_>>(),
+            [
+                Some(Anchor {
+                    part: Part::Input(0),
+                    option: 1,
+                    order: 0
+                }),
+                Some(Anchor {
+                    part: Part::Output(0),
+                    option: 1,
+                    order: 0
+                }),
+                Some(Anchor {
+                    part: Part::Trailing,
+                    option: 0,
+                    order: 0
+                }),
+            ]
+        );
+        assert_eq!(
+            cmd.to_string(),
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+    }
+
+    #[test]
+    fn added_globals_and_graphs_go_to_their_canonical_place() {
+        let mut cmd = FfmpegCommand::parse("ffmpeg -i in.mp4 -y -map [v] out.mp4").unwrap();
+        cmd.set_global("-v", Some("error"));
+        cmd.filter_complex
+            .push(CmdOption::new("-filter_complex", Some("[0:v]null[v]")));
+        assert_eq!(
+            cmd.to_string(),
+            "ffmpeg -v error -i in.mp4 -filter_complex [0:v]null[v] -y -map [v] out.mp4"
+        );
+
+        // An anchor into an output that is gone falls back to the end
+        cmd.outputs.clear();
+        assert_eq!(
+            cmd.to_string(),
+            "ffmpeg -v error -i in.mp4 -filter_complex [0:v]null[v] -y"
+        );
+    }
+
+    #[test]
+    fn unknown_options_before_options_are_booleans() {
+        let cmd =
+            FfmpegCommand::parse("ffmpeg -i in.mp4 -a53cc -c:v libx264 -ss -2 out.mp4 -muxonly")
+                .unwrap();
+        assert_eq!(
+            cmd.outputs[0].options,
+            [
+                CmdOption::new("-a53cc", None),
+                CmdOption::new("-c:v", Some("libx264")),
+                CmdOption::new("-ss", Some("-2"))
+            ]
+        );
+        assert_eq!(cmd.trailing, [CmdOption::new("-muxonly", None)]);
+    }
+
+    #[test]
+    fn set_global_replaces_values() {
+        let mut cmd = FfmpegCommand::parse("ffmpeg -v info -i in.mp4 out.mp4").unwrap();
+        cmd.set_global("-v", Some("error"));
+        cmd.set_global("-y", None);
+        assert_eq!(
+            cmd.globals,
+            [
+                CmdOption::new("-v", Some("error")),
+                CmdOption::new("-y", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn option_names() {
+        assert_eq!(CmdOption::new("-c:v", Some("copy")).name(), "c");
+        assert_eq!(CmdOption::new("-b:a:0", Some("128k")).name(), "b");
+        assert_eq!(CmdOption::new("-y", None).name(), "y");
+    }
+
+    #[test]
+    fn parse_time_formats() {
+        assert_eq!(parse_time("90"), Some(90.0));
+        assert_eq!(parse_time("1.5"), Some(1.5));
+        assert_eq!(parse_time("2s"), Some(2.0));
+        assert_eq!(parse_time("250ms"), Some(0.25));
+        assert_eq!(parse_time("1500us"), Some(0.0015));
+        assert_eq!(parse_time("01:30"), Some(90.0));
+        assert_eq!(parse_time("01:02:03.5"), Some(3723.5));
+        assert_eq!(parse_time(" -00:00:01.25 "), Some(-1.25));
+        assert_eq!(parse_time("-0.5"), Some(-0.5));
+    }
+
+    #[test]
+    fn parse_time_rejects_garbage() {
+        for value in ["", "abc", "1:2:3:4", "1:xx", "5m", "inf", "NaN"] {
+            assert_eq!(parse_time(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn format_time_trims_zeros() {
+        assert_eq!(format_time(1.5), "1.5");
+        assert_eq!(format_time(2.0), "2");
+        assert_eq!(format_time(0.0000015), "0.000002");
+        assert_eq!(format_time(-3.25), "-3.25");
+        assert_eq!(format_time(3723.5), "3723.5");
+    }
+
+    #[test]
+    fn format_then_parse_time() {
+        for seconds in [0.0, 0.04, 1.5, 59.999999, 3723.5, -12.125] {
+            let text = format_time(seconds);
+            let parsed = parse_time(&text).unwrap();
+            assert!((parsed - seconds).abs() < 1e-6, "{seconds} -> {text}");
+        }
+    }
+
+    /// Arguments from a vocabulary mixing globals, graphs, flags, unknown options and
+    /// values that look like options.
+    fn arguments() -> impl Strategy<Value = Vec<String>> {
+        let token = prop::sample::select(vec![
+            "-y",
+            "-nostdin",
+            "-v",
+            "-filter_threads",
+            "-filter_complex",
+            "-lavfi",
+            "-i",
+            "-ss",
+            "-c:v",
+            "-map",
+            "-an",
+            "-noautorotate",
+            "-a53cc",
+            "in.mp4",
+            "my file.mp4",
+            r"C:\My Videos\",
+            "[0:v]null[v]",
+            "title=\"it's\"",
+            "-",
+            "-1.5",
+            "pipe:1",
+            "",
+        ]);
+        prop::collection::vec(token.prop_map(str::to_string), 0..24)
+    }
+
+    /// Whether an input is given after an output, which `to_args` moves before it.
+    fn input_after_output(args: &[String]) -> bool {
+        let mut first_output_inputs = None;
+        (0..=args.len())
+            .filter_map(|k| FfmpegCommand::from_args("ffmpeg".to_string(), args[..k].to_vec()).ok())
+            .any(|cmd| match first_output_inputs {
+                Some(inputs) => cmd.inputs.len() > inputs,
+                None => {
+                    if !cmd.outputs.is_empty() {
+                        first_output_inputs = Some(cmd.inputs.len());
+                    }
+                    false
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn parsed_arguments_serialize_unchanged(args in arguments()) {
+            prop_assume!(!input_after_output(&args));
+            if let Ok(cmd) = FfmpegCommand::from_args("ffmpeg".to_string(), args.clone()) {
+                prop_assert_eq!(cmd.to_args(), args);
+                prop_assert_eq!(FfmpegCommand::parse(&cmd.to_string()).unwrap(), cmd);
+            }
+        }
+
+        #[test]
+        fn edited_commands_parse_back(args in arguments()) {
+            if let Ok(mut cmd) = FfmpegCommand::from_args("ffmpeg".to_string(), args) {
+                cmd.set_global("-v", Some("error"));
+                cmd.globals.retain(|o| o.flag != "-y");
+                cmd.filter_complex.push(CmdOption::new("-filter_complex", Some("anullsrc")));
+                cmd.inputs.truncate(1);
+                if let Some(output) = cmd.outputs.first_mut() {
+                    output.options.insert(0, CmdOption::new("-f", Some("null")));
+                }
+
+                let args = cmd.to_args();
+                let reparsed = FfmpegCommand::from_args("ffmpeg".to_string(), args.clone()).unwrap();
+                prop_assert_eq!(reparsed.to_args(), args);
+            }
+        }
+    }
+}
@@ -0,0 +1,628 @@
+use super::command::{parse_time, CmdInput, CmdOption, CmdOutput, FfmpegCommand};
+use super::executor::{
+    measured_stream, preview_seek, seek_inputs, start_preview, PreviewOutcome, PreviewRequest,
+    PreviewSeek, PreviewTarget, TranscodeQueue,
+};
+use super::parser::{apply_env, parse_env_map};
+use super::preview_cache::PreviewCache;
+use super::version::get_ffmpeg_build;
+use crate::utils::filesystem::get_data_dir;
+use crate::utils::template;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{State, Window};
+use tokio::sync::oneshot;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// The psnr filter reports identical frames as `inf`, they are counted as this instead.
+const MAX_PSNR: f64 = 100.0;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FrameMetrics {
+    /// Zero-based, from the start of the segment
+    pub frame: u64,
+    pub psnr: Option<f64>,
+    pub ssim: Option<f64>,
+    pub vmaf: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MetricSummary {
+    fn of(values: impl Iterator<Item = f64>) -> Option<Self> {
+        let (mut count, mut sum) = (0usize, 0.0);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for value in values {
+            count += 1;
+            sum += value;
+            min = min.min(value);
+            max = max.max(value);
+        }
+        (count > 0).then(|| Self {
+            mean: sum / count as f64,
+            min,
+            max,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VariantMetrics {
+    pub frames: Vec<FrameMetrics>,
+    pub psnr: Option<MetricSummary>,
+    pub ssim: Option<MetricSummary>,
+    pub vmaf: Option<MetricSummary>,
+}
+
+impl VariantMetrics {
+    fn from_frames(frames: Vec<FrameMetrics>) -> Self {
+        let summary = |metric: fn(&FrameMetrics) -> Option<f64>| {
+            MetricSummary::of(frames.iter().filter_map(metric))
+        };
+        Self {
+            psnr: summary(|f| f.psnr),
+            ssim: summary(|f| f.ssim),
+            vmaf: summary(|f| f.vmaf),
+            frames,
+        }
+    }
+}
+
+/// Two renders of the same segment and how close each is to the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AbComparison {
+    /// Preview files to play side by side
+    pub a: String,
+    pub b: String,
+    /// The input both variants were measured against
+    pub source: String,
+    /// Whether the ffmpeg build has libvmaf
+    pub vmaf: bool,
+    pub metrics_a: VariantMetrics,
+    pub metrics_b: VariantMetrics,
+}
+
+/// `key:value` pairs of a psnr or ssim stats line.
+fn stats_fields(line: &str) -> impl Iterator<Item = (&str, &str)> {
+    line.split_whitespace().filter_map(|t| t.split_once(':'))
+}
+
+/// Reads `key` of every line of a psnr/ssim `stats_file`, by zero-based frame.
+fn parse_stats(text: &str, key: &str) -> Vec<(u64, f64)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut frame = None;
+            let mut value = None;
+            for (k, v) in stats_fields(line) {
+                if k == "n" {
+                    frame = v.parse::<u64>().ok()?.checked_sub(1);
+                } else if k == key {
+                    value = v.parse::<f64>().ok();
+                }
+            }
+            Some((frame?, value?))
+        })
+        .collect()
+}
+
+/// `n:1 mse_avg:0.47 ... psnr_avg:51.39 ...`
+pub fn parse_psnr_stats(text: &str) -> Vec<(u64, f64)> {
+    parse_stats(text, "psnr_avg")
+        .into_iter()
+        .map(|(frame, psnr)| (frame, psnr.min(MAX_PSNR)))
+        .collect()
+}
+
+/// `n:1 Y:0.993 U:0.991 V:0.990 All:0.9921 (21.01)`
+pub fn parse_ssim_stats(text: &str) -> Vec<(u64, f64)> {
+    parse_stats(text, "All")
+}
+
+/// `frames[].frameNum` and `frames[].metrics.vmaf` of a libvmaf json log.
+pub fn parse_vmaf_log(text: &str) -> Result<Vec<(u64, f64)>> {
+    let log: serde_json::Value = serde_json::from_str(text).context("Invalid VMAF log")?;
+    let frames = log["frames"].as_array().cloned().unwrap_or_default();
+    Ok(frames
+        .iter()
+        .filter_map(|f| Some((f["frameNum"].as_u64()?, f["metrics"]["vmaf"].as_f64()?)))
+        .collect())
+}
+
+/// The input of `command` a preview seeks in, which variants are measured against.
+fn source_input(command: &FfmpegCommand) -> Option<&CmdInput> {
+    command
+        .inputs
+        .iter()
+        .find(|i| preview_seek(i) == PreviewSeek::Seek)
+}
+
+/// Compares the variant on input 0 with the source on input 1. The source is scaled to the
+/// variant's size and both start at zero, so only the encoding is measured.
+fn metrics_graph(vmaf: bool) -> String {
+    let mut metrics = vec!["psnr=stats_file=psnr.log", "ssim=stats_file=ssim.log"];
+    if vmaf {
+        metrics.push("libvmaf=log_path=vmaf.json:log_fmt=json");
+    }
+    let n = metrics.len();
+    let labels = |prefix: &str| (0..n).map(|i| format!("[{prefix}{i}]")).collect::<String>();
+
+    let mut graph = format!(
+        "[0:v]settb=AVTB,setpts=PTS-STARTPTS[dist];\
+         [1:v]settb=AVTB,setpts=PTS-STARTPTS[ref];\
+         [ref][dist]scale2ref=flags=bicubic[refs][dists];\
+         [dists]split={n}{};[refs]split={n}{}",
+        labels("d"),
+        labels("r")
+    );
+    for (i, metric) in metrics.iter().enumerate() {
+        graph.push_str(&format!(";[d{i}][r{i}]{metric}"));
+    }
+    graph
+}
+
+/// The command measuring the `variant` file against `start..start+duration` of `source`.
+/// Stats files are written to the working directory.
+pub fn metrics_command(
+    program: &str,
+    variant: &Path,
+    source: &CmdInput,
+    start: f64,
+    duration: f64,
+    vmaf: bool,
+) -> Result<FfmpegCommand> {
+    let mut reference = FfmpegCommand {
+        program: program.to_string(),
+        inputs: vec![source.clone()],
+        ..FfmpegCommand::default()
+    };
+    seek_inputs(&mut reference, start, duration).map_err(|e| anyhow!(e))?;
+    let mut source = reference.inputs.remove(0);
+    // The metrics run in their own directory
+    if Path::new(&source.url).is_file() {
+        if let Ok(path) = std::path::absolute(&source.url) {
+            source.url = path.to_string_lossy().into_owned();
+        }
+    }
+
+    let mut command = FfmpegCommand {
+        program: program.to_string(),
+        inputs: vec![
+            CmdInput {
+                options: Vec::new(),
+                url: variant.to_string_lossy().into_owned(),
+            },
+            source,
+        ],
+        filter_complex: vec![CmdOption::new(
+            "-filter_complex",
+            Some(&metrics_graph(vmaf)),
+        )],
+        outputs: vec![CmdOutput {
+            options: vec![CmdOption::new("-f", Some("null"))],
+            url: "-".to_string(),
+        }],
+        ..FfmpegCommand::default()
+    };
+    command.set_global("-hide_banner", None);
+    command.set_global("-nostats", None);
+    command.set_global("-v", Some("error"));
+    Ok(command)
+}
+
+fn process(command: &FfmpegCommand, env: &HashMap<String, String>) -> Command {
+    let mut c = Command::new(&command.program);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        c.creation_flags(CREATE_NO_WINDOW);
+    }
+    c.args(command.to_args());
+    apply_env(&mut c, env);
+    c
+}
+
+/// Last lines of an ffmpeg error output.
+fn error_tail(stderr: &str) -> String {
+    let lines: Vec<&str> = stderr.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines[lines.len().saturating_sub(3)..].join("\n")
+}
+
+fn frame(frames: &mut BTreeMap<u64, FrameMetrics>, n: u64) -> &mut FrameMetrics {
+    frames.entry(n).or_insert_with(|| FrameMetrics {
+        frame: n,
+        ..FrameMetrics::default()
+    })
+}
+
+/// Runs `metrics` in `dir` and reads the stats files it leaves there.
+fn measure(metrics: &FfmpegCommand, env: &str, dir: &Path) -> Result<VariantMetrics> {
+    let measured = process(metrics, &parse_env_map(env))
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .context("Failed to start ffmpeg")?;
+    if !measured.status.success() {
+        bail!(
+            "Metrics failed: {}",
+            error_tail(&String::from_utf8_lossy(&measured.stderr))
+        );
+    }
+
+    let mut frames: BTreeMap<u64, FrameMetrics> = BTreeMap::new();
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap_or_default();
+    for (n, psnr) in parse_psnr_stats(&read("psnr.log")) {
+        frame(&mut frames, n).psnr = Some(psnr);
+    }
+    for (n, ssim) in parse_ssim_stats(&read("ssim.log")) {
+        frame(&mut frames, n).ssim = Some(ssim);
+    }
+    if dir.join("vmaf.json").is_file() {
+        for (n, vmaf) in parse_vmaf_log(&read("vmaf.json"))? {
+            frame(&mut frames, n).vmaf = Some(vmaf);
+        }
+    }
+    Ok(VariantMetrics::from_frames(frames.into_values().collect()))
+}
+
+/// A scratch directory under `tmp` for one variant's stats files.
+fn metrics_dir(name: &str) -> Result<PathBuf> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let dir = get_data_dir()?
+        .join("tmp")
+        .join(format!("compare_{}_{nanos}_{name}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// What both variants are measured against.
+struct MetricsSegment {
+    program: String,
+    source: CmdInput,
+    start: f64,
+    duration: f64,
+    vmaf: bool,
+}
+
+/// Measures the video a [`PreviewTarget::Compare`] render of a variant kept next to its
+/// `preview`.
+fn measure_variant(
+    preview: &str,
+    segment: &MetricsSegment,
+    env: &str,
+    name: &str,
+) -> Result<VariantMetrics> {
+    let variant = measured_stream(Path::new(preview));
+    if !variant.is_file() {
+        bail!("Measured stream of variant {name} is missing: {variant:?}");
+    }
+    let metrics = metrics_command(
+        &segment.program,
+        &variant,
+        &segment.source,
+        segment.start,
+        segment.duration,
+        segment.vmaf,
+    )?;
+    let dir = metrics_dir(name)?;
+    let result = measure(&metrics, env, &dir);
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        log::warn!("Failed to remove {dir:?}: {e}");
+    }
+    result
+}
+
+/// Queues the preview of one variant. The receiver is `None` when it was cached.
+fn queue_variant(
+    window: &Window,
+    queue: &TranscodeQueue,
+    cache: &PreviewCache,
+    request: PreviewRequest,
+) -> Result<(String, Option<oneshot::Receiver<PreviewOutcome>>), String> {
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    let ticket = start_preview(window, queue, cache, request, move |outcome, _| {
+        if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+            let _ = tx.send(outcome);
+        }
+    })?;
+    Ok((ticket.path, (!ticket.cached).then_some(rx)))
+}
+
+async fn wait_variant(
+    name: &str,
+    done: Option<oneshot::Receiver<PreviewOutcome>>,
+) -> Result<(), String> {
+    let Some(done) = done else {
+        return Ok(());
+    };
+    match done.await {
+        Ok(PreviewOutcome::Rendered) => Ok(()),
+        Ok(PreviewOutcome::Failed) => Err(format!("Variant {name} failed to render")),
+        Ok(PreviewOutcome::Cancelled) | Err(_) => Err(format!("Variant {name} was cancelled")),
+    }
+}
+
+/// Renders `start..end` with both commands as previews, then measures PSNR, SSIM and, if
+/// the build has libvmaf, VMAF of each against the first seekable input of `cmd_a`.
+///
+/// The previews go through the queue and the preview cache like any other. Each render
+/// also keeps its encoded video as is, and that is what gets measured, so the mp4
+/// conversion of the preview doesn't count against it. A newer comparison supersedes this
+/// one.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn render_ab_preview(
+    window: Window,
+    cmd_a: String,
+    cmd_b: String,
+    env: String,
+    start: String,
+    end: String,
+    variables: Option<HashMap<String, String>>,
+    workflow: Option<String>,
+    queue: State<'_, TranscodeQueue>,
+    cache: State<'_, PreviewCache>,
+) -> Result<AbComparison, String> {
+    let variables = variables.unwrap_or_default();
+    let cmd_a = template::render_variables(&cmd_a, &variables);
+    let cmd_b = template::render_variables(&cmd_b, &variables);
+
+    let start_secs = parse_time(&start).ok_or_else(|| format!("Invalid preview start: {start}"))?;
+    let end_secs = parse_time(&end).ok_or_else(|| format!("Invalid preview end: {end}"))?;
+    if end_secs <= start_secs {
+        return Err("Comparing needs a segment, not a single frame".to_string());
+    }
+
+    let command_a = FfmpegCommand::parse(&cmd_a).map_err(|e| e.to_string())?;
+    let source = source_input(&command_a)
+        .ok_or("Variant A has no seekable input to measure against")?
+        .clone();
+    let vmaf = get_ffmpeg_build(&command_a.program, &env)
+        .map(|build| build.enabled_libs().contains(&"libvmaf"))
+        .unwrap_or(false);
+    let segment = MetricsSegment {
+        program: command_a.program.clone(),
+        source: source.clone(),
+        start: start_secs.max(0.0),
+        duration: end_secs - start_secs,
+        vmaf,
+    };
+
+    let request = |name: &str, cmd: &str| PreviewRequest {
+        cmd: cmd.to_string(),
+        env: env.clone(),
+        desc: serde_json::json!({
+            "tag": format!("compare {name}"),
+            "cmd": cmd,
+            "workflow": workflow.clone().unwrap_or_default(),
+        })
+        .to_string(),
+        start: start.clone(),
+        end: Some(end.clone()),
+        accurate: false,
+        channel: Some(format!("compare-{}", name.to_lowercase())),
+        target: PreviewTarget::Compare,
+    };
+    let (path_a, done_a) = queue_variant(&window, &queue, &cache, request("A", &cmd_a))?;
+    let (path_b, done_b) = queue_variant(&window, &queue, &cache, request("B", &cmd_b))?;
+    wait_variant("A", done_a).await?;
+    wait_variant("B", done_b).await?;
+
+    let (preview_a, preview_b) = (path_a.clone(), path_b.clone());
+    let (metrics_a, metrics_b) = tokio::task::spawn_blocking(move || {
+        let a = measure_variant(&preview_a, &segment, &env, "a")?;
+        let b = measure_variant(&preview_b, &segment, &env, "b")?;
+        anyhow::Ok((a, b))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    Ok(AbComparison {
+        a: path_a,
+        b: path_b,
+        source: source.url,
+        vmaf,
+        metrics_a,
+        metrics_b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::filtergraph::{parse_filtergraph, Filter};
+
+    fn frame(psnr: Option<f64>, ssim: Option<f64>) -> FrameMetrics {
+        FrameMetrics {
+            psnr,
+            ssim,
+            ..FrameMetrics::default()
+        }
+    }
+
+    #[test]
+    fn metrics_graph_pairs_every_label() {
+        for (vmaf, metrics) in [
+            (false, vec!["psnr", "ssim"]),
+            (true, vec!["psnr", "ssim", "libvmaf"]),
+        ] {
+            let graph = parse_filtergraph(&metrics_graph(vmaf)).unwrap();
+            let mut produced: Vec<&str> = Vec::new();
+            let mut consumed: Vec<&str> = Vec::new();
+            for filter in graph.filters() {
+                produced.extend(filter.outputs.iter().map(|l| l.name.as_str()));
+                consumed.extend(filter.inputs.iter().map(|l| l.name.as_str()));
+            }
+            consumed.retain(|l| !["0:v", "1:v"].contains(l));
+            produced.sort_unstable();
+            consumed.sort_unstable();
+            assert_eq!(produced, consumed, "vmaf: {vmaf}");
+
+            // The metrics end the graph, distorted first and reference second
+            let sinks: Vec<&Filter> = graph
+                .chains
+                .iter()
+                .filter_map(|chain| chain.last())
+                .filter(|f| f.outputs.is_empty())
+                .collect();
+            let names: Vec<&str> = sinks.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(names, metrics);
+            for (i, sink) in sinks.iter().enumerate() {
+                let inputs: Vec<&str> = sink.inputs.iter().map(|l| l.name.as_str()).collect();
+                assert_eq!(inputs, [format!("d{i}"), format!("r{i}")]);
+            }
+            let split = format!("split={}", metrics.len());
+            assert_eq!(metrics_graph(vmaf).matches(&split).count(), 2);
+        }
+    }
+
+    #[test]
+    fn metrics_graph_log_options() {
+        let graph = metrics_graph(true);
+        assert!(graph.contains("psnr=stats_file=psnr.log"));
+        assert!(graph.contains("ssim=stats_file=ssim.log"));
+        assert!(graph.ends_with("libvmaf=log_path=vmaf.json:log_fmt=json"));
+        assert!(!metrics_graph(false).contains("libvmaf"));
+    }
+
+    #[test]
+    fn metrics_command_seeks_the_source() {
+        let source = CmdInput {
+            options: vec![CmdOption::new("-ss", Some("10"))],
+            url: "missing.mkv".to_string(),
+        };
+        let command =
+            metrics_command("ffmpeg", Path::new("a.mp4"), &source, 2.0, 3.0, false).unwrap();
+        let graph = metrics_graph(false);
+        assert_eq!(
+            command.to_args(),
+            [
+                "-hide_banner",
+                "-nostats",
+                "-v",
+                "error",
+                "-i",
+                "a.mp4",
+                "-ss",
+                "12",
+                "-t",
+                "3",
+                "-i",
+                "missing.mkv",
+                "-filter_complex",
+                graph.as_str(),
+                "-f",
+                "null",
+                "-",
+            ]
+        );
+        assert_eq!(command.program, "ffmpeg");
+    }
+
+    #[test]
+    fn metrics_command_makes_source_files_absolute() {
+        // Tests run in the manifest directory
+        let source = CmdInput {
+            options: Vec::new(),
+            url: "Cargo.toml".to_string(),
+        };
+        let command =
+            metrics_command("ffmpeg", Path::new("a.mp4"), &source, 0.0, 1.0, true).unwrap();
+        let expected = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        assert_eq!(Path::new(&command.inputs[1].url), expected);
+        // The variant is already in the metrics directory
+        assert_eq!(command.inputs[0].url, "a.mp4");
+    }
+
+    #[test]
+    fn metrics_command_rejects_bad_trims() {
+        let source = CmdInput {
+            options: vec![CmdOption::new("-ss", Some("soon"))],
+            url: "in.mkv".to_string(),
+        };
+        let error = metrics_command("ffmpeg", Path::new("a.mp4"), &source, 0.0, 1.0, false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("-ss"), "{error}");
+    }
+
+    #[test]
+    fn psnr_stats() {
+        let text = "\
+n:1 mse_avg:0.47 mse_y:0.52 mse_u:0.35 mse_v:0.36 psnr_avg:51.39 psnr_y:50.97 psnr_u:52.69 psnr_v:52.57
+n:2 mse_avg:0.00 mse_y:0.00 mse_u:0.00 mse_v:0.00 psnr_avg:inf psnr_y:inf psnr_u:inf psnr_v:inf
+
+n:3 mse_avg:1.21 psnr_avg:47.30
+";
+        assert_eq!(
+            parse_psnr_stats(text),
+            [(0, 51.39), (1, MAX_PSNR), (2, 47.30)]
+        );
+    }
+
+    #[test]
+    fn ssim_stats() {
+        let text = "\
+n:1 Y:0.993412 U:0.991203 V:0.990114 All:0.992100 (21.007)
+n:2 Y:0.981000 U:0.980000 V:0.979000 All:0.980500 (17.11)
+";
+        assert_eq!(parse_ssim_stats(text), [(0, 0.9921), (1, 0.9805)]);
+    }
+
+    #[test]
+    fn stats_skip_broken_lines() {
+        // No frame, frame 0 which the filters never write, unparsable value
+        let text = "psnr_avg:40.0\nn:0 psnr_avg:40.0\nn:4 psnr_avg:nope\nn:5 psnr_avg:41.5\n";
+        assert_eq!(parse_psnr_stats(text), [(4, 41.5)]);
+    }
+
+    #[test]
+    fn vmaf_log() {
+        let text = r#"{
+            "version": "3.0.0",
+            "frames": [
+                {"frameNum": 0, "metrics": {"integer_adm2": 0.98, "vmaf": 95.25}},
+                {"frameNum": 1, "metrics": {"integer_adm2": 0.97}},
+                {"frameNum": 2, "metrics": {"vmaf": 93.5}}
+            ],
+            "pooled_metrics": {"vmaf": {"mean": 94.37}}
+        }"#;
+        assert_eq!(parse_vmaf_log(text).unwrap(), [(0, 95.25), (2, 93.5)]);
+        assert!(parse_vmaf_log("{}").unwrap().is_empty());
+        assert!(parse_vmaf_log("{\"frames\": [").is_err());
+    }
+
+    #[test]
+    fn summaries_skip_missing_metrics() {
+        let metrics = VariantMetrics::from_frames(vec![
+            frame(Some(40.0), Some(0.95)),
+            frame(Some(50.0), None),
+            frame(Some(45.0), Some(0.99)),
+        ]);
+        let psnr = metrics.psnr.unwrap();
+        assert_eq!((psnr.mean, psnr.min, psnr.max), (45.0, 40.0, 50.0));
+        let ssim = metrics.ssim.unwrap();
+        assert!((ssim.mean - 0.97).abs() < 1e-9, "{}", ssim.mean);
+        assert_eq!((ssim.min, ssim.max), (0.95, 0.99));
+        assert!(metrics.vmaf.is_none());
+        assert_eq!(metrics.frames.len(), 3);
+    }
+}
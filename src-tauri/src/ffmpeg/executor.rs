@@ -1,12 +1,14 @@
+use super::command::{format_time, parse_time, CmdInput, CmdOption, CmdOutput, FfmpegCommand};
 use super::parser;
-use crate::utils::{self, filesystem};
+use super::preview_cache::{self, InputIdentity, PreviewCache};
+use super::validate::{self, JobValidation};
+use crate::utils;
+use crate::FFStudioError;
 use serde::{Deserialize, Serialize};
 
-use regex::Regex;
-use std::collections::VecDeque;
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Child;
-use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Listener, Window};
 
@@ -20,6 +22,12 @@ pub struct TranscodeJob {
     pub cmds: Vec<String>,
     pub envs: Vec<String>,
     pub status: JobStatus,
+    /// Runs in the reserved preview slot instead of counting against `max_concurrent`
+    #[serde(default)]
+    pub preview: bool,
+    /// A newer preview job on the same channel cancels this one
+    #[serde(default)]
+    pub channel: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -35,8 +43,17 @@ pub struct RunningJob {
     pub id: String,
     pub desc: String,
     pub pipeline: Vec<Child>,
+    pub preview: bool,
+    pub channel: Option<String>,
 }
 
+/// Preview jobs that may run at once on top of `max_concurrent`.
+const PREVIEW_SLOTS: usize = 1;
+
+/// Sent on `transcode_<id>` instead of `EOT`/`EOT_FAILED` when the job was cancelled, or
+/// when a newer preview on the same channel replaced it.
+pub const EOT_CANCELLED: &str = "EOT_CANCELLED";
+
 #[derive(Clone)]
 pub struct TranscodeQueue {
     pub queue: Arc<Mutex<VecDeque<TranscodeJob>>>,
@@ -71,6 +88,39 @@ impl TranscodeQueue {
             cmds,
             envs,
             status: JobStatus::Queued,
+            preview: false,
+            channel: None,
+        };
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.push_back(job);
+
+        job_id
+    }
+
+    /// Queues a preview job. Queued and running jobs on the same `channel` are cancelled
+    /// first and get [`EOT_CANCELLED`] instead of their completion event.
+    pub fn add_preview_job(
+        &self,
+        cmds: Vec<String>,
+        envs: Vec<String>,
+        desc: String,
+        channel: Option<String>,
+        window: &Window,
+    ) -> String {
+        if let Some(channel) = &channel {
+            self.cancel_channel(channel, window);
+        }
+
+        let job_id = self.generate_job_id();
+        let job = TranscodeJob {
+            id: job_id.clone(),
+            desc,
+            cmds,
+            envs,
+            status: JobStatus::Queued,
+            preview: true,
+            channel,
         };
 
         let mut queue = self.queue.lock().unwrap();
@@ -79,6 +129,45 @@ impl TranscodeQueue {
         job_id
     }
 
+    /// Cancels the queued and running jobs on `channel`, each gets [`EOT_CANCELLED`].
+    pub fn cancel_channel(&self, channel: &str, window: &Window) -> usize {
+        let superseded = self.supersede(channel);
+        for id in &superseded {
+            let _ = window.emit(&format!("transcode_{id}"), EOT_CANCELLED.to_string());
+        }
+        superseded.len()
+    }
+
+    /// Removes the queued jobs on `channel` and kills the running ones. Returns their ids.
+    fn supersede(&self, channel: &str) -> Vec<String> {
+        let mut superseded = Vec::new();
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.retain(|j| {
+                let stale = j.channel.as_deref() == Some(channel);
+                if stale {
+                    superseded.push(j.id.clone());
+                }
+                !stale
+            });
+        }
+        {
+            let mut running = self.running.lock().unwrap();
+            running.retain_mut(|j| {
+                let stale = j.channel.as_deref() == Some(channel);
+                if stale {
+                    for child in j.pipeline.iter_mut() {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    superseded.push(j.id.clone());
+                }
+                !stale
+            });
+        }
+        superseded
+    }
+
     pub fn get_queue_status(&self) -> Vec<TranscodeJob> {
         let mut all_jobs = Vec::new();
 
@@ -91,6 +180,8 @@ impl TranscodeQueue {
                     cmds: vec![],
                     envs: vec![],
                     status: JobStatus::Running,
+                    preview: rj.preview,
+                    channel: rj.channel.clone(),
                 });
             }
         }
@@ -105,7 +196,16 @@ impl TranscodeQueue {
         all_jobs
     }
 
-    pub fn cancel_job(&self, job_id: &str) -> bool {
+    /// Removes or kills the job. It gets [`EOT_CANCELLED`] instead of its completion event.
+    pub fn cancel_job(&self, job_id: &str, window: &Window) -> bool {
+        let cancelled = self.remove_job(job_id);
+        if cancelled {
+            let _ = window.emit(&format!("transcode_{job_id}"), EOT_CANCELLED.to_string());
+        }
+        cancelled
+    }
+
+    fn remove_job(&self, job_id: &str) -> bool {
         // Try to remove from queue first
         {
             let mut queue = self.queue.lock().unwrap();
@@ -132,14 +232,14 @@ impl TranscodeQueue {
         false
     }
 
-    pub fn cancel_all_jobs(&self) -> usize {
-        let mut cancelled_count = 0;
+    /// Cancels every queued and running job, each gets [`EOT_CANCELLED`].
+    pub fn cancel_all_jobs(&self, window: &Window) -> usize {
+        let mut cancelled = Vec::new();
 
         // Cancel all queued jobs
         {
             let mut queue = self.queue.lock().unwrap();
-            cancelled_count += queue.len();
-            queue.clear();
+            cancelled.extend(queue.drain(..).map(|j| j.id));
         }
 
         // Kill all running jobs
@@ -151,41 +251,48 @@ impl TranscodeQueue {
                     let _ = child.wait();
                 }
             }
-            cancelled_count += running.len();
-            running.clear();
+            cancelled.extend(running.drain(..).map(|j| j.id));
         }
 
-        cancelled_count
+        for id in &cancelled {
+            let _ = window.emit(&format!("transcode_{id}"), EOT_CANCELLED.to_string());
+        }
+        cancelled.len()
     }
 
+    /// Starts queued jobs while there are free slots: `max_concurrent` for transcodes and
+    /// [`PREVIEW_SLOTS`] for previews, so previews never wait behind a batch.
     pub fn process_queue(&self, window: Window) {
-        let (max_concurrent, running_count) = {
-            let max = *self.max_concurrent.lock().unwrap();
-            let count = self.running.lock().unwrap().len();
-            (max, count)
-        };
-
-        if running_count >= max_concurrent {
-            return;
-        }
-
-        let slots_available = max_concurrent - running_count;
-
-        for _ in 0..slots_available {
+        loop {
             let job = {
+                let max_concurrent = *self.max_concurrent.lock().unwrap();
+                let (previews, transcodes) = {
+                    let running = self.running.lock().unwrap();
+                    let previews = running.iter().filter(|j| j.preview).count();
+                    (previews, running.len() - previews)
+                };
+
                 let mut queue = self.queue.lock().unwrap();
-                queue.pop_front()
+                queue
+                    .iter()
+                    .position(|j| {
+                        if j.preview {
+                            previews < PREVIEW_SLOTS
+                        } else {
+                            transcodes < max_concurrent
+                        }
+                    })
+                    .and_then(|pos| queue.remove(pos))
             };
 
-            if let Some(mut job) = job {
-                job.status = JobStatus::Running;
+            let Some(mut job) = job else {
+                break;
+            };
+            job.status = JobStatus::Running;
 
-                let _ = window.emit("queue_status_changed", self.get_queue_status());
+            let _ = window.emit("queue_status_changed", self.get_queue_status());
 
-                self.execute_job(job, window.clone());
-            } else {
-                break;
-            }
+            self.execute_job(job, window.clone());
         }
     }
 
@@ -197,6 +304,8 @@ impl TranscodeQueue {
 
         let job_id = job.id.clone();
         let job_desc = job.desc.clone();
+        let preview = job.preview;
+        let channel = job.channel.clone();
         let cmds = job.cmds;
         let envs = job.envs;
 
@@ -209,9 +318,8 @@ impl TranscodeQueue {
         for (cmd, env_str) in cmds.into_iter().zip(envs) {
             let env_map = parser::parse_env_map(&env_str);
 
-            let safe_cmd = cmd.replace("\\", "%5C");
-            let parts = match shellwords::split(&safe_cmd) {
-                Ok(data) => data,
+            let mut command = match FfmpegCommand::parse(&cmd) {
+                Ok(command) => command,
                 Err(e) => {
                     let _ =
                         window.emit(&format!("transcode_{job_id}"), format!("Parse failed: {e}"));
@@ -219,19 +327,17 @@ impl TranscodeQueue {
                     return;
                 }
             };
+            command.set_global("-progress", Some("pipe:2"));
+            command.set_global("-hide_banner", None);
 
-            let mut parts_iter = parts.into_iter().map(|s| s.replace("%5C", "\\"));
-            let program = parts_iter.next().expect("empty command");
-
-            let mut c = std::process::Command::new(program);
+            let mut c = std::process::Command::new(&command.program);
             #[cfg(windows)]
             {
                 const CREATE_NO_WINDOW: u32 = 0x08000000;
                 c.creation_flags(CREATE_NO_WINDOW);
             }
 
-            c.args(parts_iter);
-            c.arg("-progress").arg("pipe:2").arg("-hide_banner");
+            c.args(command.to_args());
 
             if let Some(stdin) = prev_stdin.take() {
                 c.stdin(stdin);
@@ -317,6 +423,8 @@ impl TranscodeQueue {
                 id: job_id.clone(),
                 desc: job_desc,
                 pipeline,
+                preview,
+                channel,
             });
         }
 
@@ -390,132 +498,415 @@ impl TranscodeQueue {
     }
 }
 
+/// Single images, which have no timeline to seek in.
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "bmp", "webp", "tif", "tiff", "tga", "dpx", "exr", "jxl", "ppm", "pgm",
+];
+
+/// Input formats that generate or capture frames rather than read a file.
+const SOURCE_FORMATS: &[&str] = &[
+    "lavfi",
+    "dshow",
+    "gdigrab",
+    "ddagrab",
+    "vfwcap",
+    "avfoundation",
+    "v4l2",
+    "x11grab",
+    "kmsgrab",
+    "fbdev",
+    "alsa",
+    "pulse",
+    "jack",
+    "oss",
+    "openal",
+    "decklink",
+];
+
+/// How a preview limits one input to the requested range.
+#[derive(Debug, PartialEq)]
+pub enum PreviewSeek {
+    /// Seek to the range and read its duration
+    Seek,
+    /// Can't seek, only bounded so it ends with the range: generated sources, captures,
+    /// looped images and `-sseof` inputs
+    Bound,
+    /// Left untouched: pipes and single images
+    Keep,
+}
+
+pub fn preview_seek(input: &CmdInput) -> PreviewSeek {
+    let url = input.url.as_str();
+    if url == "-" || url.starts_with("pipe:") {
+        return PreviewSeek::Keep;
+    }
+    let option = |name: &str| {
+        input
+            .options
+            .iter()
+            .rev()
+            .find(|o| o.name() == name)
+            .and_then(|o| o.value.as_deref())
+    };
+    if option("f").is_some_and(|f| SOURCE_FORMATS.contains(&f)) || option("sseof").is_some() {
+        return PreviewSeek::Bound;
+    }
+
+    let is_image = !url.contains('%')
+        && std::path::Path::new(url)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()));
+    if is_image {
+        if option("loop").is_some_and(|l| l != "0") {
+            return PreviewSeek::Bound;
+        }
+        return PreviewSeek::Keep;
+    }
+    PreviewSeek::Seek
+}
+
+/// Limits every input of `command` to `start..start + duration` of the output timeline.
+///
+/// Inputs already trimmed with `-ss`/`-t`/`-to` are seeked relative to their trim, and
+/// `-itsoffset` is shifted so delayed inputs stay in sync: an input starting after `start`
+/// keeps the remaining delay instead of being seeked.
+pub fn seek_inputs(command: &mut FfmpegCommand, start: f64, duration: f64) -> Result<(), String> {
+    let time = |o: &CmdOption| -> Result<f64, String> {
+        let value = o.value.as_deref().unwrap_or_default();
+        parse_time(value).ok_or_else(|| format!("Invalid time for {}: {value}", o.flag))
+    };
+
+    for input in &mut command.inputs {
+        match preview_seek(input) {
+            PreviewSeek::Keep => {}
+            PreviewSeek::Bound => {
+                input.options.retain(|o| !matches!(o.name(), "t" | "to"));
+                input
+                    .options
+                    .push(CmdOption::new("-t", Some(&format_time(duration))));
+            }
+            PreviewSeek::Seek => {
+                let mut trim_start = 0.0;
+                let mut trim_end = None;
+                let mut offset = None;
+                for o in &input.options {
+                    match o.name() {
+                        "ss" => trim_start = time(o)?,
+                        "to" => trim_end = Some(time(o)?),
+                        "itsoffset" => offset = Some(time(o)?),
+                        _ => {}
+                    }
+                }
+                if let Some(t) = input.options.iter().find(|o| o.name() == "t") {
+                    // -t wins over -to, as in ffmpeg
+                    trim_end = Some(trim_start + time(t)?);
+                }
+
+                let delay = offset.unwrap_or(0.0);
+                let seek = trim_start + (start - delay).max(0.0);
+                let length = match trim_end {
+                    Some(end) => duration.min(end - seek).max(0.0),
+                    None => duration,
+                };
+
+                input
+                    .options
+                    .retain(|o| !matches!(o.name(), "ss" | "t" | "to" | "itsoffset"));
+                if offset.is_some() {
+                    let remaining = format_time((delay - start).max(0.0));
+                    input
+                        .options
+                        .push(CmdOption::new("-itsoffset", Some(&remaining)));
+                }
+                input
+                    .options
+                    .push(CmdOption::new("-ss", Some(&format_time(seek))));
+                input
+                    .options
+                    .push(CmdOption::new("-t", Some(&format_time(length))));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What the second step of a preview turns the segment into.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum PreviewTarget {
+    /// An mp4 segment, or a png for a single frame
+    #[default]
+    Preview,
+    /// Animated GIF with a palette generated from the segment itself
+    Gif {
+        width: Option<u32>,
+        fps: Option<f64>,
+    },
+    /// Animated WebP
+    Webp {
+        width: Option<u32>,
+        fps: Option<f64>,
+    },
+    /// One png of `frames` evenly spaced frames, `columns` per row
+    Sheet {
+        width: Option<u32>,
+        frames: Option<u32>,
+        columns: Option<u32>,
+    },
+    /// An mp4 segment like `Preview`, plus the encoded video stream copied as is to
+    /// [`measured_stream`] so quality metrics see the variant's own encoding
+    Compare,
+}
+
+/// The stream copy a [`PreviewTarget::Compare`] render writes next to `preview`.
+pub fn measured_stream(preview: &Path) -> PathBuf {
+    preview.with_extension("mkv")
+}
+
+impl PreviewTarget {
+    /// Output options and extension of the second step for a segment of `duration`
+    /// seconds.
+    fn output(&self, duration: f64) -> (Vec<CmdOption>, &'static str) {
+        let positive = |v: Option<u32>, default: u32| v.filter(|&v| v > 0).unwrap_or(default);
+        let rate =
+            |v: Option<f64>, default: f64| format_time(v.filter(|&v| v > 0.0).unwrap_or(default));
+        match self {
+            PreviewTarget::Preview | PreviewTarget::Compare => (Vec::new(), "mp4"),
+            PreviewTarget::Gif { width, fps } => {
+                let graph = format!(
+                    "fps={},scale={}:-1:flags=lanczos,split[s0][s1];\
+                     [s0]palettegen=stats_mode=diff[p];[s1][p]paletteuse=diff_mode=rectangle",
+                    rate(*fps, 12.0),
+                    positive(*width, 480)
+                );
+                (
+                    vec![
+                        CmdOption::new("-vf", Some(&graph)),
+                        CmdOption::new("-loop", Some("0")),
+                        CmdOption::new("-an", None),
+                    ],
+                    "gif",
+                )
+            }
+            PreviewTarget::Webp { width, fps } => {
+                let graph = format!(
+                    "fps={},scale={}:-2:flags=lanczos",
+                    rate(*fps, 15.0),
+                    positive(*width, 640)
+                );
+                (
+                    vec![
+                        CmdOption::new("-vf", Some(&graph)),
+                        CmdOption::new("-c:v", Some("libwebp")),
+                        CmdOption::new("-q:v", Some("75")),
+                        CmdOption::new("-loop", Some("0")),
+                        CmdOption::new("-an", None),
+                    ],
+                    "webp",
+                )
+            }
+            PreviewTarget::Sheet {
+                width,
+                frames,
+                columns,
+            } => {
+                let frames = positive(*frames, 12);
+                let columns = positive(*columns, (frames as f64).sqrt().ceil() as u32).min(frames);
+                let rows = frames.div_ceil(columns);
+                // One frame every duration/frames seconds fills the grid exactly once
+                let graph = format!(
+                    "fps={},scale={}:-2,tile={columns}x{rows}:padding=4:margin=4",
+                    format_time(frames as f64 / duration),
+                    positive(*width, 320)
+                );
+                (
+                    vec![
+                        CmdOption::new("-vf", Some(&graph)),
+                        CmdOption::new("-frames:v", Some("1")),
+                        CmdOption::new("-update", Some("1")),
+                        CmdOption::new("-an", None),
+                    ],
+                    "png",
+                )
+            }
+        }
+    }
+}
+
+/// Builds the two-step preview for `start..end` of `cmd`: the first step runs the graph on
+/// that range of every input and encodes it with the first output's options into a pipe,
+/// the second turns the pipe into an mp4 segment, or a png when `end` is `None` or equal
+/// to `start`. `Compare` also keeps the encoded video, any other `target` exports the
+/// segment as a GIF, WebP or contact sheet instead.
+///
+/// A single frame normally encodes one second and keeps its first frame. With `accurate`
+/// the inputs are decoded up to `start` exactly, even when the output copies the video
+/// stream, and only that frame is encoded.
 pub fn make_preview_cmd(
     cmd: &str,
     cache_dir: PathBuf,
     start: &str,
     end: Option<&str>,
+    accurate: bool,
+    target: &PreviewTarget,
 ) -> Result<(Vec<String>, String), String> {
-    let seg_name: String = {
-        let mut unique_str = String::from_str(cmd).unwrap();
-        unique_str.push_str(start);
-        if let Some(end_str) = end {
-            unique_str.push_str(end_str);
-        }
-        unique_str
-    };
-    let seg_name: String = utils::hash::short_hash(&seg_name);
+    let seg_name = preview_cache::preview_key(cmd, start, end, accurate, target);
 
-    let re = Regex::new(r#"(?:[^\s"]+|"[^"]*")+"#).unwrap();
-    let tokens: Vec<String> = re.find_iter(cmd).map(|m| m.as_str().to_string()).collect();
-
-    let i_idx = tokens
-        .iter()
-        .position(|t| t == "-i")
-        .ok_or_else(|| "No input file found in ffmpeg command".to_string())?;
-    if i_idx + 1 >= tokens.len() {
-        return Err("No input file after -i".to_string());
+    let command = FfmpegCommand::parse(cmd).map_err(|e| e.to_string())?;
+    if command.inputs.is_empty() {
+        return Err("No input file found in ffmpeg command".to_string());
     }
+    let output = command
+        .outputs
+        .first()
+        .ok_or_else(|| "No output file found in ffmpeg command".to_string())?;
 
     let mut new_output_file = cache_dir;
     new_output_file.push(seg_name);
 
-    let program = tokens.first().unwrap().clone();
     let is_single_frame = end.is_none() || (end.is_some() && start == end.unwrap());
+    if is_single_frame && *target != PreviewTarget::Preview {
+        return Err("Exports need a segment, not a single frame".to_string());
+    }
 
-    // Step 1: Extract segment/frame to pipe
-    let mut input_opts = vec!["-y".to_string(), "-ss".to_string(), start.to_string()];
-
-    if let Some(e) = end {
-        if start != e {
-            // Segment with duration
-            input_opts.push("-to".to_string());
-            input_opts.push(e.to_string());
-        } else {
-            // Single frame - extract 1 second
-            input_opts.push("-t".to_string());
-            input_opts.push("1".to_string());
+    let start_secs = parse_time(start).ok_or_else(|| format!("Invalid preview start: {start}"))?;
+    let duration = match end {
+        // Segment with duration
+        Some(e) if start != e => {
+            let end_secs = parse_time(e).ok_or_else(|| format!("Invalid preview end: {e}"))?;
+            if end_secs <= start_secs {
+                return Err(format!("Preview end {e} is not after start {start}"));
+            }
+            end_secs - start_secs
         }
-    } else {
         // Single frame - extract 1 second
-        input_opts.push("-t".to_string());
-        input_opts.push("1".to_string());
-    }
-
-    let mut step1_tokens = Vec::new();
-    step1_tokens.extend_from_slice(&tokens[..i_idx]);
-    step1_tokens.extend(input_opts);
-    step1_tokens.extend_from_slice(&tokens[i_idx..]);
+        _ => 1.0,
+    };
 
-    // Replace output with pipe
-    if let Some(last) = step1_tokens.last_mut() {
-        *last = "pipe:1".to_string();
+    // Step 1: Extract segment/frame to pipe
+    let mut step1 = command.clone();
+    step1.set_global("-y", None);
+    seek_inputs(&mut step1, start_secs.max(0.0), duration)?;
+
+    // Keep the first output's encoding options, but write it to a pipe
+    // (NUT is more robust than MPEG-TS, but both work)
+    let mut options = output.options.clone();
+    if is_single_frame && accurate {
+        for input in &mut step1.inputs {
+            input.options.retain(|o| o.name() != "noaccurate_seek");
+        }
+        // A copied stream can only start at a keyframe
+        options.retain(|o| {
+            let stream = o.flag.split_once(':').map(|(_, spec)| spec);
+            let copies_video = matches!(o.name(), "c" | "codec" | "vcodec")
+                && stream.map_or(true, |spec| !spec.starts_with(['a', 's', 'd', 't']))
+                && o.value.as_deref() == Some("copy");
+            !copies_video && !matches!(o.name(), "frames" | "vframes")
+        });
+        options.push(CmdOption::new("-frames:v", Some("1")));
+        options.push(CmdOption::new("-an", None));
+        options.push(CmdOption::new("-sn", None));
+        options.push(CmdOption::new("-dn", None));
     }
-
-    // Add format before output (NUT is more robust than MPEG-TS, but both work)
-    let output_opts = vec!["-f".to_string(), "nut".to_string()];
-    let insert_pos = step1_tokens.len() - 1;
-    step1_tokens.splice(insert_pos..insert_pos, output_opts);
-
-    let step1_cmd = step1_tokens
-        .into_iter()
-        .map(|t| {
-            if !(t.starts_with('\"') && t.ends_with('\"')) && t.contains(' ') {
-                format!("\"{t}\"")
-            } else {
-                t
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ");
+    options.push(CmdOption::new("-f", Some("nut")));
+    step1.outputs = vec![CmdOutput {
+        options,
+        url: "pipe:1".to_string(),
+    }];
+    step1.trailing.clear();
 
     // Step 2: Convert pipe input to final format
-    let mut step2_tokens = vec![program, "-i".to_string(), "pipe:0".to_string()];
+    let mut step2 = FfmpegCommand {
+        program: command.program.clone(),
+        inputs: vec![CmdInput {
+            options: Vec::new(),
+            url: "pipe:0".to_string(),
+        }],
+        ..FfmpegCommand::default()
+    };
+    step2.set_global("-y", None);
 
+    let mut options = Vec::new();
     if is_single_frame {
         // Single frame output
-        step2_tokens.push("-frames:v".to_string());
-        step2_tokens.push("1".to_string());
-        step2_tokens.push("-update".to_string());
-        step2_tokens.push("1".to_string());
-
-        step2_tokens.push("-y".to_string());
+        options.push(CmdOption::new("-frames:v", Some("1")));
+        options.push(CmdOption::new("-update", Some("1")));
         new_output_file.set_extension("png");
     } else {
-        // Video segment output
-        step2_tokens.push("-y".to_string());
-        new_output_file.set_extension("mp4");
+        // Video segment or export output
+        let (target_options, extension) = target.output(duration);
+        options = target_options;
+        new_output_file.set_extension(extension);
+    }
+    step2.outputs.push(CmdOutput {
+        options,
+        url: new_output_file.to_string_lossy().into_owned(),
+    });
+    if *target == PreviewTarget::Compare {
+        step2.outputs.push(CmdOutput {
+            options: vec![
+                CmdOption::new("-map", Some("0:v:0")),
+                CmdOption::new("-c", Some("copy")),
+            ],
+            url: measured_stream(&new_output_file)
+                .to_string_lossy()
+                .into_owned(),
+        });
     }
-
-    step2_tokens.push(new_output_file.to_string_lossy().into_owned());
-
-    let step2_cmd = step2_tokens
-        .into_iter()
-        .map(|t| {
-            if !(t.starts_with('\"') && t.ends_with('\"')) && t.contains(' ') {
-                format!("\"{t}\"")
-            } else {
-                t
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ");
 
     Ok((
-        vec![step1_cmd, step2_cmd],
+        vec![step1.to_string(), step2.to_string()],
         new_output_file.to_string_lossy().into_owned(),
     ))
 }
 
+/// Runs [`validate::validate_job`] off the async runtime, it spawns ffmpeg and waits for
+/// each stage.
+async fn validate_blocking(cmds: Vec<String>, envs: Vec<String>) -> crate::Result<JobValidation> {
+    tokio::task::spawn_blocking(move || validate::validate_job(&cmds, &envs))
+        .await
+        .map_err(|e| FFStudioError::ffmpeg(format!("Validation failed to run: {e}")))
+}
+
+/// Checks a pipeline without queueing it, see [`validate::validate_job`].
+#[tauri::command]
+pub async fn validate_job(cmds: Vec<String>, envs: Vec<String>) -> crate::Result<JobValidation> {
+    validate_blocking(cmds, envs).await
+}
+
+/// Queues a pipeline. With `validate` set, a pipeline that fails [`validate_job`] is
+/// rejected instead of failing once it reaches the front of the queue.
 #[tauri::command]
-pub fn queue_transcode(
+pub async fn queue_transcode(
     cmds: Vec<String>,
     envs: Vec<String>,
     desc: String,
+    validate: Option<bool>,
     window: Window,
-    queue: tauri::State<TranscodeQueue>,
-) -> String {
-    assert_eq!(cmds.len(), envs.len(), "Each command must have an env");
+    queue: tauri::State<'_, TranscodeQueue>,
+) -> crate::Result<String> {
+    if cmds.len() != envs.len() {
+        return Err(FFStudioError::ffmpeg(format!(
+            "Got {} commands but {} environments",
+            cmds.len(),
+            envs.len()
+        )));
+    }
+
+    if validate.unwrap_or(false) {
+        let validation = validate_blocking(cmds.clone(), envs.clone()).await?;
+        let first_error = validation.errors().next().map(|error| {
+            format!(
+                "Validation failed at stage {}: {}",
+                error.stage + 1,
+                error.message
+            )
+        });
+        if let Some(message) = first_error {
+            return Err(FFStudioError::ffmpeg(message));
+        }
+    }
 
     let job_id = queue.add_job(cmds, envs, desc);
 
@@ -524,7 +915,7 @@ pub fn queue_transcode(
 
     let _ = window.emit("queue_status_changed", queue.get_queue_status());
 
-    job_id
+    Ok(job_id)
 }
 
 #[tauri::command]
@@ -551,7 +942,7 @@ pub fn get_queue_status(queue: tauri::State<TranscodeQueue>) -> Vec<TranscodeJob
 
 #[tauri::command]
 pub fn cancel_job(job_id: String, window: Window, queue: tauri::State<TranscodeQueue>) -> bool {
-    let result = queue.cancel_job(&job_id);
+    let result = queue.cancel_job(&job_id, &window);
 
     if result {
         // Try to start next job
@@ -564,7 +955,7 @@ pub fn cancel_job(job_id: String, window: Window, queue: tauri::State<TranscodeQ
 
 #[tauri::command]
 pub fn cancel_all_jobs(window: Window, queue: tauri::State<TranscodeQueue>) -> usize {
-    let count = queue.cancel_all_jobs();
+    let count = queue.cancel_all_jobs(&window);
 
     if count > 0 {
         let _ = window.emit("queue_status_changed", queue.get_queue_status());
@@ -573,7 +964,188 @@ pub fn cancel_all_jobs(window: Window, queue: tauri::State<TranscodeQueue>) -> u
     count
 }
 
+/// Where a preview request ended up.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreviewTicket {
+    /// Job rendering the preview, `None` when it came from the cache
+    pub job_id: Option<String>,
+    pub path: String,
+    pub cached: bool,
+}
+
+/// How a queued preview render ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewOutcome {
+    Rendered,
+    Failed,
+    /// Cancelled from the queue, or replaced by a newer request on the same channel
+    Cancelled,
+}
+
+/// A preview render of `start..end` of `cmd`, or a single frame when `end` is `None`.
+pub struct PreviewRequest {
+    pub cmd: String,
+    pub env: String,
+    pub desc: String,
+    pub start: String,
+    pub end: Option<String>,
+    pub accurate: bool,
+    pub channel: Option<String>,
+    pub target: PreviewTarget,
+}
+
+/// A preview request resolved against the cache.
+enum PreviewPlan {
+    Cached(PathBuf),
+    Render {
+        cmds: Vec<String>,
+        target: PathBuf,
+        /// Files the render writes next to `target`
+        extra: Vec<PathBuf>,
+        key: String,
+        inputs: Vec<InputIdentity>,
+    },
+}
+
+/// Cancels the older jobs on the request's channel, then looks the preview up in the
+/// cache. Returns the cancelled jobs along with the plan.
+///
+/// The channel is cleared on a cache hit too, or an older render would end after the
+/// cached file was shown and replace it.
+fn plan_preview(
+    queue: &TranscodeQueue,
+    cache: &PreviewCache,
+    request: &PreviewRequest,
+) -> Result<(Vec<String>, PreviewPlan), String> {
+    let (cmds, target_file_path) = make_preview_cmd(
+        &request.cmd,
+        cache.dir().map_err(|e| e.to_string())?,
+        &request.start,
+        request.end.as_deref(),
+        request.accurate,
+        &request.target,
+    )?;
+    let superseded = request
+        .channel
+        .as_deref()
+        .map(|channel| queue.supersede(channel))
+        .unwrap_or_default();
+
+    let target = PathBuf::from(&target_file_path);
+    let key = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let inputs = FfmpegCommand::parse(&request.cmd)
+        .map(|c| preview_cache::input_identities(&c))
+        .unwrap_or_default();
+    if let Some(path) = cache.lookup(&key, &inputs) {
+        return Ok((superseded, PreviewPlan::Cached(path)));
+    }
+
+    let extra: Vec<PathBuf> = (request.target == PreviewTarget::Compare)
+        .then(|| measured_stream(&target))
+        .into_iter()
+        .collect();
+    let plan = PreviewPlan::Render {
+        cmds,
+        target,
+        extra,
+        key,
+        inputs,
+    };
+    Ok((superseded, plan))
+}
+
+/// Queues a preview render, or returns the cached file if the same range of the same
+/// command was rendered before from unchanged inputs. Either way older jobs on the
+/// request's channel are cancelled first.
+///
+/// `on_done` gets the outcome and the file once the job ends. It isn't called for cached
+/// previews.
+pub fn start_preview(
+    window: &Window,
+    queue: &TranscodeQueue,
+    cache: &PreviewCache,
+    request: PreviewRequest,
+    on_done: impl Fn(PreviewOutcome, &str) + Send + 'static,
+) -> Result<PreviewTicket, String> {
+    let (superseded, plan) = plan_preview(queue, cache, &request)?;
+    for id in &superseded {
+        let _ = window.emit(&format!("transcode_{id}"), EOT_CANCELLED.to_string());
+    }
+    if !superseded.is_empty() {
+        let _ = window.emit("queue_status_changed", queue.get_queue_status());
+    }
+    let (final_cmds, target, extra, key, inputs) = match plan {
+        PreviewPlan::Cached(path) => {
+            return Ok(PreviewTicket {
+                job_id: None,
+                path: path.to_string_lossy().into_owned(),
+                cached: true,
+            });
+        }
+        PreviewPlan::Render {
+            cmds,
+            target,
+            extra,
+            key,
+            inputs,
+        } => (cmds, target, extra, key, inputs),
+    };
+    let target_file_path = target.to_string_lossy().into_owned();
+
+    let count = final_cmds.len();
+    let job_id = queue.add_preview_job(
+        final_cmds,
+        vec![request.env; count],
+        request.desc,
+        request.channel,
+        window,
+    );
+
+    let window_clone = window.clone();
+    let cache = cache.clone();
+    let target_path_clone = target_file_path.clone();
+
+    window.listen(format!("transcode_{job_id}"), move |event| {
+        let payload: String = serde_json::from_str(event.payload()).unwrap_or_default();
+        let outcome = match payload.as_str() {
+            "EOT" => {
+                if let Err(e) = cache.insert_with(&key, &target, extra.clone(), inputs.clone()) {
+                    log::warn!("Failed to cache preview: {e}");
+                }
+                PreviewOutcome::Rendered
+            }
+            "EOT_FAILED" => {
+                extra.iter().chain([&target]).for_each(|f| cache.discard(f));
+                PreviewOutcome::Failed
+            }
+            EOT_CANCELLED => {
+                extra.iter().chain([&target]).for_each(|f| cache.discard(f));
+                PreviewOutcome::Cancelled
+            }
+            _ => return,
+        };
+        window_clone.unlisten(event.id());
+        on_done(outcome, &target_path_clone);
+    });
+
+    queue.process_queue(window.clone());
+    let _ = window.emit("queue_status_changed", queue.get_queue_status());
+
+    Ok(PreviewTicket {
+        job_id: Some(job_id),
+        path: target_file_path,
+        cached: false,
+    })
+}
+
+/// Queues a preview render or export, see [`start_preview`]. `render_preview_listener` is
+/// emitted with the file once a queued render ends, unless it was cancelled: callers see
+/// that as [`EOT_CANCELLED`] on the job's own event.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn render_preview_request(
     window: Window,
     cmd: String,
@@ -581,31 +1153,293 @@ pub fn render_preview_request(
     desc: String,
     start: String,
     end: String,
+    variables: Option<HashMap<String, String>>,
+    accurate: Option<bool>,
+    channel: Option<String>,
+    target: Option<PreviewTarget>,
     queue: tauri::State<TranscodeQueue>,
-) -> String {
-    let data_path = filesystem::get_data_dir().unwrap();
-    let tmp_path = data_path.join("tmp");
+    cache: tauri::State<PreviewCache>,
+) -> Result<PreviewTicket, String> {
+    let cmd = utils::template::render_variables(&cmd, &variables.unwrap_or_default());
+    let end = if start != end { Some(end) } else { None };
+    let request = PreviewRequest {
+        cmd,
+        env,
+        desc,
+        start,
+        end,
+        accurate: accurate.unwrap_or(false),
+        channel,
+        target: target.unwrap_or_default(),
+    };
 
-    let real_end: Option<&str> = if start != end { Some(&end) } else { None };
+    let window_clone = window.clone();
+    start_preview(&window, &queue, &cache, request, move |outcome, path| {
+        if outcome != PreviewOutcome::Cancelled {
+            let _ = window_clone.emit("render_preview_listener", path);
+        }
+    })
+}
 
-    let (final_cmds, target_file_path) =
-        make_preview_cmd(&cmd, tmp_path, &start, real_end).unwrap();
-    let count = final_cmds.len();
-    let job_id = queue.add_job(final_cmds, vec![env.clone(); count], desc);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn queued_preview(queue: &TranscodeQueue, channel: &str) -> String {
+        let id = queue.generate_job_id();
+        queue.queue.lock().unwrap().push_back(TranscodeJob {
+            id: id.clone(),
+            desc: String::new(),
+            cmds: vec!["ffmpeg -i in.mkv out.mp4".to_string()],
+            envs: vec![String::new()],
+            status: JobStatus::Queued,
+            preview: true,
+            channel: Some(channel.to_string()),
+        });
+        id
+    }
 
-    let window_clone = window.clone();
-    let target_path_clone = target_file_path.clone();
+    fn request(channel: &str) -> PreviewRequest {
+        PreviewRequest {
+            cmd: "ffmpeg -i in.mkv -c:v libx264 out.mp4".to_string(),
+            env: String::new(),
+            desc: String::new(),
+            start: "1".to_string(),
+            end: Some("3".to_string()),
+            accurate: false,
+            channel: Some(channel.to_string()),
+            target: PreviewTarget::Preview,
+        }
+    }
 
-    window.listen(format!("transcode_{job_id}"), move |event| {
-        let payload = event.payload();
-        if payload == "\"EOT\"" || payload == "\"EOT_FAILED\"" {
-            window_clone.unlisten(event.id());
-            let _ = window_clone.emit("render_preview_listener", &target_path_clone);
+    /// The first step of a preview of `cmd`, parsed back.
+    fn preview_step1(cmd: &str, start: &str, end: Option<&str>, accurate: bool) -> FfmpegCommand {
+        let (cmds, _) = make_preview_cmd(
+            cmd,
+            PathBuf::from("cache"),
+            start,
+            end,
+            accurate,
+            &PreviewTarget::Preview,
+        )
+        .unwrap();
+        FfmpegCommand::parse(&cmds[0]).unwrap()
+    }
+
+    fn joined(options: &[CmdOption]) -> String {
+        options
+            .iter()
+            .flat_map(|o| std::iter::once(o.flag.as_str()).chain(o.value.as_deref()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn preview_seeks_every_input() {
+        // (command, start, end, options of each input)
+        let cases: &[(&str, &str, Option<&str>, &[&str])] = &[
+            (
+                "ffmpeg -i a.mkv -i b.wav -c:v libx264 out.mp4",
+                "5",
+                Some("7"),
+                &["-ss 5 -t 2", "-ss 5 -t 2"],
+            ),
+            // A single frame reads one second
+            (
+                "ffmpeg -i a.mkv out.mp4",
+                "00:01:05.5",
+                None,
+                &["-ss 65.5 -t 1"],
+            ),
+            ("ffmpeg -i a.mkv out.mp4", "3", Some("3"), &["-ss 3 -t 1"]),
+            // Relative to the input's own trim
+            (
+                "ffmpeg -ss 10 -to 20 -i a.mkv out.mp4",
+                "5",
+                Some("8"),
+                &["-ss 15 -t 3"],
+            ),
+            // -t wins over -to and cuts the range short
+            (
+                "ffmpeg -ss 10 -t 4 -to 100 -i a.mkv out.mp4",
+                "2",
+                Some("8"),
+                &["-ss 12 -t 2"],
+            ),
+            // Trimmed to end before the range
+            (
+                "ffmpeg -to 3 -i a.mkv out.mp4",
+                "5",
+                Some("7"),
+                &["-ss 5 -t 0"],
+            ),
+            // Delayed inputs keep what's left of their delay
+            (
+                "ffmpeg -i a.mkv -itsoffset 2 -i b.wav out.mp4",
+                "1",
+                Some("3"),
+                &["-ss 1 -t 2", "-itsoffset 1 -ss 0 -t 2"],
+            ),
+            (
+                "ffmpeg -i a.mkv -itsoffset 2 -i b.wav out.mp4",
+                "5",
+                Some("7"),
+                &["-ss 5 -t 2", "-itsoffset 0 -ss 3 -t 2"],
+            ),
+            // Sources and looped images can only be bounded
+            (
+                "ffmpeg -f lavfi -t 60 -i testsrc2 -loop 1 -i logo.png out.mp4",
+                "5",
+                Some("7"),
+                &["-f lavfi -t 2", "-loop 1 -t 2"],
+            ),
+            // Still images and pipes are left alone
+            (
+                "ffmpeg -i a.mkv -i logo.png -i - out.mp4",
+                "5",
+                Some("7"),
+                &["-ss 5 -t 2", "", ""],
+            ),
+        ];
+        for (cmd, start, end, expected) in cases {
+            let step1 = preview_step1(cmd, start, *end, false);
+            let inputs: Vec<String> = step1.inputs.iter().map(|i| joined(&i.options)).collect();
+            assert_eq!(inputs, *expected, "{cmd} from {start} to {end:?}");
         }
-    });
 
-    queue.process_queue(window.clone());
-    let _ = window.emit("queue_status_changed", queue.get_queue_status());
+        // Input seeking, before each -i, not on the output
+        let step1 = preview_step1(cases[0].0, "5", Some("7"), false);
+        assert_eq!(
+            step1.to_args().join(" "),
+            "-y -ss 5 -t 2 -i a.mkv -ss 5 -t 2 -i b.wav -c:v libx264 -f nut pipe:1"
+        );
+    }
+
+    #[test]
+    fn preview_accurate_single_frame() {
+        let cmd = "ffmpeg -noaccurate_seek -i a.mkv -c:v copy -c:a copy -frames 50 out.mkv";
+        // (end, accurate, input options, output options)
+        let cases = [
+            (
+                None,
+                false,
+                "-noaccurate_seek -ss 5 -t 1",
+                "-c:v copy -c:a copy -frames 50 -f nut",
+            ),
+            (
+                None,
+                true,
+                "-ss 5 -t 1",
+                "-c:a copy -frames:v 1 -an -sn -dn -f nut",
+            ),
+            // Segments always seek to the nearest keyframe
+            (
+                Some("7"),
+                true,
+                "-noaccurate_seek -ss 5 -t 2",
+                "-c:v copy -c:a copy -frames 50 -f nut",
+            ),
+        ];
+        for (end, accurate, input, output) in cases {
+            let step1 = preview_step1(cmd, "5", end, accurate);
+            assert_eq!(
+                joined(&step1.inputs[0].options),
+                input,
+                "{end:?} {accurate}"
+            );
+            assert_eq!(step1.outputs.len(), 1);
+            assert_eq!(
+                joined(&step1.outputs[0].options),
+                output,
+                "{end:?} {accurate}"
+            );
+            assert_eq!(step1.outputs[0].url, "pipe:1");
+        }
 
-    job_id
+        // Only video copies are dropped
+        let step1 = preview_step1(
+            "ffmpeg -i a.mkv -codec copy -c:s copy out.mkv",
+            "5",
+            None,
+            true,
+        );
+        assert_eq!(
+            joined(&step1.outputs[0].options),
+            "-c:s copy -frames:v 1 -an -sn -dn -f nut"
+        );
+    }
+
+    #[test]
+    fn preview_rejects_bad_ranges() {
+        let make = |start: &str, end: Option<&str>, target: &PreviewTarget| {
+            make_preview_cmd(
+                "ffmpeg -i a.mkv out.mp4",
+                PathBuf::new(),
+                start,
+                end,
+                false,
+                target,
+            )
+            .unwrap_err()
+        };
+        assert!(make("7", Some("5"), &PreviewTarget::Preview).contains("not after"));
+        assert!(make("soon", Some("5"), &PreviewTarget::Preview).contains("start"));
+        assert!(make("1", Some("later"), &PreviewTarget::Preview).contains("end"));
+        assert!(make("5", None, &PreviewTarget::Compare).contains("single frame"));
+    }
+
+    #[test]
+    fn cache_hit_cancels_older_jobs_on_the_channel() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        let queue = TranscodeQueue::default();
+
+        // Loading the index deletes files it doesn't know, load it before rendering
+        cache.stats();
+        // A render of the same segment finished earlier and is cached
+        let request = request("timeline");
+        let (_, path) = make_preview_cmd(
+            &request.cmd,
+            dir.path().to_path_buf(),
+            &request.start,
+            request.end.as_deref(),
+            false,
+            &request.target,
+        )
+        .unwrap();
+        let path = PathBuf::from(path);
+        fs::write(&path, b"mp4").unwrap();
+        let key = path.file_stem().unwrap().to_string_lossy().into_owned();
+        cache.insert(&key, &path, Vec::new()).unwrap();
+
+        let older = queued_preview(&queue, "timeline");
+        let other = queued_preview(&queue, "compare-a");
+        let (superseded, plan) = plan_preview(&queue, &cache, &request).unwrap();
+
+        assert_eq!(superseded, vec![older]);
+        assert!(matches!(plan, PreviewPlan::Cached(p) if p == path));
+        let left: Vec<String> = queue.get_queue_status().into_iter().map(|j| j.id).collect();
+        assert_eq!(left, vec![other]);
+    }
+
+    #[test]
+    fn cache_miss_plans_a_render_and_cancels_too() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        let queue = TranscodeQueue::default();
+
+        let older = queued_preview(&queue, "timeline");
+        let (superseded, plan) = plan_preview(&queue, &cache, &request("timeline")).unwrap();
+
+        assert_eq!(superseded, vec![older]);
+        match plan {
+            PreviewPlan::Render { cmds, target, .. } => {
+                assert_eq!(cmds.len(), 2);
+                assert_eq!(target.parent(), Some(dir.path()));
+            }
+            PreviewPlan::Cached(_) => panic!("nothing was cached"),
+        }
+        assert!(queue.get_queue_status().is_empty());
+    }
 }
@@ -0,0 +1,512 @@
+use super::command::{CmdOption, FfmpegCommand};
+use super::probe::probe_media;
+use crate::utils::template::{self, Collision, TemplateContext};
+use crate::{FFStudioError, Result};
+use glob::glob;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// A command template and what to fill it with.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobTemplate {
+    /// Command line, program included unless `ffmpeg` is set
+    pub template: String,
+    /// Program for a `template` that only holds the arguments, as watch folders store it
+    pub ffmpeg: Option<String>,
+    pub env: String,
+    /// `{{variable}}` values
+    pub variables: HashMap<String, String>,
+    /// Globs whose matches become one job each, used for `{input}`
+    pub inputs: Vec<String>,
+    /// Output path template, used for `{output}`
+    pub output: Option<String>,
+    /// Root `{relpath}` is computed against
+    pub base_dir: Option<PathBuf>,
+    pub workflow: String,
+    /// `{counter}` of the first job, 1 if unset
+    pub counter: Option<u64>,
+    /// Ignored when the command has `-y`, which always overwrites. Unset, a single job
+    /// overwrites as the command would be run by hand, and a batch skips taken outputs.
+    pub collision: Option<Collision>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExpandedJob {
+    pub cmd: String,
+    pub inputs: Vec<String>,
+    /// `{output}` first if the template has one, then the command's other file outputs
+    pub outputs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkippedJob {
+    pub inputs: Vec<String>,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Expansion {
+    pub jobs: Vec<ExpandedJob>,
+    pub skipped: Vec<SkippedJob>,
+}
+
+fn is_glob(url: &str) -> bool {
+    url.contains(['*', '?'])
+}
+
+/// Files matching `pattern`, sorted so the job order doesn't depend on the file system.
+fn glob_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = glob(pattern)?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// `out.mp4` -> `out_<inject>.mp4`
+fn inject_before_extension(url: &str, inject: &str) -> String {
+    let path = Path::new(url);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let file = match path.extension() {
+        Some(ext) => format!("{stem}_{inject}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_{inject}"),
+    };
+    path.with_file_name(file).to_string_lossy().into_owned()
+}
+
+/// Pipes, network streams and the null muxer are not files.
+fn is_file_url(url: &str, options: &[CmdOption]) -> bool {
+    let null_muxer = options
+        .iter()
+        .any(|o| o.name() == "f" && o.value.as_deref() == Some("null"));
+    !(null_muxer || url == "-" || url.starts_with("pipe:") || url.contains("://"))
+}
+
+/// Applies the collision policy to `path`, counting paths other jobs claimed as taken.
+fn claim(
+    path: PathBuf,
+    collision: &Collision,
+    claimed: &HashSet<PathBuf>,
+) -> std::result::Result<PathBuf, String> {
+    template::resolve_collision(path.clone(), collision, claimed)
+        .ok_or_else(|| format!("{} already exists", path.display()))
+}
+
+fn render_options(options: &mut [CmdOption], ctx: &TemplateContext) -> Result<()> {
+    for value in options.iter_mut().filter_map(|o| o.value.as_mut()) {
+        if value.contains('{') {
+            *value = template::render_arg(value, ctx)?;
+        }
+    }
+    Ok(())
+}
+
+/// Expands `request` into concrete jobs.
+///
+/// `{{variables}}` are substituted first. Then every `-i` argument containing `*` or `?`
+/// and the `inputs` globs are expanded, and job N takes the Nth match of each, in sorted
+/// order. Outputs get `*` replaced, `{placeholders}` rendered, or, for a batch with a
+/// single plain output, a suffix before the extension so jobs don't write the same file.
+/// The suffix is the input names if an output uses `{name}`, the zero-based index if one
+/// uses `{index}`, and a hash of the inputs otherwise.
+///
+/// Outputs are checked against the collision policy, `taken` and each other; a job that
+/// can't claim its outputs is listed as skipped.
+pub fn expand_job(request: &JobTemplate, taken: &HashSet<PathBuf>) -> Result<Expansion> {
+    let line = template::render_variables(&request.template, &request.variables);
+    let command = match &request.ffmpeg {
+        Some(ffmpeg) => FfmpegCommand::parse_args(ffmpeg, &line),
+        None => FfmpegCommand::parse(&line),
+    }
+    .map_err(|e| FFStudioError::parse(e.to_string()))?;
+
+    let mut slots: Vec<Vec<PathBuf>> = Vec::new();
+    if !request.inputs.is_empty() {
+        let mut files = Vec::new();
+        for pattern in &request.inputs {
+            files.extend(glob_files(pattern)?);
+        }
+        files.sort();
+        files.dedup();
+        slots.push(files);
+    }
+    let first_wildcard_slot = slots.len();
+    let wildcard_inputs: Vec<usize> = (0..command.inputs.len())
+        .filter(|&n| is_glob(&command.inputs[n].url))
+        .collect();
+    for &n in &wildcard_inputs {
+        slots.push(glob_files(&command.inputs[n].url)?);
+    }
+
+    let batch = !slots.is_empty();
+    if slots.iter().any(Vec::is_empty) {
+        return Err(FFStudioError::glob(
+            "One or more input patterns matched no files",
+        ));
+    }
+    let count = slots.iter().map(Vec::len).min().unwrap_or(1);
+    if slots.iter().any(|s| s.len() != count) {
+        log::warn!(
+            "Input patterns matched different numbers of files, using the first {count} of each"
+        );
+    }
+
+    let collision = if command.globals.iter().any(|o| o.flag == "-y") {
+        Collision::Overwrite
+    } else {
+        match &request.collision {
+            Some(collision) => collision.clone(),
+            None if batch => Collision::Skip,
+            None => Collision::Overwrite,
+        }
+    };
+    let uses = |key: &str| command.outputs.iter().any(|o| o.url.contains(key));
+    let injection = if uses("{index}") {
+        "{index}"
+    } else if uses("{name}") {
+        "{name}"
+    } else {
+        "{hash}"
+    };
+    let needs_probe = template::needs_probe(&line)
+        || request.output.as_deref().is_some_and(template::needs_probe);
+
+    let mut expansion = Expansion::default();
+    let mut claimed = taken.clone();
+
+    for index in 0..count {
+        let job_inputs: Vec<PathBuf> = if batch {
+            slots.iter().map(|s| s[index].clone()).collect()
+        } else {
+            command
+                .inputs
+                .iter()
+                .filter(|i| is_file_url(&i.url, &i.options))
+                .map(|i| PathBuf::from(&i.url))
+                .collect()
+        };
+        let input_names: Vec<String> = job_inputs
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+
+        let mut ctx = TemplateContext {
+            input: job_inputs.first().cloned().unwrap_or_default(),
+            extra_inputs: job_inputs.iter().skip(1).cloned().collect(),
+            base_dir: request.base_dir.clone(),
+            workflow: request.workflow.clone(),
+            counter: request.counter.unwrap_or(1) + index as u64,
+            variables: request.variables.clone(),
+            ..TemplateContext::default()
+        };
+        if needs_probe {
+            ctx.probe =
+                probe_media(&ctx.input.to_string_lossy(), &command.program, &request.env).ok();
+        }
+
+        match expand_one(&command, request, &collision, &mut ctx, &claimed, injection) {
+            Ok((mut job, outputs)) => {
+                for (slot, &n) in wildcard_inputs.iter().enumerate() {
+                    job.inputs[n].url = input_names[first_wildcard_slot + slot].clone();
+                }
+                if collision == Collision::Overwrite {
+                    job.set_global("-y", None);
+                }
+                claimed.extend(outputs.iter().map(PathBuf::from));
+                expansion.jobs.push(ExpandedJob {
+                    cmd: job.to_string(),
+                    inputs: input_names,
+                    outputs,
+                });
+            }
+            Err(reason) => expansion.skipped.push(SkippedJob {
+                inputs: input_names,
+                reason,
+            }),
+        }
+    }
+    Ok(expansion)
+}
+
+/// Renders one job. Errors are the reason to skip it.
+fn expand_one(
+    command: &FfmpegCommand,
+    request: &JobTemplate,
+    collision: &Collision,
+    ctx: &mut TemplateContext,
+    claimed: &HashSet<PathBuf>,
+    injection: &str,
+) -> std::result::Result<(FfmpegCommand, Vec<String>), String> {
+    let mut claimed = claimed.clone();
+    let mut outputs = Vec::new();
+
+    if let Some(output) = &request.output {
+        let path = PathBuf::from(template::render_arg(output, ctx).map_err(|e| e.to_string())?);
+        let path = claim(path, collision, &claimed)?;
+        claimed.insert(path.clone());
+        outputs.push(path.to_string_lossy().into_owned());
+        ctx.output = Some(path);
+    }
+
+    let injection = template::render_arg(injection, ctx).map_err(|e| e.to_string())?;
+    let single_output = command.outputs.len() == 1;
+    let batch = !request.inputs.is_empty() || command.inputs.iter().any(|i| is_glob(&i.url));
+
+    let mut job = command.clone();
+    for output in &mut job.outputs {
+        let mut url = if output.url.contains('*') {
+            output.url.replace('*', &injection)
+        } else if batch && single_output && !output.url.contains('{') {
+            inject_before_extension(&output.url, &injection)
+        } else {
+            output.url.clone()
+        };
+        if url.contains('{') {
+            url = template::render_arg(&url, ctx).map_err(|e| e.to_string())?;
+        }
+
+        let is_output_template = ctx.output.as_deref() == Some(Path::new(&url));
+        if !is_output_template && is_file_url(&url, &output.options) {
+            let path = claim(PathBuf::from(&url), collision, &claimed)?;
+            claimed.insert(path.clone());
+            url = path.to_string_lossy().into_owned();
+            outputs.push(url.clone());
+        }
+        output.url = url;
+    }
+
+    let render = |options: &mut [CmdOption]| render_options(options, ctx);
+    let result = (|| {
+        render(&mut job.globals)?;
+        for input in &mut job.inputs {
+            render(&mut input.options)?;
+            if input.url.contains('{') {
+                input.url = template::render_arg(&input.url, ctx)?;
+            }
+        }
+        render(&mut job.filter_complex)?;
+        for output in &mut job.outputs {
+            render(&mut output.options)?;
+        }
+        render(&mut job.trailing)
+    })();
+    result.map_err(|e| e.to_string())?;
+
+    Ok((job, outputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Creates empty `files` in `dir`.
+    fn touch(dir: &Path, files: &[&str]) {
+        for file in files {
+            fs::write(dir.join(file), "").unwrap();
+        }
+    }
+
+    fn request(template: String) -> JobTemplate {
+        JobTemplate {
+            template,
+            workflow: "w".to_string(),
+            ..JobTemplate::default()
+        }
+    }
+
+    fn expand(request: &JobTemplate) -> Expansion {
+        expand_job(request, &HashSet::new()).unwrap()
+    }
+
+    /// File names of every job's inputs.
+    fn input_names(expansion: &Expansion) -> Vec<Vec<String>> {
+        expansion
+            .jobs
+            .iter()
+            .map(|job| {
+                job.inputs
+                    .iter()
+                    .map(|i| {
+                        Path::new(i)
+                            .file_name()
+                            .unwrap()
+                            .to_string_lossy()
+                            .into_owned()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn glob_matches_are_sorted() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(dir.path(), &["c.mp4", "a.mp4", "b.mp4", "skip.txt"]);
+
+        let expansion = expand(&request(format!(
+            "ffmpeg -i {d}/*.mp4 {d}/out/{{name}}.mkv"
+        )));
+        assert_eq!(input_names(&expansion), [["a.mp4"], ["b.mp4"], ["c.mp4"]]);
+        assert_eq!(expansion.jobs[1].outputs, [format!("{d}/out/b.mkv")]);
+
+        let mut from_inputs = request(format!("ffmpeg -i {{input}} {d}/out/{{name}}.mkv"));
+        from_inputs.inputs = vec![format!("{d}/c*.mp4"), format!("{d}/[ab].mp4")];
+        assert_eq!(
+            input_names(&expand(&from_inputs)),
+            [["a.mp4"], ["b.mp4"], ["c.mp4"]]
+        );
+    }
+
+    #[test]
+    fn jobs_pair_the_nth_match_of_each_pattern() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(
+            dir.path(),
+            &["v1.mp4", "v2.mp4", "v3.mp4", "s1.srt", "s2.srt"],
+        );
+
+        let expansion = expand(&request(format!(
+            "ffmpeg -i {d}/v*.mp4 -i {d}/s*.srt -c copy {d}/out/{{index}}.mkv"
+        )));
+        // v3.mp4 has no subtitle to go with
+        assert_eq!(
+            input_names(&expansion),
+            [["v1.mp4", "s1.srt"], ["v2.mp4", "s2.srt"]]
+        );
+        let cmd = FfmpegCommand::parse(&expansion.jobs[1].cmd).unwrap();
+        assert_eq!(cmd.inputs[0].url, format!("{d}/v2.mp4"));
+        assert_eq!(cmd.inputs[1].url, format!("{d}/s2.srt"));
+        assert_eq!(expansion.jobs[1].outputs, [format!("{d}/out/1.mkv")]);
+    }
+
+    #[test]
+    fn unmatched_pattern_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(dir.path(), &["v1.mp4"]);
+        let line = format!("ffmpeg -i {d}/v*.mp4 -i {d}/*.srt {d}/out.mkv");
+        assert!(expand_job(&request(line), &HashSet::new()).is_err());
+    }
+
+    #[test]
+    fn outputs_get_the_inputs_injected() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(dir.path(), &["a.mp4", "b.mp4"]);
+        let outputs = |line: String| -> Vec<Vec<String>> {
+            let outputs = expand(&request(line)).jobs.into_iter().map(|j| j.outputs);
+            outputs
+                .map(|o| {
+                    o.iter()
+                        .map(|p| {
+                            Path::new(p)
+                                .file_name()
+                                .unwrap()
+                                .to_string_lossy()
+                                .into_owned()
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+
+        // `*` takes the input names when an output uses `{name}`
+        assert_eq!(
+            outputs(format!(
+                "ffmpeg -i {d}/*.mp4 {d}/*.mkv -f ffmetadata {d}/{{name}}.txt"
+            )),
+            [["a.mkv", "a.txt"], ["b.mkv", "b.txt"]]
+        );
+        // and the index when one uses `{index}`
+        assert_eq!(
+            outputs(format!(
+                "ffmpeg -i {d}/*.mp4 {d}/x_*.mkv -f ffmetadata {d}/{{index}}.txt"
+            )),
+            [["x_0.mkv", "0.txt"], ["x_1.mkv", "1.txt"]]
+        );
+
+        // A single plain output gets a hash of the inputs
+        let hashed = outputs(format!("ffmpeg -i {d}/*.mp4 {d}/out.mkv"));
+        assert_eq!(hashed.len(), 2);
+        assert_ne!(hashed[0], hashed[1]);
+        for name in hashed.iter().flatten() {
+            let hash = name
+                .strip_prefix("out_")
+                .unwrap()
+                .strip_suffix(".mkv")
+                .unwrap();
+            assert!(
+                !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit() || c == '-'),
+                "{name}"
+            );
+        }
+        assert_eq!(hashed, outputs(format!("ffmpeg -i {d}/*.mp4 {d}/out.mkv")));
+    }
+
+    #[test]
+    fn jobs_of_a_batch_dont_claim_the_same_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(dir.path(), &["a.mp4", "b.mp4", "c.mp4"]);
+        let mut same_output = request(format!("ffmpeg -i {d}/*.mp4 {d}/{{workflow}}.mkv"));
+
+        let expansion = expand(&same_output);
+        assert_eq!(input_names(&expansion), [["a.mp4"]]);
+        assert_eq!(expansion.skipped.len(), 2);
+        assert!(expansion.skipped[0].inputs[0].ends_with("b.mp4"));
+        assert!(expansion.skipped[0].reason.contains("w.mkv"));
+
+        same_output.collision = Some(Collision::Suffix);
+        let outputs: Vec<String> = expand(&same_output)
+            .jobs
+            .into_iter()
+            .flat_map(|j| j.outputs)
+            .collect();
+        assert_eq!(
+            outputs,
+            [
+                format!("{d}/w.mkv"),
+                format!("{d}/w_1.mkv"),
+                format!("{d}/w_2.mkv")
+            ]
+        );
+
+        // Outputs reserved by jobs expanded earlier count too
+        let taken = HashSet::from([PathBuf::from(format!("{d}/w.mkv"))]);
+        same_output.collision = None;
+        let expansion = expand_job(&same_output, &taken).unwrap();
+        assert!(expansion.jobs.is_empty());
+        assert_eq!(expansion.skipped.len(), 3);
+    }
+
+    #[test]
+    fn single_job_overwrites_unless_told_otherwise() {
+        let dir = tempfile::tempdir().unwrap();
+        let d = dir.path().display();
+        touch(dir.path(), &["in.mp4", "out.mkv"]);
+        let mut single = request(format!("ffmpeg -i {d}/in.mp4 {d}/out.mkv"));
+
+        let expansion = expand(&single);
+        assert!(expansion.skipped.is_empty());
+        let cmd = FfmpegCommand::parse(&expansion.jobs[0].cmd).unwrap();
+        assert!(cmd.globals.iter().any(|o| o.flag == "-y"));
+        assert_eq!(expansion.jobs[0].outputs, [format!("{d}/out.mkv")]);
+
+        single.collision = Some(Collision::Skip);
+        let expansion = expand(&single);
+        assert!(expansion.jobs.is_empty());
+        assert_eq!(expansion.skipped.len(), 1);
+
+        single.collision = Some(Collision::Suffix);
+        assert_eq!(expand(&single).jobs[0].outputs, [format!("{d}/out_1.mkv")]);
+    }
+}
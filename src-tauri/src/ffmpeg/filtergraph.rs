@@ -0,0 +1,1057 @@
+use super::command::{CmdOutput, FfmpegCommand};
+use crate::workflow::types::{CodecInfo, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Options every filter accepts through its AVFilterContext, on top of its own.
+const CONTEXT_OPTIONS: &[&str] = &["enable", "threads", "thread_type", "extra_hw_frames"];
+
+/// Byte range in the filtergraph text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    pub name: String,
+    /// Includes the brackets
+    pub span: Span,
+}
+
+/// One `key=value` or positional value of a filter, with quotes and escapes removed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilterArg {
+    pub key: Option<String>,
+    pub value: String,
+    pub key_span: Option<Span>,
+    pub value_span: Span,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Filter {
+    pub inputs: Vec<Label>,
+    pub name: String,
+    /// Instance name from `name@id`
+    pub id: Option<String>,
+    pub name_span: Span,
+    pub args: Vec<FilterArg>,
+    pub outputs: Vec<Label>,
+}
+
+/// A parsed filtergraph. Filters in a chain are joined by `,`, chains by `;`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterGraph {
+    /// Leading `sws_flags=...;`
+    pub sws_flags: Option<String>,
+    pub chains: Vec<Vec<Filter>>,
+}
+
+impl FilterGraph {
+    pub fn filters(&self) -> impl Iterator<Item = &Filter> {
+        self.chains.iter().flatten()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Nothing wrong, e.g. a check that was skipped
+    Info,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraphIssue {
+    pub severity: Severity,
+    pub message: String,
+    /// `None` when the problem isn't in the graph text, e.g. a bad `-map`
+    pub span: Option<Span>,
+}
+
+impl GraphIssue {
+    fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+}
+
+/// Unescaped characters, each with the byte offset it came from in the graph text.
+type Chars = Vec<(usize, char)>;
+
+fn collect(chars: &[(usize, char)]) -> String {
+    chars.iter().map(|(_, c)| c).collect()
+}
+
+fn end_of(src: &[(usize, char)]) -> usize {
+    src.last().map_or(0, |&(pos, c)| pos + c.len_utf8())
+}
+
+fn span_of(chars: &[(usize, char)], fallback: usize) -> Span {
+    match (chars.first(), chars.last()) {
+        (Some(&(start, _)), Some(&(end, c))) => Span {
+            start,
+            end: end + c.len_utf8(),
+        },
+        _ => Span {
+            start: fallback,
+            end: fallback,
+        },
+    }
+}
+
+fn skip_ws(src: &[(usize, char)], mut i: usize) -> usize {
+    while src.get(i).is_some_and(|(_, c)| c.is_whitespace()) {
+        i += 1;
+    }
+    i
+}
+
+/// Reads a token the way ffmpeg's `av_get_token` does: up to an unquoted, unescaped char
+/// from `terms`, dropping `'quotes'` and `\` escapes and trimming unprotected whitespace.
+/// Returns the token and the index it stopped at.
+fn get_token(
+    src: &[(usize, char)],
+    start: usize,
+    terms: &[char],
+) -> Result<(Chars, usize), GraphIssue> {
+    let mut i = skip_ws(src, start);
+    let mut out: Chars = Vec::new();
+    let mut keep = 0;
+
+    while let Some(&(pos, c)) = src.get(i) {
+        if terms.contains(&c) {
+            break;
+        }
+        i += 1;
+        match c {
+            '\\' => {
+                if let Some(&escaped) = src.get(i) {
+                    out.push(escaped);
+                    i += 1;
+                    keep = out.len();
+                }
+            }
+            '\'' => {
+                loop {
+                    match src.get(i) {
+                        Some(&(_, '\'')) => break,
+                        Some(&quoted) => out.push(quoted),
+                        None => {
+                            return Err(GraphIssue::error(
+                                "Unterminated quote",
+                                Span {
+                                    start: pos,
+                                    end: end_of(src),
+                                },
+                            ))
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                keep = out.len();
+            }
+            _ => {
+                out.push((pos, c));
+                if !c.is_whitespace() {
+                    keep = out.len();
+                }
+            }
+        }
+    }
+
+    out.truncate(keep);
+    Ok((out, i))
+}
+
+fn parse_label(src: &[(usize, char)], i: usize) -> Result<(Label, usize), GraphIssue> {
+    let open = src[i].0;
+    let (name, end) = get_token(src, i + 1, &[']'])?;
+    let Some(&(close, _)) = src.get(end) else {
+        return Err(GraphIssue::error(
+            "Missing ']' after label",
+            Span {
+                start: open,
+                end: end_of(src),
+            },
+        ));
+    };
+    let span = Span {
+        start: open,
+        end: close + 1,
+    };
+    if name.is_empty() {
+        return Err(GraphIssue::error("Empty label", span));
+    }
+    let label = Label {
+        name: collect(&name),
+        span,
+    };
+    Ok((label, end + 1))
+}
+
+fn parse_labels(
+    src: &[(usize, char)],
+    mut i: usize,
+    labels: &mut Vec<Label>,
+) -> Result<usize, GraphIssue> {
+    while src.get(i).is_some_and(|(_, c)| *c == '[') {
+        let (label, next) = parse_label(src, i)?;
+        labels.push(label);
+        i = skip_ws(src, next);
+    }
+    Ok(i)
+}
+
+/// Same as ffmpeg: letters, digits and `-_/.` make a key, anything else starts a value.
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | '.')
+}
+
+/// Splits the already unescaped `name=` part of a filter into `key=value` pairs separated
+/// by `:`. Positional values are only allowed before the first named one.
+fn parse_args(chars: &[(usize, char)]) -> Result<Vec<FilterArg>, GraphIssue> {
+    let mut args: Vec<FilterArg> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let key_len = chars[i..]
+            .iter()
+            .take_while(|(_, c)| is_key_char(*c))
+            .count();
+        let has_key = key_len > 0 && chars.get(i + key_len).is_some_and(|(_, c)| *c == '=');
+        let key = has_key.then(|| &chars[i..i + key_len]);
+        let value_start = if has_key { i + key_len + 1 } else { i };
+
+        let (value, next) = get_token(chars, value_start, &[':'])?;
+        let fallback = chars.get(value_start).map_or(end_of(chars), |c| c.0);
+        let value_span = span_of(&value, fallback);
+
+        if key.is_none() && args.iter().any(|a| a.key.is_some()) {
+            return Err(GraphIssue::error(
+                "Positional value after a named option",
+                value_span,
+            ));
+        }
+        args.push(FilterArg {
+            key: key.map(collect),
+            value: collect(&value),
+            key_span: key.map(|k| span_of(k, fallback)),
+            value_span,
+        });
+        i = next + 1;
+    }
+    Ok(args)
+}
+
+fn parse_filter(src: &[(usize, char)], i: usize) -> Result<(Filter, usize), GraphIssue> {
+    let (name, mut i) = get_token(src, i, &['=', ',', ';', '['])?;
+    let name_span = span_of(&name, src.get(i).map_or(end_of(src), |c| c.0));
+    let name = collect(&name);
+    if name.is_empty() {
+        return Err(GraphIssue::error("Missing filter name", name_span));
+    }
+
+    let mut filter = Filter {
+        name_span,
+        ..Filter::default()
+    };
+    match name.split_once('@') {
+        Some((name, id)) => {
+            filter.name = name.to_string();
+            filter.id = Some(id.to_string());
+        }
+        None => filter.name = name,
+    }
+
+    if src.get(i).is_some_and(|(_, c)| *c == '=') {
+        let (args, next) = get_token(src, i + 1, &['[', ']', ',', ';'])?;
+        filter.args = parse_args(&args)?;
+        i = next;
+    }
+    Ok((filter, i))
+}
+
+/// Parses filtergraph syntax: `[in]name@id=key=value:value[out], ...; ...`.
+///
+/// Quoting and escaping follow ffmpeg's two levels: the graph level splits filters and
+/// labels, then each filter's arguments are split into options. Stops at the first
+/// syntax error, like ffmpeg does.
+pub fn parse_filtergraph(text: &str) -> Result<FilterGraph, GraphIssue> {
+    let src: Chars = text.char_indices().collect();
+    let mut graph = FilterGraph::default();
+    let mut i = skip_ws(&src, 0);
+
+    let rest = src.get(i).map_or("", |&(pos, _)| &text[pos..]);
+    if rest.starts_with("sws_flags=") {
+        let start = i + "sws_flags=".len();
+        let Some(end) = src[start..].iter().position(|(_, c)| *c == ';') else {
+            return Err(GraphIssue::error(
+                "sws_flags must be followed by ';'",
+                Span {
+                    start: src[i].0,
+                    end: text.len(),
+                },
+            ));
+        };
+        graph.sws_flags = Some(collect(&src[start..start + end]).trim().to_string());
+        i = start + end + 1;
+    }
+
+    loop {
+        let mut chain = Vec::new();
+        loop {
+            let mut inputs = Vec::new();
+            i = parse_labels(&src, skip_ws(&src, i), &mut inputs)?;
+            let (mut filter, next) = parse_filter(&src, i)?;
+            filter.inputs = inputs;
+            i = parse_labels(&src, skip_ws(&src, next), &mut filter.outputs)?;
+            chain.push(filter);
+
+            if src.get(i).is_some_and(|(_, c)| *c == ',') {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        graph.chains.push(chain);
+
+        match src.get(i) {
+            None => break,
+            Some((_, ';')) => {
+                i = skip_ws(&src, i + 1);
+                if i >= src.len() {
+                    break;
+                }
+            }
+            Some(&(pos, c)) => {
+                return Err(GraphIssue::error(
+                    format!("Unexpected '{c}'"),
+                    Span {
+                        start: pos,
+                        end: pos + c.len_utf8(),
+                    },
+                ))
+            }
+        }
+    }
+    Ok(graph)
+}
+
+/// What the surrounding command offers a `-filter_complex` graph.
+#[derive(Clone, Debug, Default)]
+pub struct GraphContext<'a> {
+    pub input_count: usize,
+    /// Labels the outputs take with `-map [label]`
+    pub mapped: HashSet<&'a str>,
+}
+
+/// `[0:v]`, `[1]`: a stream of an input file rather than a link label.
+fn is_stream_ref(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Catches the usual mistakes in an `enable` expression, ffmpeg does the real parsing.
+fn check_expression(expr: &str) -> Option<String> {
+    if expr.trim().is_empty() {
+        return Some("Empty 'enable' expression".to_string());
+    }
+    let mut depth = 0;
+    for c in expr.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some("Unbalanced ')' in 'enable' expression".to_string()),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    (depth > 0).then(|| {
+        "Unbalanced '(' in 'enable' expression, quote it if it contains commas, \
+         e.g. enable='between(t,1,5)'"
+            .to_string()
+    })
+}
+
+fn check_filter(filter: &Filter, node: &Node, issues: &mut Vec<GraphIssue>) {
+    for arg in &filter.args {
+        let Some(key) = &arg.key else {
+            continue;
+        };
+        let key_span = arg.key_span.unwrap_or(arg.value_span);
+        // "/key=path" loads the value from a file
+        let (name, from_file) = match key.strip_prefix('/') {
+            Some(name) => (name, true),
+            None => (key.as_str(), false),
+        };
+
+        if name == "enable" {
+            if node.filter.as_ref().is_some_and(|f| !f.timeline) {
+                issues.push(GraphIssue::error(
+                    format!("Filter '{}' doesn't support timeline editing", filter.name),
+                    key_span,
+                ));
+            } else if let Some(message) = check_expression(&arg.value).filter(|_| !from_file) {
+                issues.push(GraphIssue::error(message, arg.value_span));
+            }
+            continue;
+        }
+        if CONTEXT_OPTIONS.contains(&name) {
+            continue;
+        }
+
+        match node.options.iter().find(|o| o.flag == name) {
+            // Aliases and options of child classes such as swscale aren't in the catalog
+            None => issues.push(GraphIssue::warning(
+                format!("Option '{name}' is not listed for filter '{}'", filter.name),
+                key_span,
+            )),
+            Some(option) if !from_file => {
+                if let Err(message) = option.check_value(&arg.value) {
+                    issues.push(GraphIssue::error(message, arg.value_span));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Follows the links the way ffmpeg builds them: explicit input labels come first, then
+/// the unlabeled outputs of the previous filter in the chain.
+fn check_links(
+    graph: &FilterGraph,
+    catalog: &HashMap<&str, &Node>,
+    complex: Option<&GraphContext>,
+    issues: &mut Vec<GraphIssue>,
+) {
+    let mut produced: HashMap<&str, Span> = HashMap::new();
+    let mut consumed: HashMap<&str, Span> = HashMap::new();
+
+    for chain in &graph.chains {
+        let mut carried = 0;
+        for filter in chain {
+            let info = catalog
+                .get(filter.name.as_str())
+                .and_then(|n| n.filter.as_ref());
+
+            let given = filter.inputs.len() + carried;
+            if let Some(info) = info.filter(|i| !i.dynamic_inputs) {
+                if given > info.inputs.len() {
+                    issues.push(GraphIssue::error(
+                        format!(
+                            "Filter '{}' takes {} input(s) but is given {given}",
+                            filter.name,
+                            info.inputs.len()
+                        ),
+                        filter.name_span,
+                    ));
+                }
+            }
+
+            for label in &filter.inputs {
+                if let Some(ctx) = complex.filter(|_| is_stream_ref(&label.name)) {
+                    let digits: String = label
+                        .name
+                        .chars()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    if digits.parse::<usize>().is_ok_and(|n| n >= ctx.input_count) {
+                        issues.push(GraphIssue::error(
+                            format!(
+                                "Input #{digits} doesn't exist, the command has {} input(s)",
+                                ctx.input_count
+                            ),
+                            label.span,
+                        ));
+                    }
+                    continue;
+                }
+                if consumed.insert(&label.name, label.span).is_some() {
+                    issues.push(GraphIssue::error(
+                        format!("Label [{}] is used as an input more than once", label.name),
+                        label.span,
+                    ));
+                }
+            }
+
+            for label in &filter.outputs {
+                if produced.insert(&label.name, label.span).is_some() {
+                    issues.push(GraphIssue::error(
+                        format!("Label [{}] is produced more than once", label.name),
+                        label.span,
+                    ));
+                }
+            }
+
+            carried = match info.filter(|i| !i.dynamic_outputs) {
+                Some(info) => {
+                    if filter.outputs.len() > info.outputs.len() {
+                        issues.push(GraphIssue::error(
+                            format!(
+                                "Filter '{}' has {} output(s) but {} labels",
+                                filter.name,
+                                info.outputs.len(),
+                                filter.outputs.len()
+                            ),
+                            filter.name_span,
+                        ));
+                    }
+                    info.outputs.len().saturating_sub(filter.outputs.len())
+                }
+                None => usize::from(filter.outputs.is_empty()),
+            };
+        }
+    }
+
+    // A simple graph (-vf/-af) may leave one labeled input and output open
+    let Some(ctx) = complex else {
+        return;
+    };
+    for (name, span) in &consumed {
+        if !produced.contains_key(name) {
+            issues.push(GraphIssue::error(
+                format!("Label [{name}] is not produced by any filter"),
+                *span,
+            ));
+        }
+    }
+    for (name, span) in &produced {
+        if !consumed.contains_key(name) && !ctx.mapped.contains(name) {
+            issues.push(GraphIssue::error(
+                format!("Output [{name}] is never used, map it with -map \"[{name}]\""),
+                *span,
+            ));
+        }
+    }
+}
+
+/// Checks filter names, option names and values against `nodes`, and that every label
+/// is produced once and consumed. Pass `complex` for a `-filter_complex` graph.
+///
+/// Catalog checks are skipped when `nodes` has no filters, e.g. when listing them failed.
+pub fn validate_filtergraph(
+    graph: &FilterGraph,
+    nodes: &[Node],
+    complex: Option<&GraphContext>,
+) -> Vec<GraphIssue> {
+    let mut catalog: HashMap<&str, &Node> = HashMap::new();
+    for node in nodes.iter().filter(|n| n.pcategory == "filters") {
+        // The filter's own node comes before its extra AVOptions sections
+        catalog.entry(node.name.as_str()).or_insert(node);
+    }
+
+    let mut issues = Vec::new();
+    if !catalog.is_empty() {
+        for filter in graph.filters() {
+            match catalog.get(filter.name.as_str()) {
+                Some(node) => check_filter(filter, node, &mut issues),
+                None => issues.push(GraphIssue::error(
+                    format!("Unknown filter '{}'", filter.name),
+                    filter.name_span,
+                )),
+            }
+        }
+    }
+    check_links(graph, &catalog, complex, &mut issues);
+
+    issues.sort_by_key(|i| i.span.map(|s| s.start));
+    issues
+}
+
+/// A problem in one of a command's filtergraphs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommandIssue {
+    /// Option holding the graph the span points into, e.g. `-filter_complex` or `-vf`
+    pub option: String,
+    /// Output the option belongs to, `None` for `-filter_complex`
+    pub output: Option<usize>,
+    #[serde(flatten)]
+    pub issue: GraphIssue,
+}
+
+/// Validates `-filter_complex` and every output's `-vf`/`-af`/`-filter`, that each
+/// `-map [label]` has a matching output in the complex graph, and that the formats an
+/// output asks for are supported by its encoders.
+pub fn check_command(cmd: &FfmpegCommand, nodes: &[Node]) -> Vec<CommandIssue> {
+    let mut issues = Vec::new();
+    let mapped: HashSet<&str> = cmd
+        .outputs
+        .iter()
+        .flat_map(|o| o.maps())
+        .filter_map(|m| m.strip_prefix('[')?.strip_suffix(']'))
+        .collect();
+
+    let mut produced: HashSet<String> = HashSet::new();
+    for complex in &cmd.filter_complex {
+        let text = complex.value.as_deref().unwrap_or_default();
+        let ctx = GraphContext {
+            input_count: cmd.inputs.len(),
+            mapped: mapped.clone(),
+        };
+        let graph_issues = match parse_filtergraph(text) {
+            Ok(graph) => {
+                produced.extend(
+                    graph
+                        .filters()
+                        .flat_map(|f| &f.outputs)
+                        .map(|l| l.name.clone()),
+                );
+                validate_filtergraph(&graph, nodes, Some(&ctx))
+            }
+            Err(issue) => {
+                // Labels are unknown, don't blame the maps for it
+                produced.extend(mapped.iter().map(|m| m.to_string()));
+                vec![issue]
+            }
+        };
+        issues.extend(graph_issues.into_iter().map(|issue| CommandIssue {
+            option: complex.flag.clone(),
+            output: None,
+            issue,
+        }));
+    }
+
+    let mut unknown: Vec<&str> = mapped
+        .iter()
+        .filter(|m| !produced.contains(**m))
+        .copied()
+        .collect();
+    unknown.sort_unstable();
+    issues.extend(unknown.into_iter().map(|label| CommandIssue {
+        option: "-map".to_string(),
+        output: None,
+        issue: GraphIssue {
+            severity: Severity::Error,
+            message: format!("-map [{label}] doesn't match any -filter_complex output"),
+            span: None,
+        },
+    }));
+
+    for (index, output) in cmd.outputs.iter().enumerate() {
+        for option in &output.options {
+            if !matches!(option.name(), "vf" | "af" | "filter") {
+                continue;
+            }
+            let Some(text) = &option.value else {
+                continue;
+            };
+            let graph_issues = match parse_filtergraph(text) {
+                Ok(graph) => validate_filtergraph(&graph, nodes, None),
+                Err(issue) => vec![issue],
+            };
+            issues.extend(graph_issues.into_iter().map(|issue| CommandIssue {
+                option: option.flag.clone(),
+                output: Some(index),
+                issue,
+            }));
+        }
+        check_encoder_formats(output, index, nodes, &mut issues);
+    }
+    issues
+}
+
+/// Media type letter of a stream specifier, `-c:v:0` -> `v`. `None` applies to all streams.
+fn stream_type(flag: &str) -> Option<&str> {
+    flag.split(':')
+        .nth(1)
+        .filter(|t| matches!(*t, "v" | "a" | "s" | "d" | "t"))
+}
+
+/// Checks `-pix_fmt`, `-sample_fmt`, `-ar` and `-ch_layout` against the formats each
+/// encoder of the output lists. Encoders ffmpeg didn't restrict, or that aren't in the
+/// catalog, are skipped.
+fn check_encoder_formats(
+    output: &CmdOutput,
+    index: usize,
+    nodes: &[Node],
+    issues: &mut Vec<CommandIssue>,
+) {
+    let encoders: Vec<(Option<&str>, &str, &CodecInfo)> = output
+        .options
+        .iter()
+        .filter_map(|option| {
+            let kind = match option.name() {
+                "c" | "codec" => stream_type(&option.flag),
+                "vcodec" => Some("v"),
+                "acodec" => Some("a"),
+                _ => return None,
+            };
+            let name = option.value.as_deref()?;
+            let info = nodes
+                .iter()
+                .filter(|n| n.pcategory == "encoders" && n.name == name)
+                .find_map(|n| n.codec.as_ref())?;
+            Some((kind, name, info))
+        })
+        .collect();
+    if encoders.is_empty() {
+        return;
+    }
+
+    for option in &output.options {
+        let Some(value) = option.value.as_deref() else {
+            continue;
+        };
+        let (what, supported): (&str, fn(&CodecInfo, &str) -> bool) = match option.name() {
+            // A leading '+' only disables automatic conversion
+            "pix_fmt" => ("pixel format", |c, v| {
+                c.supports_pix_fmt(v.trim_start_matches('+'))
+            }),
+            "sample_fmt" => ("sample format", |c, v| c.supports_sample_fmt(v)),
+            "ar" => ("sample rate", |c, v| {
+                v.parse().map_or(true, |rate| c.supports_sample_rate(rate))
+            }),
+            "ch_layout" | "channel_layout" => {
+                ("channel layout", |c, v| c.supports_channel_layout(v))
+            }
+            _ => continue,
+        };
+        let kind = stream_type(&option.flag);
+        for (encoder_kind, encoder, info) in &encoders {
+            if kind.is_some() && encoder_kind.is_some() && kind != *encoder_kind {
+                continue;
+            }
+            if !supported(info, value) {
+                issues.push(CommandIssue {
+                    option: option.flag.clone(),
+                    output: Some(index),
+                    issue: GraphIssue {
+                        severity: Severity::Warning,
+                        message: format!("Encoder '{encoder}' doesn't support {what} '{value}'"),
+                        span: None,
+                    },
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::types::{FilterInfo, FilterPad, OptionEntry};
+
+    fn encoder(name: &str, codec: CodecInfo) -> Node {
+        Node {
+            name: name.to_string(),
+            pcategory: "encoders".to_string(),
+            codec: Some(codec),
+            ..Default::default()
+        }
+    }
+
+    fn catalog() -> Vec<Node> {
+        vec![
+            encoder(
+                "libx264",
+                CodecInfo {
+                    pix_fmts: vec!["yuv420p".to_string(), "yuv444p".to_string()],
+                    ..Default::default()
+                },
+            ),
+            encoder(
+                "libopus",
+                CodecInfo {
+                    sample_fmts: vec!["s16".to_string(), "flt".to_string()],
+                    sample_rates: vec![48000, 24000],
+                    channel_layouts: vec!["mono".to_string(), "stereo".to_string()],
+                    ..Default::default()
+                },
+            ),
+            encoder("ffv1", CodecInfo::default()),
+        ]
+    }
+
+    fn warnings(line: &str) -> Vec<String> {
+        let cmd = FfmpegCommand::parse(line).unwrap();
+        check_command(&cmd, &catalog())
+            .into_iter()
+            .map(|i| format!("{} {}", i.option, i.issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn supported_formats_pass() {
+        assert!(warnings(
+            "ffmpeg -i in.mkv -c:v libx264 -pix_fmt +yuv420p -c:a libopus -ar 48000 \
+             -sample_fmt flt -ch_layout stereo out.mkv"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn unsupported_formats_warn() {
+        assert_eq!(
+            warnings(
+                "ffmpeg -i in.mkv -c:v libx264 -pix_fmt rgb24 -c:a libopus -ar 44100 \
+                 -ch_layout 5.1 out.mkv"
+            ),
+            vec![
+                "-pix_fmt Encoder 'libx264' doesn't support pixel format 'rgb24'",
+                "-ar Encoder 'libopus' doesn't support sample rate '44100'",
+                "-ch_layout Encoder 'libopus' doesn't support channel layout '5.1'",
+            ]
+        );
+    }
+
+    #[test]
+    fn stream_specifiers_pick_the_encoder() {
+        // The audio encoder lists no pixel formats, the video one no sample rates
+        assert_eq!(
+            warnings(
+                "ffmpeg -i in.mkv -c:v libx264 -c:a libopus -pix_fmt:v yuv444p -ar:a 24000 out.mkv"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            warnings("ffmpeg -i in.mkv -vcodec libx264 -pix_fmt:v:0 gray out.mkv").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn unrestricted_and_unknown_encoders_are_skipped() {
+        assert!(warnings("ffmpeg -i in.mkv -c:v ffv1 -pix_fmt gray16le out.mkv").is_empty());
+        assert!(warnings("ffmpeg -i in.mkv -c:v libx265 -pix_fmt gray out.mkv").is_empty());
+        assert!(warnings("ffmpeg -i in.mkv -c copy -pix_fmt gray out.mkv").is_empty());
+    }
+
+    fn option(flag: &str, kind: &str) -> OptionEntry {
+        OptionEntry {
+            flag: flag.to_string(),
+            r#type: Some(kind.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn filter(name: &str, inputs: usize, outputs: usize, options: Vec<OptionEntry>) -> Node {
+        let pads = |n| (0..n).map(|_| FilterPad::default()).collect();
+        Node {
+            name: name.to_string(),
+            pcategory: "filters".to_string(),
+            options,
+            filter: Some(FilterInfo {
+                inputs: pads(inputs),
+                outputs: pads(outputs),
+                timeline: name != "scale",
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn filters() -> Vec<Node> {
+        let brightness = OptionEntry {
+            min: Some(-1.0),
+            max: Some(1.0),
+            ..option("brightness", "<float>")
+        };
+        let eof_action = OptionEntry {
+            enum_vals: vec!["repeat".into(), "endall".into(), "pass".into()],
+            ..option("eof_action", "<enum>")
+        };
+        let mut split = filter("split", 1, 0, vec![option("outputs", "<int>")]);
+        split.filter.as_mut().unwrap().dynamic_outputs = true;
+        vec![
+            filter(
+                "scale",
+                1,
+                1,
+                vec![option("w", "<string>"), option("h", "<string>")],
+            ),
+            filter("eq", 1, 1, vec![brightness]),
+            filter(
+                "overlay",
+                2,
+                1,
+                vec![option("x", "<string>"), option("y", "<string>"), eof_action],
+            ),
+            split,
+            filter("nullsink", 1, 0, Vec::new()),
+        ]
+    }
+
+    /// Severity, message and the text the span covers, of each issue of a simple graph or,
+    /// with `inputs`, of a complex one whose `[out]` is mapped.
+    fn graph_issues(text: &str, inputs: Option<usize>) -> Vec<(Severity, String, String)> {
+        let ctx = inputs.map(|input_count| GraphContext {
+            input_count,
+            mapped: HashSet::from(["out"]),
+        });
+        let issues = match parse_filtergraph(text) {
+            Ok(graph) => validate_filtergraph(&graph, &filters(), ctx.as_ref()),
+            Err(issue) => vec![issue],
+        };
+        issues
+            .into_iter()
+            .map(|i| {
+                let spanned = i.span.map_or("", |s| &text[s.start..s.end]);
+                (i.severity, i.message, spanned.to_string())
+            })
+            .collect()
+    }
+
+    fn error(message: &str, spanned: &str) -> (Severity, String, String) {
+        (Severity::Error, message.to_string(), spanned.to_string())
+    }
+
+    #[test]
+    fn bad_option_values() {
+        assert_eq!(
+            graph_issues("scale=w=1280:h=-2,eq=brightness=1.5", None),
+            [error(
+                "brightness: 1.5 is out of range (from -1 to 1)",
+                "1.5"
+            )]
+        );
+        assert_eq!(
+            graph_issues("[0:v][1:v]overlay=x=10:eof_action=loop[out]", Some(2)),
+            [error(
+                "eof_action: 'loop' is not one of repeat, endall, pass",
+                "loop"
+            )]
+        );
+        // Expressions, values from files and unknown options aren't judged
+        assert!(graph_issues("eq=brightness='sin(t)/2'", None).is_empty());
+        assert!(graph_issues("eq=/brightness=level.txt", None).is_empty());
+        assert_eq!(
+            graph_issues("scale=width=1280", None),
+            [(
+                Severity::Warning,
+                "Option 'width' is not listed for filter 'scale'".to_string(),
+                "width".to_string()
+            )]
+        );
+        // Positional values have no name to look up
+        assert!(graph_issues("scale=1280:-2", None).is_empty());
+    }
+
+    #[test]
+    fn unknown_filters() {
+        let issues = graph_issues("scale=640:-2,sclae=320:-2", None);
+        assert_eq!(issues, [error("Unknown filter 'sclae'", "sclae")]);
+
+        // Without a catalog nothing is unknown
+        let graph = parse_filtergraph("sclae=320:-2").unwrap();
+        assert!(validate_filtergraph(&graph, &[], None).is_empty());
+    }
+
+    #[test]
+    fn wrong_pad_counts() {
+        assert_eq!(
+            graph_issues("[0:v][1:v][2:v]overlay[out]", Some(3)),
+            [error(
+                "Filter 'overlay' takes 2 input(s) but is given 3",
+                "overlay"
+            )]
+        );
+        // The unlabeled output of scale is carried into eq next to [1:v]
+        assert_eq!(
+            graph_issues("[0:v]scale=640:-2,[1:v]eq[out]", Some(2)),
+            [error("Filter 'eq' takes 1 input(s) but is given 2", "eq")]
+        );
+        assert_eq!(
+            graph_issues("[0:v]scale=640:-2[out][extra]", Some(1)),
+            [
+                error("Filter 'scale' has 1 output(s) but 2 labels", "scale"),
+                error(
+                    "Output [extra] is never used, map it with -map \"[extra]\"",
+                    "[extra]"
+                ),
+            ]
+        );
+        // Dynamic pads take any count
+        assert!(graph_issues(
+            "[0:v]split=3[a][b][c];[a][b]overlay,[c]overlay[out]",
+            Some(1)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn label_mistakes() {
+        assert_eq!(
+            graph_issues("[0:v]split[a][a];[a]nullsink;[b][2:v]overlay[out]", Some(2)),
+            [
+                error("Label [a] is produced more than once", "[a]"),
+                error("Label [b] is not produced by any filter", "[b]"),
+                error(
+                    "Input #2 doesn't exist, the command has 2 input(s)",
+                    "[2:v]"
+                ),
+            ]
+        );
+        // A simple graph may leave its labels open
+        assert!(graph_issues("[in]scale=640:-2[scaled]", None).is_empty());
+    }
+
+    #[test]
+    fn timeline_options() {
+        assert_eq!(
+            graph_issues("scale=640:-2:enable='gte(t,1)'", None),
+            [error(
+                "Filter 'scale' doesn't support timeline editing",
+                "enable"
+            )]
+        );
+        let issues = graph_issues("eq=brightness=0.1:enable='between(t,1,5'", None);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].1.starts_with("Unbalanced '('"), "{:?}", issues[0]);
+        assert!(graph_issues("eq=brightness=0.1:enable='between(t,1,5)'", None).is_empty());
+    }
+
+    #[test]
+    fn spans_are_byte_ranges() {
+        // Multibyte text before the issue shifts the span by bytes, not characters
+        let text = "drawtext=text='é':x=1,sclae";
+        let graph = parse_filtergraph(text).unwrap();
+        let issues = validate_filtergraph(&graph, &filters(), None);
+        let unknown = issues.iter().find(|i| i.message.contains("sclae")).unwrap();
+        assert_eq!(unknown.span, Some(Span { start: 23, end: 28 }));
+        assert_eq!(&text[23..28], "sclae");
+
+        // Issues come sorted by position
+        let starts: Vec<usize> = issues
+            .iter()
+            .filter_map(|i| i.span.map(|s| s.start))
+            .collect();
+        assert!(starts.windows(2).all(|w| w[0] <= w[1]), "{starts:?}");
+        assert_eq!(issues[0].message, "Unknown filter 'drawtext'");
+    }
+
+    #[test]
+    fn syntax_errors_point_at_the_text() {
+        // (graph, message, span)
+        let cases = [
+            ("[0:v]scale=640:-2[out", "Missing ']' after label", 17..21),
+            ("scale=w='640:h=-2", "Unterminated quote", 8..17),
+            ("[0:v][]scale", "Empty label", 5..7),
+            // Empty, where the name should be
+            ("[0:v];scale", "Missing filter name", 5..5),
+        ];
+        for (text, message, range) in cases {
+            let issue = parse_filtergraph(text).unwrap_err();
+            assert_eq!(issue.message, message, "{text}");
+            assert_eq!(
+                issue.span,
+                Some(Span {
+                    start: range.start,
+                    end: range.end
+                }),
+                "{text}"
+            );
+        }
+    }
+}
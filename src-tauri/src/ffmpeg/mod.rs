@@ -1,3 +1,11 @@
+pub mod command;
+pub mod compare;
 pub mod executor;
+pub mod expand;
+pub mod filtergraph;
 pub mod parser;
+pub mod preview_cache;
+pub mod probe;
+pub mod validate;
 pub mod version;
+pub mod waveform;
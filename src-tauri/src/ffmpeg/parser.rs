@@ -1,10 +1,16 @@
-use crate::workflow::types::{Node, OptionEntry};
-use anyhow::Result;
+use crate::workflow::types::{
+    Catalog, CategoryReport, CodecInfo, DeviceInfo, FilterInfo, FilterPad, MediaType, Node,
+    OptionEntry,
+};
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -14,6 +20,16 @@ pub fn apply_env<'a>(cmd: &'a mut Command, env_map: &HashMap<String, String>) ->
     cmd.envs(env_map.iter());
     cmd
 }
+/// Like [`apply_env`], but forces the C locale so help output parses the same on every system.
+pub fn apply_catalog_env<'a>(
+    cmd: &'a mut Command,
+    env_map: &HashMap<String, String>,
+) -> &'a mut Command {
+    apply_env(cmd, env_map);
+    cmd.env("LC_ALL", "C");
+    cmd
+}
+
 pub fn parse_env_map(env_str: &str) -> HashMap<String, String> {
     let mut m = HashMap::new();
     for line in env_str.lines() {
@@ -27,99 +43,202 @@ pub fn parse_env_map(env_str: &str) -> HashMap<String, String> {
     m
 }
 
-pub fn parse_ffmpeg(ffmpeg: &str, env_str: &str) -> Result<Vec<Node>> {
-    let env_map = parse_env_map(env_str);
-    let acc: Arc<Mutex<Vec<Node>>> = Arc::new(Mutex::new(Vec::new()));
-
-    let jobs: Vec<_> = vec![
-        ("filter", 8),
-        ("encoder", 10),
-        ("decoder", 10),
-        ("muxer", 4),
-        ("demuxer", 4),
-        //("bsf", 3),
-    ];
+type CatalogParser = fn(&str, &HashMap<String, String>) -> Result<Vec<Node>>;
+type CatalogJob<'a> = Box<dyn Fn() -> Result<Vec<Node>> + Send + Sync + 'a>;
+
+/// Parses every catalog category on its own thread. A failing category doesn't fail
+/// the whole catalog, it is recorded in the report instead.
+pub fn parse_ffmpeg(ffmpeg: &str, env_str: &str) -> Result<Catalog> {
+    parse_ffmpeg_with(ffmpeg, env_str, default_processes())
+}
 
-    let mut handles = Vec::new();
+/// [`parse_ffmpeg`] running at most `processes` per-item help processes at once, across
+/// all categories.
+pub fn parse_ffmpeg_with(ffmpeg: &str, env_str: &str, processes: usize) -> Result<Catalog> {
+    let mut catalog = Catalog::default();
+    parse_into(&mut catalog, ffmpeg, env_str, processes, None);
+    Ok(catalog)
+}
 
-    for (name, header) in jobs {
-        let ff = ffmpeg.to_string();
-        let envc = env_map.clone();
-        let accc = Arc::clone(&acc);
-        let h = thread::spawn(move || {
-            if let Ok(nodes) = parse_general(&ff, name, header, &envc) {
-                accc.lock().unwrap().extend(nodes);
-            }
-        });
-        handles.push(h);
+/// Parses the categories that failed in `catalog` again, so a cached partial catalog
+/// only costs what is missing. Categories that still fail stay marked as failed.
+pub fn reparse_failed(ffmpeg: &str, env_str: &str, catalog: &mut Catalog) {
+    let failed: Vec<String> = catalog
+        .report
+        .failed()
+        .map(|c| c.category.clone())
+        .collect();
+    if !failed.is_empty() {
+        parse_into(catalog, ffmpeg, env_str, default_processes(), Some(&failed));
     }
+}
 
-    // sample_fmts
-    {
-        let ff = ffmpeg.to_string();
-        let envc = env_map.clone();
-        let accc = Arc::clone(&acc);
-        let h = thread::spawn(move || {
-            if let Ok(node) = parse_sample_fmts(&ff, &envc) {
-                accc.lock().unwrap().push(node);
-            }
-        });
-        handles.push(h);
+fn default_processes() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .clamp(2, 8)
+}
+
+/// Parses every category, or the ones in `only`, into `catalog`, replacing their
+/// earlier report entries.
+fn parse_into(
+    catalog: &mut Catalog,
+    ffmpeg: &str,
+    env_str: &str,
+    processes: usize,
+    only: Option<&[String]>,
+) {
+    let env_map = parse_env_map(env_str);
+    let env_map = &env_map;
+    let limit = ProcessLimit::new(processes);
+    let limit = &limit;
+
+    let mut jobs: Vec<(&str, CatalogJob)> = Vec::new();
+    for (category, name) in [
+        ("filters", "filter"),
+        ("encoders", "encoder"),
+        ("decoders", "decoder"),
+        ("muxers", "muxer"),
+        ("demuxers", "demuxer"),
+        ("bsfs", "bsf"),
+    ] {
+        jobs.push((
+            category,
+            Box::new(move || parse_general(ffmpeg, name, env_map, limit)),
+        ));
+    }
+    jobs.push((
+        "protocols",
+        Box::new(|| parse_protocols(ffmpeg, env_map, limit)),
+    ));
+    jobs.push((
+        "devices",
+        Box::new(|| parse_devices(ffmpeg, env_map, limit)),
+    ));
+    jobs.push((
+        "sample_fmts",
+        Box::new(|| parse_sample_fmts(ffmpeg, env_map).map(|n| vec![n])),
+    ));
+    jobs.push((
+        "pix_fmts",
+        Box::new(|| parse_pix_fmts(ffmpeg, env_map).map(|n| vec![n])),
+    ));
+    let catalogs: [(&str, CatalogParser); 5] = [
+        ("globals", parse_globals),
+        ("contexts", parse_contexts),
+        ("hwaccels", parse_hwaccels),
+        ("layouts", parse_layouts),
+        ("colors", parse_colors),
+    ];
+    for (category, parse) in catalogs {
+        jobs.push((category, Box::new(move || parse(ffmpeg, env_map))));
+    }
+    if let Some(only) = only {
+        jobs.retain(|(category, _)| only.iter().any(|o| o == category));
     }
 
-    // pix_fmts
-    {
-        let ff = ffmpeg.to_string();
-        let envc = env_map.clone();
-        let accc = Arc::clone(&acc);
-        let h = thread::spawn(move || {
-            if let Ok(node) = parse_pix_fmts(&ff, &envc) {
-                accc.lock().unwrap().push(node);
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .iter()
+            .map(|(category, job)| {
+                let handle = scope.spawn(move || {
+                    let start = Instant::now();
+                    let result = job();
+                    (result, start.elapsed())
+                });
+                (*category, handle)
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|(category, handle)| match handle.join() {
+                Ok((result, elapsed)) => (category, result, elapsed),
+                Err(_) => (
+                    category,
+                    Err(anyhow!("parser thread panicked")),
+                    Duration::ZERO,
+                ),
+            })
+            .collect()
+    });
+
+    for (category, result, elapsed) in results {
+        let mut entry = CategoryReport {
+            category: category.to_string(),
+            ok: result.is_ok(),
+            duration_ms: elapsed.as_millis() as u64,
+            ..CategoryReport::default()
+        };
+        match result {
+            Ok(nodes) => {
+                entry.count = nodes.len();
+                catalog.nodes.extend(nodes);
             }
-        });
-        handles.push(h);
+            Err(e) => {
+                log::warn!("Failed to parse ffmpeg {category}: {e:#}");
+                entry.error = Some(format!("{e:#}"));
+            }
+        }
+        let report = &mut catalog.report.categories;
+        match report.iter_mut().find(|c| c.category == category) {
+            Some(existing) => *existing = entry,
+            None => report.push(entry),
+        }
     }
 
-    // globals
-    {
-        let ff = ffmpeg.to_string();
-        let envc = env_map.clone();
-        let accc = Arc::clone(&acc);
-        let h = thread::spawn(move || {
-            if let Ok(nodes) = parse_globals(&ff, &envc) {
-                accc.lock().unwrap().extend(nodes);
-            }
-        });
-        handles.push(h);
+    attach_named_values(&mut catalog.nodes);
+}
+
+/// Fills `<pix_fmt>`, `<sample_fmt>` and `<channel_layout>` options with the names
+/// listed by the matching catalog node, so the graph can offer them as choices.
+fn attach_named_values(nodes: &mut [Node]) {
+    let mut lists: HashMap<&str, Vec<String>> = HashMap::new();
+    for (node_name, type_name) in [
+        ("pixel format", "<pix_fmt>"),
+        ("sample format", "<sample_fmt>"),
+        ("channel layout", "<channel_layout>"),
+    ] {
+        let values = nodes
+            .iter()
+            .find(|n| !n.is_av_option && n.name == node_name)
+            .and_then(|n| n.options.first())
+            .map(|o| o.enum_vals.clone())
+            .unwrap_or_default();
+        lists.insert(type_name, values);
     }
 
-    // contexts
+    for opt in nodes
+        .iter_mut()
+        .filter(|n| n.is_av_option)
+        .flat_map(|n| n.options.iter_mut())
     {
-        let ff = ffmpeg.to_string();
-        let envc = env_map.clone();
-        let accc = Arc::clone(&acc);
-        let h = thread::spawn(move || {
-            if let Ok(nodes) = parse_contexts(&ff, &envc) {
-                accc.lock().unwrap().extend(nodes);
-            }
-        });
-        handles.push(h);
+        let Some(values) = opt.r#type.as_deref().and_then(|t| lists.get(t)) else {
+            continue;
+        };
+        if opt.enum_vals.is_empty() {
+            opt.enum_vals = values.clone();
+        }
     }
+}
 
-    for h in handles {
-        let _ = h.join();
+/// Error for a catalog command that exited with a failure, with the end of its stderr.
+/// A build that doesn't know a flag still prints the header, so the output alone would
+/// pass for an empty category.
+fn exit_error(out: &Output) -> anyhow::Error {
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let lines: Vec<&str> = stderr.lines().filter(|l| !l.trim().is_empty()).collect();
+    let tail = lines[lines.len().saturating_sub(3)..].join("\n");
+    if tail.is_empty() {
+        anyhow!("ffmpeg exited with {}", out.status)
+    } else {
+        anyhow!("ffmpeg exited with {}: {tail}", out.status)
     }
-
-    let out = Arc::try_unwrap(acc).unwrap().into_inner().unwrap();
-    Ok(out)
 }
 
-fn parse_general(
-    ffmpeg: &str,
-    name: &str,
-    header_size: usize,
-    env_map: &HashMap<String, String>,
-) -> Result<Vec<Node>> {
+/// Runs `ffmpeg <args> -hide_banner` and returns stdout.
+fn run_listing(ffmpeg: &str, args: &[&str], env_map: &HashMap<String, String>) -> Result<String> {
     let mut cmd = Command::new(ffmpeg);
     #[cfg(windows)]
     {
@@ -128,232 +247,594 @@ fn parse_general(
         cmd.creation_flags(CREATE_NO_WINDOW);
     }
 
-    cmd.arg(format!("-{name}s"))
+    cmd.args(args)
         .arg("-hide_banner")
         .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
+        .stderr(Stdio::piped());
+    apply_catalog_env(&mut cmd, env_map);
     let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
-    let mut lines: Vec<&str> = text.lines().collect();
-    if lines.len() > header_size {
-        lines.drain(..header_size);
-    } else {
-        lines.clear();
+    if !out.status.success() {
+        return Err(exit_error(&out).context(args.join(" ")));
     }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
 
-    let section_re = Regex::new(r"^(.+?)\s+AVOptions:\s*$").unwrap();
-    let header_re: Regex = Regex::new(r"\s<[^>]+>\s").unwrap();
-    let timeline_re: Regex = Regex::new(r"(?i)(timeline.*support|enable.*option)").unwrap();
-    let re = Regex::new(r"^\s*-?[\w\d_][\w\d_-]*\s*(<\w+>|\d+)?\s+[A-Z.]*\s+.*$").unwrap();
-
-    let mut nodes = Vec::new();
-    for line in lines {
-        if line.trim().is_empty() {
-            continue;
+/// Splits a listing such as `-encoders` or `-pix_fmts` into (flags, columns) per entry.
+///
+/// Listings with a flag legend end it with a dashed line as wide as the flag column
+/// (" ------", " --", "-----"), so the flags are cut at that width; they may contain
+/// spaces, e.g. "D d" for devices in `-formats`. Listings without one (`-filters`,
+/// `-bsfs`) skip the title and "X = meaning" legend lines, and take the first column
+/// as flags when `has_flags` is set.
+fn listing_entries(text: &str, has_flags: bool) -> Vec<(String, Vec<&str>)> {
+    let lines: Vec<&str> = text.lines().collect();
+    let separator = lines.iter().position(|l| {
+        let t = l.trim();
+        t.len() >= 2 && t.chars().all(|c| c == '-')
+    });
+
+    let mut entries = Vec::new();
+    match separator {
+        Some(sep) => {
+            let line = lines[sep];
+            let indent = line.len() - line.trim_start().len();
+            let width = line.trim().len();
+            for line in &lines[sep + 1..] {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let flags = line.get(indent..indent + width).unwrap_or_default();
+                let rest = line.get(indent + width..).unwrap_or_default();
+                entries.push((flags.trim().to_string(), rest.split_whitespace().collect()));
+            }
         }
-        let words: Vec<&str> = line.split_whitespace().collect();
-        if words.is_empty() {
-            continue;
+        None => {
+            let body = lines.iter().skip_while(|l| {
+                let t = l.trim();
+                t.is_empty() || t.ends_with(':') || t.contains(" = ")
+            });
+            for line in body {
+                let mut words: Vec<&str> = line.split_whitespace().collect();
+                if words.is_empty() {
+                    continue;
+                }
+                let flags = if has_flags {
+                    words.remove(0).to_string()
+                } else {
+                    String::new()
+                };
+                entries.push((flags, words));
+            }
         }
+    }
+    entries
+}
 
-        let global_name = if words.len() == 1 {
-            words.first().unwrap_or(&"").to_string()
-        } else {
-            words.get(1).unwrap_or(&"").to_string()
+fn parse_general(
+    ffmpeg: &str,
+    name: &str,
+    env_map: &HashMap<String, String>,
+    limit: &ProcessLimit,
+) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &[&format!("-{name}s")], env_map)?;
+
+    let mut items: Vec<(String, String, Option<FilterInfo>)> = Vec::new();
+    for (flags, words) in listing_entries(&text, name != "bsf") {
+        let Some(global_name) = words.first() else {
+            continue;
         };
-        let global_desc = if words.len() == 1 {
-            "No description".to_string()
-        } else {
-            words.iter().skip(3).cloned().collect::<Vec<_>>().join(" ")
+        // Filters have an extra pad column: " TSC scale  V->V  Scale the input..."
+        let desc_start = if name == "filter" { 2 } else { 1 };
+        let global_desc = match words.get(desc_start..) {
+            Some(desc) if !desc.is_empty() => desc.join(" "),
+            _ => "No description".to_string(),
+        };
+        let filter = match (name, words.get(1)) {
+            ("filter", Some(io)) => parse_filter_listing(&flags, io),
+            _ => None,
         };
+        items.push((global_name.to_string(), global_desc, filter));
+    }
+
+    let names: Vec<&str> = items.iter().map(|(n, _, _)| n.as_str()).collect();
+    let helps = fetch_item_help(ffmpeg, name, &names, env_map, limit)?;
+
+    let mut nodes = Vec::new();
+    for ((global_name, global_desc, filter), help_text) in items.iter().zip(helps) {
+        nodes.extend(parse_item_help(
+            name,
+            global_name,
+            global_desc,
+            filter.as_ref(),
+            &help_text,
+        ));
+    }
+    Ok(nodes)
+}
 
-        // Obtain per-item help
-        let mut help_cmd = Command::new(ffmpeg);
-        apply_env(&mut help_cmd, env_map);
+fn media_type(s: &str) -> MediaType {
+    match s {
+        "V" | "video" => MediaType::Video,
+        "A" | "audio" => MediaType::Audio,
+        _ => MediaType::Unknown,
+    }
+}
 
-        #[cfg(windows)]
-        {
-            // Prevent a new terminal from appearing
-            const CREATE_NO_WINDOW: u32 = 0x08000000;
-            help_cmd.creation_flags(CREATE_NO_WINDOW);
+/// Parses the flag and pad columns of `ffmpeg -filters`, e.g. `TSC` and `VV->V`.
+/// `N` means a dynamic number of pads, `|` a source or sink.
+fn parse_filter_listing(flags: &str, io: &str) -> Option<FilterInfo> {
+    let (inputs, outputs) = io.split_once("->")?;
+    let pads = |side: &str| -> (Vec<FilterPad>, bool) {
+        match side {
+            "|" => (Vec::new(), false),
+            "N" => (Vec::new(), true),
+            _ => (
+                side.chars()
+                    .map(|c| FilterPad {
+                        name: String::new(),
+                        media: media_type(&c.to_string()),
+                    })
+                    .collect(),
+                false,
+            ),
         }
+    };
+    let (inputs, dynamic_inputs) = pads(inputs);
+    let (outputs, dynamic_outputs) = pads(outputs);
+
+    Some(FilterInfo {
+        inputs,
+        outputs,
+        dynamic_inputs,
+        dynamic_outputs,
+        timeline: flags.contains('T'),
+        slice_threading: flags.contains('S'),
+        command: flags.contains('C'),
+    })
+}
 
-        help_cmd
-            .args(["-h", &format!("{name}={global_name}"), "-hide_banner"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+/// Refines pads with the names and types from the "Inputs:"/"Outputs:" block of
+/// `-h filter=NAME`. Keeps the listing's pads if the block is missing.
+fn parse_filter_pads(help_text: &str, info: &mut FilterInfo) {
+    let mut side: Option<bool> = None; // Some(true) = inputs
+    let mut inputs: Option<(Vec<FilterPad>, bool)> = None;
+    let mut outputs: Option<(Vec<FilterPad>, bool)> = None;
 
-        let help = help_cmd.output().unwrap();
-        let help_text = if help.stdout.is_empty() {
-            &help.stderr
-        } else {
-            &help.stdout
+    for line in help_text.lines() {
+        let trimmed = line.trim();
+        if SECTION_RE.is_match(line) {
+            break;
+        }
+        match trimmed {
+            "Inputs:" => {
+                side = Some(true);
+                inputs = Some((Vec::new(), false));
+                continue;
+            }
+            "Outputs:" => {
+                side = Some(false);
+                outputs = Some((Vec::new(), false));
+                continue;
+            }
+            _ => {}
+        }
+
+        let target = match side {
+            Some(true) => inputs.as_mut(),
+            Some(false) => outputs.as_mut(),
+            None => None,
         };
-        let full_desc: Vec<String> = String::from_utf8_lossy(help_text)
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
+        let Some((pads, dynamic)) = target else {
+            continue;
+        };
+        if let Some(c) = PAD_RE.captures(trimmed) {
+            pads.push(FilterPad {
+                name: c[1].to_string(),
+                media: media_type(&c[2]),
+            });
+        } else if trimmed.starts_with("dynamic") {
+            *dynamic = true;
+        } else if !trimmed.starts_with("none") {
+            side = None;
+        }
+    }
 
-        let filtered: Vec<&str> = full_desc
-            .iter()
-            .skip_while(|s| !section_re.is_match(s))
-            .map(|s| s.as_str())
-            .collect();
+    if let Some((pads, dynamic)) = inputs {
+        info.inputs = pads;
+        info.dynamic_inputs = dynamic;
+    }
+    if let Some((pads, dynamic)) = outputs {
+        info.outputs = pads;
+        info.dynamic_outputs = dynamic;
+    }
+}
 
-        let mut is_main_node = true;
-        let mut is_dup_fields = false;
-        let mut current_node: Option<Node> = None;
-        let mut current_opt: Option<OptionEntry> = None;
-        let mut repeated_opt: HashSet<String> = HashSet::new();
+fn run_item_help(
+    ffmpeg: &str,
+    name: &str,
+    item: &str,
+    env_map: &HashMap<String, String>,
+) -> Result<String> {
+    let mut help_cmd = Command::new(ffmpeg);
+    apply_catalog_env(&mut help_cmd, env_map);
 
-        for opt_line in filtered.iter() {
-            if section_re.is_match(opt_line) {
-                if let Some(mut prev) = current_node.take() {
-                    if let Some(opt) = current_opt.take() {
-                        prev.options.push(opt);
-                    }
+    #[cfg(windows)]
+    {
+        // Prevent a new terminal from appearing
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        help_cmd.creation_flags(CREATE_NO_WINDOW);
+    }
 
-                    // --- Add "enable" option if timeline support is mentioned ---
-                    if timeline_re.is_match(&String::from_utf8_lossy(help_text)) {
-                        let opt = OptionEntry {
-                            flag: "enable".to_string(),
-                            no_args: false,
-                            desc: Some("Enable timeline support for this filter".to_string()),
-                            ..OptionEntry::default()
-                        };
-                        prev.options.push(opt);
-                    }
-                    nodes.push(prev);
-                }
+    help_cmd
+        .args(["-h", &format!("{name}={item}"), "-hide_banner"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
 
-                let node = Node {
-                    name: if is_main_node {
-                        global_name.clone()
-                    } else {
-                        opt_line.split_whitespace().take(1).collect()
-                    },
-                    is_av_option: is_main_node,
-                    desc: global_desc.clone(),
-                    category: String::new(),
-                    pcategory: format!("{name}s"),
-                    full_desc: full_desc.clone(),
-                    options: Vec::new(),
-                };
+    let help = help_cmd.output()?;
+    if !help.status.success() {
+        return Err(exit_error(&help));
+    }
+    let help_text = if help.stdout.is_empty() {
+        &help.stderr
+    } else {
+        &help.stdout
+    };
+    Ok(String::from_utf8_lossy(help_text).into_owned())
+}
 
-                current_node = Some(node);
-                repeated_opt.clear();
-                is_main_node = false;
-            } else if re.is_match(opt_line) {
-                if let Some(n) = &mut current_node {
-                    if header_re.is_match(opt_line) {
-                        // New flag line: "-flag <type> category ..."
-                        let parts: Vec<&str> = opt_line.split_whitespace().collect();
-                        if parts.len() > 2 {
-                            is_dup_fields = false;
-                            // Not all args have description
-                            if parts.len() > 3 {
-                                let desc: String =
-                                    parts.iter().skip(3).fold(String::new(), |acc, s| acc + s);
-                                if repeated_opt.contains(&desc) {
-                                    is_dup_fields = true;
-                                    continue;
-                                } else {
-                                    repeated_opt.insert(desc);
-                                }
-                            }
+/// Runs `-h name=item` for every item on a bounded pool of worker threads.
+/// ffmpeg exits after the first `-h`, so each item still needs its own process,
+/// but they no longer run one after another. Results keep the order of `items`.
+fn fetch_item_help(
+    ffmpeg: &str,
+    name: &str,
+    items: &[&str],
+    env_map: &HashMap<String, String>,
+    limit: &ProcessLimit,
+) -> Result<Vec<String>> {
+    run_pool(items, limit, |item| {
+        run_item_help(ffmpeg, name, item, env_map)
+            .map_err(|e| e.context(format!("-h {name}={item}")))
+    })
+}
 
-                            if let Some(prev) = current_opt.take() {
-                                n.options.push(prev);
-                            }
-                            let opt = OptionEntry {
-                                flag: parts[0].to_string(),
-                                r#type: Some(parts[1].to_string()),
-                                category: Some(parts[2].to_string()),
-                                enum_vals: Vec::new(),
-                                ..OptionEntry::default()
-                            };
+/// Counting semaphore shared by the pools of every category, so a catalog parse never
+/// runs more than `permits` help processes at once.
+struct ProcessLimit {
+    permits: usize,
+    free: Mutex<usize>,
+    released: Condvar,
+}
 
-                            n.category = if n.category.is_empty() {
-                                parts[2].to_string()
-                            } else if n.category != parts[2] {
-                                "~".to_string()
-                            } else {
-                                n.category.to_string()
-                            };
+/// Returned to its [`ProcessLimit`] when dropped, panics included.
+struct Permit<'a>(&'a ProcessLimit);
 
-                            current_opt = Some(opt);
-                        }
-                    } else if !is_dup_fields {
-                        if let Some(ref mut opt) = current_opt {
-                            if opt.r#type.as_deref() == Some("<flags>") {
-                                match &mut opt.desc {
-                                    Some(desc) => desc.push_str(&format!("<br>{opt_line}")),
-                                    None => opt.desc = Some(opt_line.to_string()),
-                                };
-                            } else if let Some(enum_val) = opt_line.split_whitespace().next() {
-                                opt.enum_vals.push(enum_val.to_string());
-                                opt.r#type = Some("<enum>".to_string());
-                                opt.no_args = true;
-                            }
-                        }
+impl ProcessLimit {
+    fn new(permits: usize) -> Self {
+        let permits = permits.max(1);
+        Self {
+            permits,
+            free: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut free = self.free.lock().unwrap();
+        while *free == 0 {
+            free = self.released.wait(free).unwrap();
+        }
+        *free -= 1;
+        Permit(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap() += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// Calls `f` for every item on a bounded pool of worker threads and stops at the
+/// first error. Each call holds a permit of `limit`. Results keep the order of `items`.
+fn run_pool<T, F>(items: &[&str], limit: &ProcessLimit, f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&str) -> Result<T> + Sync,
+{
+    let workers = limit.permits.min(items.len());
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new(items.iter().map(|_| None).collect());
+    let failure: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() || failure.lock().unwrap().is_some() {
+                    break;
+                }
+                let permit = limit.acquire();
+                let result = f(items[i]);
+                drop(permit);
+                match result {
+                    Ok(value) => results.lock().unwrap()[i] = Some(value),
+                    Err(e) => {
+                        *failure.lock().unwrap() = Some(e);
+                        break;
                     }
                 }
-            }
+            });
         }
-        if let Some(mut n) = current_node.take() {
-            if let Some(opt) = current_opt.take() {
-                n.options.push(opt);
-            }
-            // --- Add "enable" option if timeline support is mentioned ---
-            if timeline_re.is_match(&String::from_utf8_lossy(help_text)) {
-                let opt = OptionEntry {
-                    flag: "enable".to_string(),
-                    no_args: false,
-                    desc: Some("Enable timeline support for this filter".to_string()),
-                    ..OptionEntry::default()
-                };
+    });
 
-                n.options.push(opt);
-            }
+    if let Some(e) = failure.into_inner().unwrap() {
+        return Err(e);
+    }
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .zip(items)
+        .map(|(r, item)| r.ok_or_else(|| anyhow!("missing result for {item}")))
+        .collect()
+}
 
-            nodes.push(n);
-        }
+static SECTION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(.+?)\s+AVOptions:\s*$").unwrap());
+static HEADER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s<[^>]+>\s").unwrap());
+static PAD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#\d+: (\S+) \((\w+)\)").unwrap());
+static RANGE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\(from (\S+) to (\S+?)\)").unwrap());
+static DEFAULT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\(default (.*)\)\s*$").unwrap());
+static OPTION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*-?[\w\d_][\w\d_-]*\s*(<\w+>|\d+)?\s+[A-Z.]*\s+.*$").unwrap());
+
+/// Maps the symbolic limits ffmpeg prints (INT_MAX, -FLT_MAX, ...) to numbers.
+fn parse_limit(s: &str) -> Option<f64> {
+    let (negative, name) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let value = match name {
+        "INT_MAX" => i32::MAX as f64,
+        "INT_MIN" => i32::MIN as f64,
+        "UINT32_MAX" => u32::MAX as f64,
+        "I64_MAX" => i64::MAX as f64,
+        "I64_MIN" => i64::MIN as f64,
+        "UINT64_MAX" => u64::MAX as f64,
+        "FLT_MAX" => f32::MAX as f64,
+        "FLT_MIN" => f32::MIN_POSITIVE as f64,
+        "DBL_MAX" => f64::MAX,
+        "DBL_MIN" => f64::MIN_POSITIVE,
+        other => other.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Fills the typed fields of `opt` from its AVOption help line:
+/// `-flag <type> ED.VA...T. description (from MIN to MAX) (default VAL)`.
+fn parse_option_details(opt: &mut OptionEntry, scope: &str, line: &str) {
+    opt.encoding = scope.contains('E');
+    opt.decoding = scope.contains('D');
+    opt.filtering = scope.contains('F');
+    opt.video = scope.contains('V');
+    opt.audio = scope.contains('A');
+    opt.subtitle = scope.contains('S');
+    opt.runtime_param = scope.contains('T');
+
+    if let Some(c) = RANGE_RE.captures(line) {
+        opt.min = parse_limit(&c[1]);
+        opt.max = parse_limit(&c[2]);
+    }
+    if let Some(c) = DEFAULT_RE.captures(line) {
+        opt.default = Some(c[1].trim_matches('"').to_string());
+    }
+}
+
+/// Turns the output of `ffmpeg -h name=global_name` into the item's node plus one
+/// node per extra AVOptions section it prints.
+fn parse_item_help(
+    name: &str,
+    global_name: &str,
+    global_desc: &str,
+    filter: Option<&FilterInfo>,
+    help_text: &str,
+) -> Vec<Node> {
+    let global_name = global_name.to_string();
+    let global_desc = global_desc.to_string();
+
+    let mut nodes = Vec::new();
+    let full_desc: Vec<String> = help_text.lines().map(|s| s.to_string()).collect();
+
+    let filtered: Vec<&str> = full_desc
+        .iter()
+        .skip_while(|s| !SECTION_RE.is_match(s))
+        .map(|s| s.as_str())
+        .collect();
+
+    let mut is_main_node = true;
+    let mut is_dup_fields = false;
+    let mut current_node: Option<Node> = None;
+    let mut current_opt: Option<OptionEntry> = None;
+    let mut repeated_opt: HashSet<String> = HashSet::new();
+
+    for opt_line in filtered.iter() {
+        if SECTION_RE.is_match(opt_line) {
+            if let Some(mut prev) = current_node.take() {
+                if let Some(opt) = current_opt.take() {
+                    prev.options.push(opt);
+                }
+                nodes.push(prev);
+            }
 
-        if is_main_node {
-            //means no info about this node is exists.
             let node = Node {
-                name: global_name.clone(),
-                desc: global_desc.clone(),
+                name: if is_main_node {
+                    global_name.clone()
+                } else {
+                    opt_line.split_whitespace().take(1).collect()
+                },
                 is_av_option: is_main_node,
+                desc: global_desc.clone(),
                 category: String::new(),
                 pcategory: format!("{name}s"),
                 full_desc: full_desc.clone(),
                 options: Vec::new(),
+                filter: None,
+                device: None,
+                codec: None,
             };
-            nodes.push(node);
+
+            current_node = Some(node);
+            repeated_opt.clear();
+            is_main_node = false;
+        } else if OPTION_RE.is_match(opt_line) {
+            if let Some(n) = &mut current_node {
+                if HEADER_RE.is_match(opt_line) {
+                    // New flag line: "-flag <type> category ..."
+                    let parts: Vec<&str> = opt_line.split_whitespace().collect();
+                    if parts.len() > 2 {
+                        is_dup_fields = false;
+                        // Not all args have description
+                        if parts.len() > 3 {
+                            let desc: String =
+                                parts.iter().skip(3).fold(String::new(), |acc, s| acc + s);
+                            if repeated_opt.contains(&desc) {
+                                is_dup_fields = true;
+                                continue;
+                            } else {
+                                repeated_opt.insert(desc);
+                            }
+                        }
+
+                        if let Some(prev) = current_opt.take() {
+                            n.options.push(prev);
+                        }
+                        let mut opt = OptionEntry {
+                            flag: parts[0].to_string(),
+                            r#type: Some(parts[1].to_string()),
+                            category: Some(parts[2].to_string()),
+                            enum_vals: Vec::new(),
+                            ..OptionEntry::default()
+                        };
+                        parse_option_details(&mut opt, parts[2], opt_line);
+
+                        n.category = if n.category.is_empty() {
+                            parts[2].to_string()
+                        } else if n.category != parts[2] {
+                            "~".to_string()
+                        } else {
+                            n.category.to_string()
+                        };
+
+                        current_opt = Some(opt);
+                    }
+                } else if !is_dup_fields {
+                    if let Some(ref mut opt) = current_opt {
+                        if opt.r#type.as_deref() == Some("<flags>") {
+                            match &mut opt.desc {
+                                Some(desc) => desc.push_str(&format!("<br>{opt_line}")),
+                                None => opt.desc = Some(opt_line.to_string()),
+                            };
+                        } else if let Some(enum_val) = opt_line.split_whitespace().next() {
+                            opt.enum_vals.push(enum_val.to_string());
+                            opt.r#type = Some("<enum>".to_string());
+                            opt.no_args = true;
+                        }
+                    }
+                }
+            }
         }
     }
-    Ok(nodes)
+    if let Some(mut n) = current_node.take() {
+        if let Some(opt) = current_opt.take() {
+            n.options.push(opt);
+        }
+        nodes.push(n);
+    }
+
+    if is_main_node {
+        //means no info about this node is exists.
+        let node = Node {
+            name: global_name.clone(),
+            desc: global_desc.clone(),
+            is_av_option: is_main_node,
+            category: String::new(),
+            pcategory: format!("{name}s"),
+            full_desc: full_desc.clone(),
+            options: Vec::new(),
+            filter: None,
+            device: None,
+            codec: None,
+        };
+        nodes.push(node);
+    }
+
+    // The item's own node always comes first
+    if let (Some(info), Some(main)) = (filter, nodes.first_mut()) {
+        let mut info = info.clone();
+        parse_filter_pads(help_text, &mut info);
+        if info.timeline {
+            main.options.push(OptionEntry {
+                flag: "enable".to_string(),
+                no_args: false,
+                desc: Some("Enable timeline support for this filter".to_string()),
+                ..OptionEntry::default()
+            });
+        }
+        main.filter = Some(info);
+    }
+    if matches!(name, "encoder" | "decoder") {
+        if let Some(main) = nodes.first_mut() {
+            main.codec = Some(parse_codec_info(help_text));
+        }
+    }
+    nodes
 }
 
-fn parse_globals(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
-    let mut cmd = Command::new(ffmpeg);
-    let re = Regex::new(r" {2,}").unwrap(); // 2 or more literal spaces
+/// Reads the indented "Label: values" lines printed above the AVOptions sections, e.g.
+/// "    Supported pixel formats: yuv420p yuv422p".
+fn parse_codec_info(help_text: &str) -> CodecInfo {
+    let mut info = CodecInfo::default();
+    let list = |values: &str| -> Vec<String> {
+        values
+            .split_whitespace()
+            .filter(|v| *v != "none")
+            .map(str::to_string)
+            .collect()
+    };
 
-    #[cfg(windows)]
-    {
-        // Prevent a new terminal from appearing
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
+    for line in help_text.lines() {
+        if SECTION_RE.is_match(line) {
+            break;
+        }
+        let Some((label, values)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let values = values.trim();
+        match label {
+            "General capabilities" => info.capabilities = list(values),
+            "Threading capabilities" => info.threading = Some(values.to_string()),
+            "Supported pixel formats" => info.pix_fmts = list(values),
+            "Supported sample formats" => info.sample_fmts = list(values),
+            "Supported sample rates" => {
+                info.sample_rates = values
+                    .split_whitespace()
+                    .filter_map(|r| r.parse().ok())
+                    .collect()
+            }
+            "Supported channel layouts" => info.channel_layouts = list(values),
+            "Supported framerates" => info.framerates = list(values),
+            "Supported hardware devices" => info.hw_devices = list(values),
+            _ => {}
+        }
     }
+    info
+}
 
-    cmd.args(["-h", "long", "-hide_banner"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
-    let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
+fn parse_globals(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
+    let re = Regex::new(r" {2,}").unwrap(); // 2 or more literal spaces
+    let text = run_listing(ffmpeg, &["-h", "long"], env_map)?;
 
     let mut nodes: Vec<Node> = Vec::new();
     let mut lines = text.lines();
@@ -431,21 +912,7 @@ fn parse_globals(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<
 
 fn parse_contexts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
     // Execute ffmpeg command to get help output
-    let mut cmd = Command::new(ffmpeg);
-
-    #[cfg(windows)]
-    {
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
-    }
-
-    cmd.args(["-h", "full", "-hide_banner"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
-
-    let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
+    let text = run_listing(ffmpeg, &["-h", "full"], env_map)?;
 
     // Parse the output into nodes
     let mut nodes: Vec<Node> = Vec::new();
@@ -534,6 +1001,7 @@ fn parse_contexts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec
                                 let category = &after_type[..category_end];
                                 if !category.is_empty() {
                                     opt.category = Some(category.to_string());
+                                    parse_option_details(&mut opt, category, line);
                                 }
 
                                 // Extract description
@@ -585,66 +1053,219 @@ fn parse_contexts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec
     Ok(nodes)
 }
 
-fn parse_bsfs(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
-    let mut cmd = Command::new(ffmpeg);
-    #[cfg(windows)]
-    {
-        // Prevent a new terminal from appearing
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
+fn parse_protocols(
+    ffmpeg: &str,
+    env_map: &HashMap<String, String>,
+    limit: &ProcessLimit,
+) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &["-protocols"], env_map)?;
+
+    // "Input:" and "Output:" each list one protocol per line
+    let mut protocols: Vec<(String, bool, bool)> = Vec::new();
+    let mut is_input = true;
+    for line in text.lines().skip(1) {
+        match line.trim() {
+            "" => continue,
+            "Input:" => is_input = true,
+            "Output:" => is_input = false,
+            proto => match protocols.iter_mut().find(|(n, _, _)| n == proto) {
+                Some(entry) if is_input => entry.1 = true,
+                Some(entry) => entry.2 = true,
+                None => protocols.push((proto.to_string(), is_input, !is_input)),
+            },
+        }
     }
 
-    cmd.args(["-bsfs", "-hide_banner"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
-    let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
+    let names: Vec<&str> = protocols.iter().map(|(n, _, _)| n.as_str()).collect();
+    let helps = fetch_item_help(ffmpeg, "protocol", &names, env_map, limit)?;
 
     let mut nodes = Vec::new();
-    for line in text.lines().skip(1) {
-        if line.trim().is_empty() {
-            continue;
-        }
-        let words: Vec<&str> = line.split_whitespace().collect();
-        if words.is_empty() {
+    for ((proto, input, output), help_text) in protocols.iter().zip(helps) {
+        let desc = match (input, output) {
+            (true, true) => "Input/output protocol",
+            (true, false) => "Input protocol",
+            _ => "Output protocol",
+        };
+        nodes.extend(parse_item_help("protocol", proto, desc, None, &help_text));
+    }
+    Ok(nodes)
+}
+
+fn parse_devices(
+    ffmpeg: &str,
+    env_map: &HashMap<String, String>,
+    limit: &ProcessLimit,
+) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &["-devices"], env_map)?;
+
+    // " D  alsa            ALSA audio output"
+    let mut devices: Vec<(String, String, DeviceInfo)> = Vec::new();
+    for (flags, words) in listing_entries(&text, true) {
+        let [name, desc @ ..] = words.as_slice() else {
             continue;
-        }
-        let node = Node {
-            is_av_option: false,
-            name: words[0].to_string(),
-            desc: "No info".to_string(),
-            ..Node::default()
         };
+        let info = DeviceInfo {
+            input: flags.contains('D'),
+            output: flags.contains('E'),
+            ..DeviceInfo::default()
+        };
+        devices.push((name.to_string(), desc.join(" "), info));
+    }
 
-        nodes.push(node);
+    // There is no "-h device=", the options live on the demuxer or muxer
+    let items: Vec<String> = devices
+        .iter()
+        .map(|(name, _, info)| {
+            let kind = if info.input { "demuxer" } else { "muxer" };
+            format!("{kind}={name}")
+        })
+        .collect();
+    let items: Vec<&str> = items.iter().map(String::as_str).collect();
+    let helps = run_pool(&items, limit, |item| {
+        let (kind, name) = item.split_once('=').unwrap_or(("demuxer", item));
+        let help = run_item_help(ffmpeg, kind, name, env_map)
+            .map_err(|e| e.context(format!("-h {item}")))?;
+        Ok((
+            help,
+            list_endpoints(ffmpeg, "-sources", name, env_map),
+            list_endpoints(ffmpeg, "-sinks", name, env_map),
+        ))
+    })?;
+
+    let mut nodes = Vec::new();
+    for ((name, desc, mut info), (help_text, sources, sinks)) in devices.into_iter().zip(helps) {
+        if info.input {
+            info.sources = sources;
+        }
+        if info.output {
+            info.sinks = sinks;
+        }
+        let mut device_nodes = parse_item_help("device", &name, &desc, None, &help_text);
+        if let Some(main) = device_nodes.first_mut() {
+            main.device = Some(info);
+        }
+        nodes.extend(device_nodes);
     }
     Ok(nodes)
 }
 
-fn parse_pix_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Node> {
-    let mut cmd = Command::new(ffmpeg);
-    #[cfg(windows)]
-    {
-        // Prevent a new terminal from appearing
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
+/// Names auto-detected by `-sources NAME` or `-sinks NAME`. Most devices can't list
+/// anything, which is not an error.
+fn list_endpoints(
+    ffmpeg: &str,
+    flag: &str,
+    device: &str,
+    env_map: &HashMap<String, String>,
+) -> Vec<String> {
+    let Ok(text) = run_listing(ffmpeg, &[flag, device], env_map) else {
+        return Vec::new();
+    };
+    // "Auto-detected sources for pulse:" then "* name [description]"
+    text.lines()
+        .skip(1)
+        .filter_map(|l| {
+            let l = l.trim().trim_start_matches('*').trim();
+            let name = l.split(" [").next().unwrap_or(l).trim();
+            (!name.is_empty()).then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn parse_hwaccels(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &["-hwaccels"], env_map)?;
+
+    let option = OptionEntry {
+        category: Some(String::new()),
+        flag: "-hwaccel".to_string(),
+        no_args: true,
+        enum_vals: listing_entries(&text, false)
+            .into_iter()
+            .filter_map(|(_, words)| words.first().map(|w| w.to_string()))
+            .collect(),
+        ..OptionEntry::default()
+    };
+
+    Ok(vec![Node {
+        name: "hwaccel".to_string(),
+        desc: "Hardware acceleration methods".to_string(),
+        pcategory: "hwaccels".to_string(),
+        is_av_option: false,
+        options: vec![option],
+        ..Node::default()
+    }])
+}
+
+fn parse_layouts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &["-layouts"], env_map)?;
+
+    // "Individual channels:" and "Standard channel layouts:", each with a
+    // "NAME  DESCRIPTION"/"NAME  DECOMPOSITION" header
+    let mut in_layouts = false;
+    let mut option = OptionEntry {
+        category: Some(String::new()),
+        flag: "-ch_layout".to_string(),
+        no_args: true,
+        ..OptionEntry::default()
+    };
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("NAME") {
+            continue;
+        }
+        if trimmed.ends_with(':') {
+            in_layouts = trimmed.starts_with("Standard");
+            continue;
+        }
+        if in_layouts {
+            if let Some(layout) = trimmed.split_whitespace().next() {
+                option.enum_vals.push(layout.to_string());
+            }
+        }
     }
 
-    cmd.args(["-pix_fmts", "-hide_banner"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
-    let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
+    Ok(vec![Node {
+        name: "channel layout".to_string(),
+        desc: "Standard channel layouts".to_string(),
+        pcategory: "layouts".to_string(),
+        is_av_option: false,
+        full_desc: text.lines().map(str::to_string).collect(),
+        options: vec![option],
+        ..Node::default()
+    }])
+}
 
-    let mut lines: Vec<&str> = text.lines().collect();
-    if lines.len() > 8 {
-        lines.drain(..8);
-    } else {
-        lines.clear();
+fn parse_colors(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Vec<Node>> {
+    let text = run_listing(ffmpeg, &["-colors"], env_map)?;
+
+    // "name  #RRGGBB" header, then "AliceBlue                        #f0f8ff"
+    let mut option = OptionEntry {
+        category: Some(String::new()),
+        flag: "color".to_string(),
+        r#type: Some("<color>".to_string()),
+        ..OptionEntry::default()
+    };
+    for line in text.lines() {
+        if let [name, hex] = line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            if hex.starts_with('#') && *hex != "#RRGGBB" {
+                option.enum_vals.push(name.to_string());
+            }
+        }
     }
 
+    Ok(vec![Node {
+        name: "color".to_string(),
+        desc: "Named colors".to_string(),
+        pcategory: "colors".to_string(),
+        is_av_option: false,
+        full_desc: text.lines().map(str::to_string).collect(),
+        options: vec![option],
+        ..Node::default()
+    }])
+}
+
+fn parse_pix_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Node> {
+    let text = run_listing(ffmpeg, &["-pix_fmts"], env_map)?;
+
     let mut node = Node {
         name: "pixel format".to_string(),
         desc: "No info".to_string(),
@@ -659,13 +1280,10 @@ fn parse_pix_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Nod
         ..OptionEntry::default()
     };
 
-    for line in lines {
-        if line.trim().is_empty() {
-            continue;
-        }
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            option.enum_vals.push(parts[1].to_string());
+    // "IO... yuv420p                3             12      8-8-8"
+    for (_, parts) in listing_entries(&text, true) {
+        if let Some(fmt) = parts.first() {
+            option.enum_vals.push(fmt.to_string());
         }
     }
     node.options.push(option);
@@ -673,20 +1291,7 @@ fn parse_pix_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Nod
 }
 
 fn parse_sample_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<Node> {
-    let mut cmd = Command::new(ffmpeg);
-    #[cfg(windows)]
-    {
-        // Prevent a new terminal from appearing
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-        cmd.creation_flags(CREATE_NO_WINDOW);
-    }
-
-    cmd.args(["-sample_fmts", "-hide_banner"])
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null());
-    apply_env(&mut cmd, env_map);
-    let out = cmd.output()?;
-    let text = String::from_utf8_lossy(&out.stdout);
+    let text = run_listing(ffmpeg, &["-sample_fmts"], env_map)?;
 
     let mut node = Node {
         name: "sample format".to_string(),
@@ -702,15 +1307,587 @@ fn parse_sample_fmts(ffmpeg: &str, env_map: &HashMap<String, String>) -> Result<
         ..OptionEntry::default()
     };
 
-    for line in text.lines().skip(1) {
-        if line.trim().is_empty() {
-            continue;
-        }
+    // "name   depth" header, then "u8        8"
+    for line in text.lines() {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if let Some(fmt) = parts.first() {
-            option.enum_vals.push((*fmt).to_string());
+        if let [fmt, depth] = parts.as_slice() {
+            if depth.parse::<u32>().is_ok() {
+                option.enum_vals.push((*fmt).to_string());
+            }
         }
     }
     node.options.push(option);
     Ok(node)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Help output under `tests/fixtures/ffmpeg/`. The files are trimmed by hand to a few
+    /// entries each and follow the layout of ffmpeg 7.x, they weren't captured as a whole
+    /// from one build.
+    fn fixture(name: &str) -> String {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/ffmpeg", name]
+            .iter()
+            .collect();
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    fn names(entries: &[(String, Vec<&str>)]) -> Vec<String> {
+        entries
+            .iter()
+            .filter_map(|(_, words)| words.first().map(|w| w.to_string()))
+            .collect()
+    }
+
+    fn option<'a>(node: &'a Node, flag: &str) -> &'a OptionEntry {
+        node.options
+            .iter()
+            .find(|o| o.flag == flag)
+            .unwrap_or_else(|| panic!("{} has no {flag}", node.name))
+    }
+
+    #[test]
+    fn listing_entries_after_separator() {
+        let text = fixture("encoders.txt");
+        let entries = listing_entries(&text, true);
+        assert_eq!(
+            names(&entries),
+            [
+                "a64multi",
+                "libx264",
+                "h264_nvenc",
+                "mpeg4",
+                "aac",
+                "libopus",
+                "opus",
+                "srt"
+            ]
+        );
+        assert_eq!(entries[3].0, "VFS..D");
+        assert_eq!(entries[6].0, "A..X.D");
+        assert_eq!(
+            entries[4].1,
+            ["aac", "AAC", "(Advanced", "Audio", "Coding)"]
+        );
+    }
+
+    #[test]
+    fn listing_entries_skip_legend() {
+        let text = fixture("filters.txt");
+        let entries = listing_entries(&text, true);
+        assert_eq!(
+            names(&entries),
+            [
+                "abench",
+                "amix",
+                "anullsrc",
+                "concat",
+                "scale",
+                "scale2ref",
+                "split",
+                "nullsink"
+            ]
+        );
+        assert_eq!(entries[4].0, "TSC");
+        assert_eq!(entries[4].1[1], "V->V");
+
+        let text = fixture("bsfs.txt");
+        let entries = listing_entries(&text, false);
+        assert_eq!(
+            names(&entries),
+            ["aac_adtstoasc", "h264_mp4toannexb", "null"]
+        );
+        assert!(entries.iter().all(|(flags, _)| flags.is_empty()));
+    }
+
+    #[test]
+    fn filter_listing_pads() {
+        let text = fixture("filters.txt");
+        let filters: HashMap<String, FilterInfo> = listing_entries(&text, true)
+            .into_iter()
+            .filter_map(|(flags, words)| {
+                Some((
+                    words[0].to_string(),
+                    parse_filter_listing(&flags, words[1])?,
+                ))
+            })
+            .collect();
+
+        let scale = &filters["scale"];
+        assert!(scale.timeline && scale.slice_threading && scale.command);
+        assert_eq!(scale.inputs.len(), 1);
+        assert_eq!(scale.outputs[0].media, MediaType::Video);
+
+        assert!(filters["amix"].dynamic_inputs);
+        assert_eq!(filters["amix"].outputs[0].media, MediaType::Audio);
+        let source = &filters["anullsrc"];
+        assert!(source.inputs.is_empty() && !source.dynamic_inputs);
+        let sink = &filters["nullsink"];
+        assert!(sink.outputs.is_empty() && !sink.dynamic_outputs);
+        assert!(filters["split"].dynamic_outputs);
+        assert_eq!(filters["scale2ref"].inputs.len(), 2);
+    }
+
+    #[test]
+    fn parse_item_help_encoder() {
+        let text = fixture("help_encoder_libx264.txt");
+        let nodes = parse_item_help("encoder", "libx264", "libx264 H.264", None, &text);
+        assert_eq!(nodes.len(), 1);
+
+        let node = &nodes[0];
+        assert_eq!(node.name, "libx264");
+        assert!(node.is_av_option);
+        assert_eq!(node.pcategory, "encoders");
+        assert_eq!(node.category, "E..V.......");
+        assert_eq!(
+            node.options
+                .iter()
+                .map(|o| o.flag.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "-preset",
+                "-tune",
+                "-fastfirstpass",
+                "-crf",
+                "-qp",
+                "-aq-mode",
+                "-aq-strength",
+                "-partitions",
+                "-x264-params"
+            ]
+        );
+
+        let preset = option(node, "-preset");
+        assert_eq!(preset.r#type.as_deref(), Some("<string>"));
+        assert_eq!(preset.default.as_deref(), Some("medium"));
+        assert!(preset.encoding && preset.video && !preset.decoding);
+
+        let crf = option(node, "-crf");
+        assert_eq!(crf.min, Some(-1.0));
+        assert_eq!(crf.max, Some(f32::MAX as f64));
+        assert_eq!(crf.default.as_deref(), Some("-1"));
+
+        let aq_mode = option(node, "-aq-mode");
+        assert_eq!(aq_mode.r#type.as_deref(), Some("<enum>"));
+        assert_eq!(
+            aq_mode.enum_vals,
+            ["none", "variance", "autovariance", "autovariance-biased"]
+        );
+        assert!(aq_mode.no_args);
+        assert_eq!(aq_mode.max, Some(i32::MAX as f64));
+
+        let codec = node.codec.as_ref().unwrap();
+        assert_eq!(codec.capabilities, ["dr1", "delay", "threads"]);
+        assert_eq!(codec.threading.as_deref(), Some("other"));
+        assert_eq!(codec.pix_fmts.len(), 15);
+        assert_eq!(codec.pix_fmts[0], "yuv420p");
+        assert!(codec.sample_fmts.is_empty());
+    }
+
+    #[test]
+    fn parse_item_help_filter() {
+        let text = fixture("help_filter_scale.txt");
+        let listing = parse_filter_listing("TSC", "V->V");
+        let nodes = parse_item_help("filter", "scale", "Scale", listing.as_ref(), &text);
+        assert_eq!(
+            nodes.iter().map(|n| n.name.as_str()).collect::<Vec<_>>(),
+            ["scale", "SWScaler"]
+        );
+
+        let scale = &nodes[0];
+        assert!(scale.is_av_option);
+        // Mixed scopes across options
+        assert_eq!(scale.category, "~");
+        // Aliases with the same description are listed once
+        assert_eq!(
+            scale
+                .options
+                .iter()
+                .map(|o| o.flag.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "w",
+                "h",
+                "flags",
+                "interl",
+                "in_range",
+                "force_original_aspect_ratio",
+                "param0",
+                "eval",
+                "enable"
+            ]
+        );
+        assert!(option(scale, "w").runtime_param);
+        assert_eq!(option(scale, "flags").default.as_deref(), Some(""));
+        assert_eq!(
+            option(scale, "in_range").enum_vals,
+            ["auto", "unknown", "full", "limited"]
+        );
+        assert_eq!(option(scale, "param0").min, Some(f64::MIN));
+        assert_eq!(option(scale, "param0").max, Some(f64::MAX));
+
+        let filter = scale.filter.as_ref().unwrap();
+        assert_eq!(filter.inputs[0].name, "default");
+        assert_eq!(filter.outputs[0].media, MediaType::Video);
+        assert!(filter.timeline);
+
+        let sws = &nodes[1];
+        assert!(!sws.is_av_option);
+        let flags = option(sws, "sws_flags");
+        assert!(flags.enum_vals.is_empty());
+        assert_eq!(
+            flags.desc.as_deref().map(|d| d.matches("<br>").count()),
+            Some(2)
+        );
+        assert_eq!(option(sws, "param0").min, Some(i32::MIN as f64));
+    }
+
+    #[test]
+    fn parse_item_help_without_options() {
+        let text = "Codec 'nope' is not recognized by FFmpeg.\n";
+        let nodes = parse_item_help("encoder", "nope", "Nothing", None, text);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].name, "nope");
+        assert_eq!(nodes[0].desc, "Nothing");
+        assert!(nodes[0].options.is_empty());
+    }
+
+    #[test]
+    fn pools_share_the_process_limit() {
+        let limit = ProcessLimit::new(3);
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<String> = (0..24).map(|n| n.to_string()).collect();
+        let items: Vec<&str> = items.iter().map(String::as_str).collect();
+
+        let work = |item: &str| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(item.len())
+        };
+        let results: Vec<Vec<usize>> = thread::scope(|scope| {
+            let pools: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| run_pool(&items, &limit, work).unwrap()))
+                .collect();
+            pools.into_iter().map(|p| p.join().unwrap()).collect()
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        for lens in results {
+            assert_eq!(lens, items.iter().map(|i| i.len()).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn run_pool_stops_at_first_error() {
+        let limit = ProcessLimit::new(2);
+        let result = run_pool(&["a", "b", "c"], &limit, |item| match item {
+            "b" => Err(anyhow!("no help for {item}")),
+            _ => Ok(()),
+        });
+        assert_eq!(result.unwrap_err().to_string(), "no help for b");
+    }
+
+    #[test]
+    fn listing_entries_of_pix_fmts_and_devices() {
+        let pix_fmts = fixture("pix_fmts.txt");
+        assert_eq!(
+            names(&listing_entries(&pix_fmts, true)),
+            [
+                "yuv420p",
+                "yuyv422",
+                "rgb24",
+                "gray",
+                "monow",
+                "vaapi",
+                "yuv420p10le"
+            ]
+        );
+
+        let devices = fixture("devices.txt");
+        let entries = listing_entries(&devices, true);
+        assert_eq!(
+            names(&entries),
+            ["alsa", "lavfi", "pulse", "sdl,sdl2", "x11grab"]
+        );
+        let (flags, _) = &entries[1];
+        assert!(flags.contains('D') && !flags.contains('E'), "lavfi");
+        let (flags, _) = &entries[3];
+        assert!(!flags.contains('D') && flags.contains('E'), "sdl");
+    }
+
+    /// The AVOption flag column grew as flags were added. The fixture is rewritten with
+    /// narrower columns to check the scope is cut at whatever width the build uses.
+    #[test]
+    fn item_help_flag_column_widths() {
+        let text = fixture("help_encoder_libx264.txt");
+        for width in [8, 10, 11] {
+            let text = text.replace("E..V.......", &"E..V......."[..width]);
+            let nodes = parse_item_help("encoder", "libx264", "libx264", None, &text);
+            assert_eq!(nodes.len(), 1, "{width}");
+            let node = &nodes[0];
+            assert_eq!(node.options.len(), 9, "{width}");
+            assert_eq!(node.category.len(), width, "{width}");
+
+            let crf = option(node, "-crf");
+            assert!(crf.encoding && crf.video && !crf.audio, "{width}");
+            assert_eq!(
+                (crf.min, crf.max),
+                (Some(-1.0), Some(f32::MAX as f64)),
+                "{width}"
+            );
+            assert_eq!(
+                option(node, "-aq-mode").enum_vals.len(),
+                4,
+                "{width} -aq-mode"
+            );
+            assert_eq!(
+                node.codec.as_ref().map(|c| c.pix_fmts.len()),
+                Some(15),
+                "{width}"
+            );
+        }
+    }
+
+    #[test]
+    fn catalog_env_forces_c_locale() {
+        let env = parse_env_map("PATH=/usr/bin\nLC_ALL=de_DE.UTF-8\nLANG=de_DE.UTF-8\n");
+        let mut cmd = Command::new("ffmpeg");
+        apply_catalog_env(&mut cmd, &env);
+
+        let envs: HashMap<String, Option<String>> = cmd
+            .get_envs()
+            .map(|(k, v)| {
+                (
+                    k.to_string_lossy().into_owned(),
+                    v.map(|v| v.to_string_lossy().into_owned()),
+                )
+            })
+            .collect();
+        assert_eq!(envs["LC_ALL"].as_deref(), Some("C"));
+        assert_eq!(envs["PATH"].as_deref(), Some("/usr/bin"));
+    }
+
+    #[test]
+    fn limits_parse_in_c_locale_only() {
+        let mut opt = OptionEntry::default();
+        let line = "  -ratio <float> ..FV....... ratio (from 0.5 to 1e+06) (default 0.75)";
+        parse_option_details(&mut opt, "..FV.......", line);
+        assert_eq!((opt.min, opt.max), (Some(0.5), Some(1e6)));
+        assert_eq!(opt.default.as_deref(), Some("0.75"));
+        assert!(opt.filtering && opt.video);
+
+        // What a German locale would print, the reason catalogs run with LC_ALL=C
+        assert_eq!(parse_limit("0,5"), None);
+        assert_eq!(parse_limit("-INT_MAX"), Some(-(i32::MAX as f64)));
+    }
+
+    #[test]
+    fn reparse_failed_only_touches_failed_categories() {
+        let mut catalog = Catalog::default();
+        for (category, ok) in [("filters", true), ("colors", false), ("layouts", false)] {
+            catalog.report.categories.push(CategoryReport {
+                category: category.to_string(),
+                ok,
+                count: usize::from(ok),
+                ..CategoryReport::default()
+            });
+        }
+        catalog.nodes.push(Node {
+            name: "scale".to_string(),
+            ..Node::default()
+        });
+
+        reparse_failed("/nonexistent/ffmpeg", "", &mut catalog);
+
+        let report = &catalog.report.categories;
+        assert_eq!(
+            report
+                .iter()
+                .map(|c| c.category.as_str())
+                .collect::<Vec<_>>(),
+            ["filters", "colors", "layouts"]
+        );
+        assert!(report[0].ok && report[0].error.is_none());
+        assert!(report[1..].iter().all(|c| !c.ok && c.error.is_some()));
+        assert_eq!(catalog.nodes.len(), 1);
+    }
+
+    /// A build that lists hwaccels but rejects every other listing flag.
+    #[cfg(unix)]
+    #[test]
+    fn rejected_listing_flags_fail_their_category() {
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = fake_ffmpeg(
+            dir.path(),
+            "case \"$1\" in\n\
+             -hwaccels) printf 'Hardware acceleration methods:\\nvdpau\\n'; exit 0 ;;\n\
+             esac\n\
+             echo \"Unrecognized option '${1#-}'.\" >&2\n\
+             echo 'Error splitting the argument list: Option not found' >&2\n\
+             exit 8\n",
+        );
+
+        let catalog = parse_ffmpeg_with(ffmpeg.to_str().unwrap(), "", 2).unwrap();
+        let report = |category: &str| {
+            catalog
+                .report
+                .categories
+                .iter()
+                .find(|c| c.category == category)
+                .unwrap()
+                .clone()
+        };
+
+        let hwaccels = report("hwaccels");
+        assert!(hwaccels.ok);
+        assert_eq!(hwaccels.count, 1);
+        for category in ["devices", "protocols", "layouts", "encoders"] {
+            let entry = report(category);
+            assert!(!entry.ok, "{category} passed");
+            assert_eq!(entry.count, 0);
+            let error = entry.error.unwrap();
+            assert!(error.contains("Option not found"), "{category}: {error}");
+        }
+    }
+
+    /// Writes an executable `ffmpeg` script to `dir` running `body`.
+    #[cfg(unix)]
+    fn fake_ffmpeg(dir: &std::path::Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let ffmpeg = dir.join("ffmpeg");
+        fs::write(&ffmpeg, format!("#!/bin/sh\n{body}")).unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        ffmpeg
+    }
+
+    /// `tests/fixtures/ffmpeg/catalog_golden.json` is what `parse_ffmpeg` returned for the
+    /// fixtures before per-item help moved to a pool, sorted. Two later fixes are applied
+    /// to it: listing descriptions keep their first word, and filters no longer get an
+    /// `enable` option (it is `FilterInfo::timeline` now). Only the fields and categories
+    /// the parser had then are compared.
+    #[cfg(unix)]
+    #[test]
+    fn catalog_matches_golden() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixtures: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/ffmpeg"]
+            .iter()
+            .collect();
+        // Anything without a fixture lists nothing
+        let ffmpeg = fake_ffmpeg(
+            dir.path(),
+            &format!(
+                "F='{}'\n\
+                 case \"$1 $2\" in\n\
+                 '-encoders '*) cat \"$F/encoders.txt\" ;;\n\
+                 '-filters '*) cat \"$F/filters.txt\" ;;\n\
+                 '-pix_fmts '*) cat \"$F/pix_fmts.txt\" ;;\n\
+                 '-h encoder=libx264') cat \"$F/help_encoder_libx264.txt\" ;;\n\
+                 '-h filter=scale') cat \"$F/help_filter_scale.txt\" ;;\n\
+                 esac\n",
+                fixtures.display()
+            ),
+        );
+
+        let golden: Vec<serde_json::Value> =
+            serde_json::from_str(&fixture("catalog_golden.json")).unwrap();
+        let node_fields: Vec<&String> = golden[0].as_object().unwrap().keys().collect();
+        let option_fields: Vec<&String> = golden
+            .iter()
+            .flat_map(|n| n["options"].as_array().unwrap())
+            .next()
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        let categories: HashSet<&str> = golden
+            .iter()
+            .map(|n| n["pcategory"].as_str().unwrap())
+            .collect();
+
+        let project = |node: &Node| {
+            let value = serde_json::to_value(node).unwrap();
+            let mut out = serde_json::Map::new();
+            for field in &node_fields {
+                out.insert(field.to_string(), value[field.as_str()].clone());
+            }
+            let options: Vec<serde_json::Value> = value["options"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|option| {
+                    let kept = option_fields
+                        .iter()
+                        .map(|f| (f.to_string(), option[f.as_str()].clone()));
+                    serde_json::Value::Object(kept.collect())
+                })
+                .collect();
+            out.insert("options".to_string(), options.into());
+            serde_json::Value::Object(out)
+        };
+
+        let catalog = parse_ffmpeg_with(ffmpeg.to_str().unwrap(), "", 4).unwrap();
+        let mut nodes: Vec<&Node> = catalog
+            .nodes
+            .iter()
+            .filter(|n| categories.contains(n.pcategory.as_str()))
+            .collect();
+        nodes.sort_by(|a, b| {
+            (&a.pcategory, &a.name, a.is_av_option).cmp(&(&b.pcategory, &b.name, b.is_av_option))
+        });
+        let parsed: Vec<serde_json::Value> = nodes.into_iter().map(project).collect();
+
+        assert_eq!(parsed.len(), golden.len());
+        for (parsed, golden) in parsed.iter().zip(&golden) {
+            assert_eq!(parsed, golden, "{}", golden["name"]);
+        }
+    }
+
+    /// Times a full catalog parse with one help process at a time against the default
+    /// pool, and checks both give the same catalog. The output itself is checked against
+    /// the golden catalog by `catalog_matches_golden`. Needs a real ffmpeg:
+    ///
+    /// `FFSTUDIO_FFMPEG=/usr/bin/ffmpeg cargo test --release parse_ffmpeg_timing -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn parse_ffmpeg_timing() {
+        let ffmpeg = std::env::var("FFSTUDIO_FFMPEG").unwrap_or_else(|_| "ffmpeg".to_string());
+        let env = std::env::vars()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let timed = |parse: &dyn Fn() -> Result<Catalog>| {
+            let start = Instant::now();
+            let catalog = parse().unwrap();
+            (catalog, start.elapsed())
+        };
+        let (serial, serial_time) = timed(&|| parse_ffmpeg_with(&ffmpeg, &env, 1));
+        let (pooled, pooled_time) = timed(&|| parse_ffmpeg(&ffmpeg, &env));
+
+        for report in &pooled.report.categories {
+            println!(
+                "{:<12} {:>6} nodes {:>7} ms",
+                report.category, report.count, report.duration_ms
+            );
+        }
+        println!(
+            "serial {serial_time:?}, pooled {pooled_time:?}, {:.1}x",
+            serial_time.as_secs_f64() / pooled_time.as_secs_f64()
+        );
+
+        assert!(pooled.report.is_complete(), "{:?}", pooled.report);
+        assert_eq!(
+            serde_json::to_string(&serial.nodes).unwrap(),
+            serde_json::to_string(&pooled.nodes).unwrap()
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use super::command::FfmpegCommand;
+use super::executor::PreviewTarget;
+use crate::utils::filesystem::get_data_dir;
+use crate::utils::hash::short_hash;
+use crate::{FFStudioError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_FILE: &str = "index.json";
+
+/// 1 GiB
+const DEFAULT_LIMIT: u64 = 1 << 30;
+
+/// A local input file as it was when a preview was rendered from it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputIdentity {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64,
+}
+
+impl InputIdentity {
+    /// `None` for anything that isn't a local file: pipes, streams, lavfi sources.
+    pub fn of(path: &str) -> Option<Self> {
+        let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Some(Self {
+            path: path.to_string(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// Key of a preview of `cmd` from `start` to `end`, also the name of the rendered file.
+/// It only depends on the request, so the same preview is found again after a restart.
+pub fn preview_key(
+    cmd: &str,
+    start: &str,
+    end: Option<&str>,
+    accurate: bool,
+    target: &PreviewTarget,
+) -> String {
+    let mut unique = format!("{cmd}{start}{}", end.unwrap_or_default());
+    if accurate {
+        unique.push_str("accurate");
+    }
+    if *target != PreviewTarget::Preview {
+        unique.push_str(&serde_json::to_string(target).unwrap_or_default());
+    }
+    short_hash(&unique)
+}
+
+/// Identities of every local file `command` reads.
+pub fn input_identities(command: &FfmpegCommand) -> Vec<InputIdentity> {
+    command
+        .inputs
+        .iter()
+        .filter_map(|input| InputIdentity::of(&input.url))
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: String,
+    pub path: PathBuf,
+    /// Files rendered along with `path`, e.g. the measured stream of a comparison
+    #[serde(default)]
+    pub extra: Vec<PathBuf>,
+    /// Of all the files
+    pub size: u64,
+    pub inputs: Vec<InputIdentity>,
+    pub created: u64,
+    pub last_used: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheIndex {
+    limit: u64,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl Default for CacheIndex {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_LIMIT,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl CacheEntry {
+    fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.path).chain(&self.extra)
+    }
+
+    fn remove_files(&self) {
+        self.files().for_each(|f| remove_file(f));
+    }
+}
+
+/// Total size of `files`, `None` if one of them is missing.
+fn files_size<'a>(files: impl Iterator<Item = &'a PathBuf>) -> Option<u64> {
+    files
+        .map(|f| {
+            fs::metadata(f)
+                .ok()
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+        })
+        .sum()
+}
+
+impl CacheIndex {
+    fn bytes(&self) -> u64 {
+        self.entries.values().map(|e| e.size).sum()
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub limit: u64,
+    /// Counted since the app started
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// `None` for [`cache_dir`]
+    dir: Option<PathBuf>,
+    index: Option<CacheIndex>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+/// Rendered previews under `tmp/preview`, bounded by a size limit.
+///
+/// Entries are keyed by the hash of the preview command and range, and remember the size
+/// and modification time of every input so a preview of a file that changed since is
+/// rendered again. When the limit is exceeded the least recently used entries are deleted.
+#[derive(Clone, Default)]
+pub struct PreviewCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Directory previews are rendered into.
+pub fn cache_dir() -> Result<PathBuf> {
+    dir_or_default(&None)
+}
+
+fn dir_or_default(dir: &Option<PathBuf>) -> Result<PathBuf> {
+    let dir = match dir {
+        Some(dir) => dir.clone(),
+        None => get_data_dir()?.join("tmp").join("preview"),
+    };
+    fs::create_dir_all(&dir).map_err(|e| {
+        FFStudioError::file_system(format!("Failed to create preview cache directory: {e}"))
+    })?;
+    Ok(dir)
+}
+
+/// Deletes everything in `tmp` except the preview cache directory `keep`.
+pub fn clear_tmp(tmp: &Path, keep: &Path) -> Result<()> {
+    if !tmp.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(tmp)? {
+        let entry_path = entry?.path();
+        if entry_path == keep {
+            continue;
+        }
+        let removed = if entry_path.is_dir() {
+            fs::remove_dir_all(&entry_path)
+        } else {
+            fs::remove_file(&entry_path)
+        };
+        // Files still open by a running job can't be removed on Windows
+        if let Err(e) = removed {
+            log::warn!("Failed to remove {entry_path:?}: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn remove_file(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Failed to remove cached preview {path:?}: {e}");
+        }
+    }
+}
+
+/// Reads the index, dropping entries whose file is gone and deleting files no entry
+/// refers to.
+fn load_index(dir: &Path) -> CacheIndex {
+    let mut index: CacheIndex = fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+
+    index
+        .entries
+        .retain(|_, entry| match files_size(entry.files()) {
+            Some(size) => {
+                entry.size = size;
+                true
+            }
+            None => false,
+        });
+
+    if let Ok(read_dir) = fs::read_dir(dir) {
+        for path in read_dir.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let known = index
+                .entries
+                .values()
+                .any(|e| e.files().any(|f| *f == path));
+            if path.is_file() && !known && path.file_name() != Some(INDEX_FILE.as_ref()) {
+                remove_file(&path);
+            }
+        }
+    }
+    index
+}
+
+impl CacheState {
+    fn index(&mut self) -> &mut CacheIndex {
+        let dir = &self.dir;
+        self.index.get_or_insert_with(|| match dir_or_default(dir) {
+            Ok(dir) => load_index(&dir),
+            Err(e) => {
+                log::warn!("Preview cache unavailable: {e}");
+                CacheIndex::default()
+            }
+        })
+    }
+
+    fn save(&mut self) {
+        let result = dir_or_default(&self.dir).and_then(|dir| {
+            let text = serde_json::to_string_pretty(self.index())?;
+            fs::write(dir.join(INDEX_FILE), text)?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            log::warn!("Failed to save preview cache index: {e}");
+        }
+    }
+
+    /// Deletes the least recently used entries other than `keep` until the cache fits its
+    /// limit.
+    fn evict(&mut self, keep: Option<&str>) {
+        loop {
+            let index = self.index();
+            if index.bytes() <= index.limit {
+                return;
+            }
+            let oldest = index
+                .entries
+                .values()
+                .filter(|e| Some(e.key.as_str()) != keep)
+                .min_by_key(|e| e.last_used)
+                .map(|e| e.key.clone());
+            let Some(key) = oldest else {
+                return;
+            };
+            if let Some(entry) = index.entries.remove(&key) {
+                entry.remove_files();
+                self.evictions += 1;
+            }
+        }
+    }
+
+    fn stats(&mut self) -> CacheStats {
+        let (hits, misses, evictions) = (self.hits, self.misses, self.evictions);
+        let index = self.index();
+        CacheStats {
+            entries: index.entries.len(),
+            bytes: index.bytes(),
+            limit: index.limit,
+            hits,
+            misses,
+            evictions,
+        }
+    }
+}
+
+impl PreviewCache {
+    /// A cache kept in `dir` instead of [`cache_dir`].
+    pub fn in_dir(dir: PathBuf) -> Self {
+        let state = CacheState {
+            dir: Some(dir),
+            ..CacheState::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Directory previews are rendered into.
+    pub fn dir(&self) -> Result<PathBuf> {
+        dir_or_default(&self.lock().dir)
+    }
+
+    /// The cached file for `key`, if it exists and was rendered from the same `inputs`.
+    /// A stale entry is deleted.
+    pub fn lookup(&self, key: &str, inputs: &[InputIdentity]) -> Option<PathBuf> {
+        let mut state = self.lock();
+        let index = state.index();
+        let found = match index.entries.get_mut(key) {
+            Some(entry) if entry.inputs == inputs && entry.files().all(|f| f.is_file()) => {
+                entry.last_used = now_millis();
+                Some(entry.path.clone())
+            }
+            Some(_) => {
+                if let Some(stale) = index.entries.remove(key) {
+                    stale.remove_files();
+                }
+                None
+            }
+            None => None,
+        };
+
+        if found.is_some() {
+            state.hits += 1;
+        } else {
+            state.misses += 1;
+        }
+        state.save();
+        found
+    }
+
+    /// Records a rendered preview and evicts older ones if the cache is over its limit.
+    pub fn insert(&self, key: &str, path: &Path, inputs: Vec<InputIdentity>) -> Result<()> {
+        self.insert_with(key, path, Vec::new(), inputs)
+    }
+
+    /// Like [`PreviewCache::insert`], for a preview that rendered `extra` files too. They
+    /// are looked up and evicted together.
+    pub fn insert_with(
+        &self,
+        key: &str,
+        path: &Path,
+        extra: Vec<PathBuf>,
+        inputs: Vec<InputIdentity>,
+    ) -> Result<()> {
+        let mut size = 0;
+        for file in std::iter::once(path).chain(extra.iter().map(PathBuf::as_path)) {
+            size += fs::metadata(file)
+                .map_err(|e| {
+                    FFStudioError::file_system(format!("Failed to read preview {file:?}: {e}"))
+                })?
+                .len();
+        }
+        let now = now_millis();
+
+        let mut state = self.lock();
+        state.index().entries.insert(
+            key.to_string(),
+            CacheEntry {
+                key: key.to_string(),
+                path: path.to_path_buf(),
+                extra,
+                size,
+                inputs,
+                created: now,
+                last_used: now,
+            },
+        );
+        state.evict(Some(key));
+        state.save();
+        Ok(())
+    }
+
+    /// Deletes a preview that failed to render, along with its entry if it had one.
+    pub fn discard(&self, path: &Path) {
+        let mut state = self.lock();
+        state.index().entries.retain(|_, e| {
+            let keep = e.path != path;
+            if !keep {
+                e.remove_files();
+            }
+            keep
+        });
+        remove_file(path);
+        state.save();
+    }
+
+    pub fn set_limit(&self, bytes: u64) -> CacheStats {
+        let mut state = self.lock();
+        state.index().limit = bytes;
+        state.evict(None);
+        state.save();
+        state.stats()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats()
+    }
+
+    /// Deletes every cached preview. The limit is kept.
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        for (_, entry) in state.index().entries.drain() {
+            entry.remove_files();
+        }
+        state.save();
+    }
+}
+
+#[tauri::command]
+pub fn get_preview_cache_stats(cache: tauri::State<PreviewCache>) -> CacheStats {
+    cache.stats()
+}
+
+#[tauri::command]
+pub fn set_preview_cache_limit(bytes: u64, cache: tauri::State<PreviewCache>) -> CacheStats {
+    cache.set_limit(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CMD: &str = "ffmpeg -i in.mkv -c:v libx264 out.mp4";
+
+    /// Writes `size` bytes to `name` in the cache directory.
+    fn render(cache: &PreviewCache, name: &str, size: usize) -> PathBuf {
+        let path = cache.dir().unwrap().join(name);
+        fs::write(&path, vec![0u8; size]).unwrap();
+        path
+    }
+
+    fn identity(modified: u64) -> Vec<InputIdentity> {
+        vec![InputIdentity {
+            path: "in.mkv".to_string(),
+            size: 100,
+            modified,
+        }]
+    }
+
+    /// Makes the next `last_used` later than the previous one.
+    fn tick() {
+        std::thread::sleep(std::time::Duration::from_millis(3));
+    }
+
+    #[test]
+    fn keys_depend_on_the_request_only() {
+        let key = |cmd: &str, start: &str, end: Option<&str>, accurate: bool| {
+            preview_key(cmd, start, end, accurate, &PreviewTarget::Preview)
+        };
+        let base = key(CMD, "1", Some("3"), false);
+        assert_eq!(base, key(CMD, "1", Some("3"), false));
+        for other in [
+            key(
+                "ffmpeg -i in.mkv -c:v libx265 out.mp4",
+                "1",
+                Some("3"),
+                false,
+            ),
+            key(CMD, "2", Some("3"), false),
+            key(CMD, "1", Some("4"), false),
+            key(CMD, "1", None, false),
+            key(CMD, "1", Some("3"), true),
+            preview_key(
+                CMD,
+                "1",
+                Some("3"),
+                false,
+                &PreviewTarget::Gif {
+                    width: None,
+                    fps: None,
+                },
+            ),
+        ] {
+            assert_ne!(other, base);
+        }
+    }
+
+    #[test]
+    fn entries_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        cache.stats();
+        let path = render(&cache, "a.mp4", 10);
+        cache.insert("a", &path, identity(1)).unwrap();
+        let gone = render(&cache, "gone.mp4", 10);
+        cache.insert("gone", &gone, identity(1)).unwrap();
+        fs::remove_file(&gone).unwrap();
+
+        // Not in the index, deleted when it is loaded
+        let stray = render(&cache, "stray.mp4", 10);
+
+        let restarted = PreviewCache::in_dir(dir.path().to_path_buf());
+        assert_eq!(restarted.lookup("a", &identity(1)), Some(path));
+        assert_eq!(restarted.lookup("gone", &identity(1)), None);
+        assert!(!stray.exists());
+        let stats = restarted.stats();
+        assert_eq!((stats.entries, stats.bytes), (1, 10));
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+    }
+
+    #[test]
+    fn changed_inputs_are_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        cache.stats();
+        let path = render(&cache, "a.mp4", 10);
+        cache.insert("a", &path, identity(1)).unwrap();
+
+        assert_eq!(cache.lookup("a", &identity(2)), None);
+        assert!(!path.exists());
+        assert_eq!(cache.lookup("a", &identity(1)), None);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        cache.set_limit(10);
+
+        let a = render(&cache, "a.mp4", 4);
+        cache.insert("a", &a, identity(1)).unwrap();
+        tick();
+        let b = render(&cache, "b.mp4", 4);
+        cache.insert("b", &b, identity(1)).unwrap();
+        tick();
+        assert!(cache.lookup("a", &identity(1)).is_some());
+        tick();
+
+        let c = render(&cache, "c.mp4", 4);
+        cache.insert("c", &c, identity(1)).unwrap();
+        assert!(a.exists() && !b.exists() && c.exists());
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.bytes, stats.evictions), (2, 8, 1));
+
+        // A new entry is kept even when it is larger than the limit on its own
+        tick();
+        let big = render(&cache, "big.mp4", 20);
+        cache.insert("big", &big, identity(1)).unwrap();
+        assert!(!a.exists() && !c.exists() && big.exists());
+        assert_eq!(cache.stats().entries, 1);
+
+        cache.set_limit(5);
+        assert!(!big.exists());
+        assert_eq!(cache.stats().evictions, 4);
+    }
+
+    #[test]
+    fn extra_files_count_and_go_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PreviewCache::in_dir(dir.path().to_path_buf());
+        cache.set_limit(100);
+        let main = render(&cache, "cmp.mp4", 30);
+        let extra = render(&cache, "cmp_variant.mp4", 50);
+        cache
+            .insert_with("cmp", &main, vec![extra.clone()], identity(1))
+            .unwrap();
+        assert_eq!(cache.stats().bytes, 80);
+
+        // Missing one of its files makes the whole entry stale
+        fs::remove_file(&extra).unwrap();
+        assert_eq!(cache.lookup("cmp", &identity(1)), None);
+        assert!(!main.exists());
+
+        let main = render(&cache, "cmp.mp4", 30);
+        let extra = render(&cache, "cmp_variant.mp4", 50);
+        cache
+            .insert_with("cmp", &main, vec![extra.clone()], identity(1))
+            .unwrap();
+        cache.clear();
+        assert!(!main.exists() && !extra.exists());
+        assert_eq!(cache.stats().limit, 100);
+    }
+
+    #[test]
+    fn clearing_tmp_keeps_the_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let preview = tmp.path().join("preview");
+        fs::create_dir_all(&preview).unwrap();
+        fs::write(preview.join("a.mp4"), "cached").unwrap();
+        fs::create_dir_all(tmp.path().join("validate_1").join("nested")).unwrap();
+        fs::write(tmp.path().join("segment.nut"), "").unwrap();
+
+        clear_tmp(tmp.path(), &preview).unwrap();
+        let left: Vec<PathBuf> = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(left, std::slice::from_ref(&preview));
+        assert!(preview.join("a.mp4").exists());
+
+        clear_tmp(&tmp.path().join("missing"), &preview).unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+use super::version::get_mediainfo;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+static DURATION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Duration:\s*(\d+):(\d+):(\d+(?:\.\d+)?)").unwrap());
+static STREAM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"Stream #\S+:\s*(Video|Audio|Subtitle|Data):\s*(\w+)(.*)$").unwrap());
+static SIZE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b(\d{1,5})x(\d{1,5})\b").unwrap());
+
+/// Basic stream properties read from `ffmpeg -i` output.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MediaProbe {
+    pub duration: Option<f64>,
+    pub has_video: bool,
+    pub has_audio: bool,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: Option<String>,
+}
+
+impl MediaProbe {
+    pub fn is_audio_only(&self) -> bool {
+        self.has_audio && !self.has_video
+    }
+}
+
+pub fn parse_mediainfo(lines: &[String]) -> MediaProbe {
+    let mut probe = MediaProbe::default();
+
+    for line in lines {
+        if probe.duration.is_none() {
+            if let Some(c) = DURATION_RE.captures(line) {
+                let h: f64 = c[1].parse().unwrap_or(0.0);
+                let m: f64 = c[2].parse().unwrap_or(0.0);
+                let s: f64 = c[3].parse().unwrap_or(0.0);
+                probe.duration = Some(h * 3600.0 + m * 60.0 + s);
+                continue;
+            }
+        }
+
+        let Some(c) = STREAM_RE.captures(line) else {
+            continue;
+        };
+        let codec = c[2].to_string();
+        let rest = &c[3];
+
+        match &c[1] {
+            // Cover art shows up as a video stream, it does not make a file "video"
+            "Video" if !rest.contains("(attached pic)") && !probe.has_video => {
+                probe.has_video = true;
+                probe.video_codec = Some(codec);
+                if let Some(size) = SIZE_RE.captures(rest) {
+                    probe.width = size[1].parse().ok();
+                    probe.height = size[2].parse().ok();
+                }
+                // "h264 (High), yuv420p(progressive), 1920x1080" -> second field
+                probe.pix_fmt = rest
+                    .split(", ")
+                    .nth(1)
+                    .and_then(|f| f.split(['(', ' ']).next())
+                    .filter(|f| !f.is_empty())
+                    .map(|f| f.to_string());
+            }
+            "Audio" if !probe.has_audio => {
+                probe.has_audio = true;
+                probe.audio_codec = Some(codec);
+            }
+            _ => {}
+        }
+    }
+
+    probe
+}
+
+pub fn probe_media(path: &str, ffmpeg: &str, env_str: &str) -> Result<MediaProbe> {
+    let lines = get_mediainfo(path, ffmpeg, env_str)?;
+    let probe = parse_mediainfo(&lines);
+    if !probe.has_video && !probe.has_audio {
+        return Err(anyhow!("No audio or video streams found in {path}"));
+    }
+    Ok(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// `ffmpeg -i` output under `tests/fixtures/ffmpeg/`, trimmed by hand to the layout
+    /// of ffmpeg 7.x.
+    fn mediainfo(name: &str) -> Vec<String> {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/ffmpeg", name]
+            .iter()
+            .collect();
+        let text =
+            std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn video_with_audio() {
+        let probe = parse_mediainfo(&mediainfo("mediainfo_mp4.txt"));
+        assert_eq!(probe.duration, Some(62.54));
+        assert!(probe.has_video && probe.has_audio && !probe.is_audio_only());
+        assert_eq!(probe.video_codec.as_deref(), Some("h264"));
+        assert_eq!(probe.audio_codec.as_deref(), Some("aac"));
+        // Not the codec tag, 0x31637661
+        assert_eq!((probe.width, probe.height), (Some(1920), Some(1080)));
+        assert_eq!(probe.pix_fmt.as_deref(), Some("yuv420p"));
+    }
+
+    #[test]
+    fn cover_art_is_not_video() {
+        let probe = parse_mediainfo(&mediainfo("mediainfo_mp3_cover.txt"));
+        assert_eq!(probe.duration, Some(205.06));
+        assert!(probe.is_audio_only());
+        assert_eq!(probe.audio_codec.as_deref(), Some("mp3"));
+        assert_eq!(probe.video_codec, None);
+        assert_eq!((probe.width, probe.height), (None, None));
+    }
+
+    #[test]
+    fn hdr_video_with_chapters_and_subtitles() {
+        let probe = parse_mediainfo(&mediainfo("mediainfo_mkv_hdr.txt"));
+        // The stream DURATION tags don't override the container's
+        let duration = probe.duration.unwrap();
+        assert!((duration - 6727.33).abs() < 1e-6, "{duration}");
+        assert_eq!(probe.video_codec.as_deref(), Some("hevc"));
+        assert_eq!(probe.audio_codec.as_deref(), Some("eac3"));
+        assert_eq!((probe.width, probe.height), (Some(3840), Some(2160)));
+        assert_eq!(probe.pix_fmt.as_deref(), Some("yuv420p10le"));
+    }
+
+    #[test]
+    fn no_streams() {
+        let lines = ["clip.mp4: No such file or directory".to_string()];
+        let probe = parse_mediainfo(&lines);
+        assert!(!probe.has_video && !probe.has_audio);
+        assert_eq!(probe.duration, None);
+    }
+}
@@ -0,0 +1,466 @@
+use super::command::{CmdOption, FfmpegCommand};
+use super::filtergraph::{check_command, Severity, Span};
+use super::parser::{apply_env, parse_env_map};
+use super::probe::probe_media;
+use crate::utils::filesystem::{get_data_dir, load_nodes};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// A dry run reads no frames, so anything slower than this is stuck on an input.
+const DRY_RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Output options dropped from the dry run: they either limit what is already limited to
+/// zero frames or depend on files from an earlier pass.
+const DRY_RUN_DROPPED: &[&str] = &[
+    "frames",
+    "vframes",
+    "aframes",
+    "t",
+    "to",
+    "fs",
+    "pass",
+    "passlogfile",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Check {
+    /// The command line itself
+    Command,
+    /// An input file exists and ffmpeg can read it
+    Input,
+    /// Filtergraphs against the cached catalog
+    Filtergraph,
+    /// ffmpeg run for zero frames
+    DryRun,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobDiagnostic {
+    /// Index of the pipeline stage
+    pub stage: usize,
+    pub check: Check,
+    pub severity: Severity,
+    pub message: String,
+    /// Input URL or option the diagnostic is about
+    pub subject: Option<String>,
+    /// Position in the filtergraph `subject` holds
+    pub span: Option<Span>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JobValidation {
+    pub ok: bool,
+    pub diagnostics: Vec<JobDiagnostic>,
+}
+
+impl JobValidation {
+    pub fn errors(&self) -> impl Iterator<Item = &JobDiagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+}
+
+struct Stage<'a> {
+    index: usize,
+    diagnostics: &'a mut Vec<JobDiagnostic>,
+}
+
+impl Stage<'_> {
+    fn push(&mut self, check: Check, severity: Severity, message: String, subject: Option<&str>) {
+        self.diagnostics.push(JobDiagnostic {
+            stage: self.index,
+            check,
+            severity,
+            message,
+            subject: subject.map(str::to_string),
+            span: None,
+        });
+    }
+}
+
+fn is_pipe(url: &str) -> bool {
+    url == "-" || url.starts_with("pipe:")
+}
+
+/// `rtmp://host/app`, but not `C:\videos` or a plain path.
+fn has_scheme(url: &str) -> bool {
+    url.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Checks every stage of a pipeline without transcoding anything: inputs exist and can
+/// be probed, filtergraphs match the cached catalog, and ffmpeg accepts the command when
+/// asked for zero frames.
+///
+/// Stages reading from the previous stage's pipe get no dry run, there is nothing to
+/// feed them.
+pub fn validate_job(cmds: &[String], envs: &[String]) -> JobValidation {
+    let mut diagnostics = Vec::new();
+    for (index, cmd) in cmds.iter().enumerate() {
+        let env = envs.get(index).map(String::as_str).unwrap_or_default();
+        let mut stage = Stage {
+            index,
+            diagnostics: &mut diagnostics,
+        };
+        validate_stage(cmd, env, &mut stage);
+    }
+
+    JobValidation {
+        ok: !diagnostics.iter().any(|d| d.severity == Severity::Error),
+        diagnostics,
+    }
+}
+
+fn validate_stage(cmd: &str, env: &str, stage: &mut Stage) {
+    let command = match FfmpegCommand::parse(cmd) {
+        Ok(command) => command,
+        Err(e) => {
+            stage.push(Check::Command, Severity::Error, e.to_string(), None);
+            return;
+        }
+    };
+    if command.outputs.is_empty() {
+        stage.push(
+            Check::Command,
+            Severity::Error,
+            "No output file".to_string(),
+            None,
+        );
+        return;
+    }
+
+    let mut inputs_ok = true;
+    let mut piped = false;
+    for input in &command.inputs {
+        let url = input.url.as_str();
+        if is_pipe(url) {
+            piped = true;
+            continue;
+        }
+        // Devices, lavfi sources and network streams can't be checked as files
+        if has_scheme(url) || input.options.iter().any(|o| o.name() == "f") {
+            continue;
+        }
+        // Image sequences such as frame_%04d.png only exist once expanded
+        if !url.contains('%') && !Path::new(url).exists() {
+            stage.push(
+                Check::Input,
+                Severity::Error,
+                format!("Input file not found: {url}"),
+                Some(url),
+            );
+            inputs_ok = false;
+            continue;
+        }
+        if let Err(e) = probe_media(url, &command.program, env) {
+            stage.push(Check::Input, Severity::Error, e.to_string(), Some(url));
+            inputs_ok = false;
+        }
+    }
+
+    // Only a cached catalog, parsing one isn't cheap
+    if let Ok(catalog) = load_nodes(&command.program, env) {
+        for issue in check_command(&command, &catalog.nodes) {
+            stage.diagnostics.push(JobDiagnostic {
+                stage: stage.index,
+                check: Check::Filtergraph,
+                severity: issue.issue.severity,
+                message: issue.issue.message,
+                subject: Some(issue.option),
+                span: issue.issue.span,
+            });
+        }
+    }
+
+    if piped {
+        stage.push(
+            Check::DryRun,
+            Severity::Info,
+            "Reads from the previous stage, dry run skipped".to_string(),
+            None,
+        );
+        return;
+    }
+    if !inputs_ok {
+        return;
+    }
+    if let Err(e) = dry_run(&command, env, stage) {
+        stage.push(
+            Check::DryRun,
+            Severity::Error,
+            format!("Dry run failed: {e}"),
+            None,
+        );
+    }
+}
+
+/// The command limited to zero frames, with outputs redirected into `dir`.
+///
+/// Runs with `-n` rather than `-y`: the redirected outputs are always new files, and a
+/// path the parser didn't recognise as an output is never overwritten.
+///
+/// Outputs keep their muxer so its options are still checked. Network outputs without
+/// an explicit format go to the null muxer instead.
+///
+/// With `-frames 0` ffmpeg may finish before a filtergraph is configured, since that
+/// waits for the first decoded frame, so bad filter options or formats that only show
+/// up then can still pass.
+fn dry_run_command(command: &FfmpegCommand, dir: &Path) -> FfmpegCommand {
+    let mut dry = command.clone();
+    dry.globals.retain(|o| {
+        !matches!(
+            o.name(),
+            "y" | "v" | "loglevel" | "stats_period" | "progress" | "report" | "vstats_file"
+        )
+    });
+    dry.set_global("-n", None);
+    dry.set_global("-hide_banner", None);
+    dry.set_global("-nostdin", None);
+    dry.set_global("-nostats", None);
+    dry.set_global("-v", Some("error"));
+
+    for (index, output) in dry.outputs.iter_mut().enumerate() {
+        output
+            .options
+            .retain(|o| !DRY_RUN_DROPPED.contains(&o.name()));
+        output.options.push(CmdOption::new("-frames", Some("0")));
+
+        let has_format = output.options.iter().any(|o| o.name() == "f");
+        if has_scheme(&output.url) && !has_format {
+            output.options.push(CmdOption::new("-f", Some("null")));
+            output.url = "-".to_string();
+            continue;
+        }
+        let name = if is_pipe(&output.url) || has_scheme(&output.url) {
+            "pipe".to_string()
+        } else {
+            Path::new(&output.url)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "output".to_string())
+        };
+        output.url = dir
+            .join(format!("{index}_{name}"))
+            .to_string_lossy()
+            .into_owned();
+    }
+    dry
+}
+
+fn dry_run(command: &FfmpegCommand, env: &str, stage: &mut Stage) -> Result<()> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let dir = get_data_dir()?
+        .join("tmp")
+        .join(format!("validate_{}_{nanos}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let dry = dry_run_command(command, &dir);
+    let result = run_with_timeout(&dry, env);
+    let _ = std::fs::remove_dir_all(&dir);
+    let (status, lines) = result?;
+
+    match status {
+        None => stage.push(
+            Check::DryRun,
+            Severity::Error,
+            format!("ffmpeg didn't finish within {}s", DRY_RUN_TIMEOUT.as_secs()),
+            None,
+        ),
+        Some(status) => {
+            let severity = if status.success() {
+                Severity::Warning
+            } else {
+                Severity::Error
+            };
+            for line in &lines {
+                stage.push(Check::DryRun, severity, line.clone(), None);
+            }
+            if !status.success() && lines.is_empty() {
+                stage.push(
+                    Check::DryRun,
+                    Severity::Error,
+                    format!("ffmpeg exited with {status}"),
+                    None,
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs `command` and collects its stderr lines. The status is `None` when it had to be
+/// killed.
+fn run_with_timeout(
+    command: &FfmpegCommand,
+    env: &str,
+) -> Result<(Option<ExitStatus>, Vec<String>)> {
+    let mut c = Command::new(&command.program);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        c.creation_flags(CREATE_NO_WINDOW);
+    }
+    c.args(command.to_args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    apply_env(&mut c, &parse_env_map(env));
+
+    let mut child = c.spawn()?;
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+
+    let deadline = Instant::now() + DRY_RUN_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let text = reader.join().unwrap_or_default();
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect();
+    Ok((status, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dry(line: &str) -> FfmpegCommand {
+        dry_run_command(&FfmpegCommand::parse(line).unwrap(), Path::new("/tmp/dry"))
+    }
+
+    /// `name` in the directory [`dry`] redirects outputs to.
+    fn redirected(name: &str) -> String {
+        Path::new("/tmp/dry")
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn flags(options: &[CmdOption]) -> Vec<String> {
+        options
+            .iter()
+            .map(|o| match &o.value {
+                Some(value) => format!("{} {value}", o.flag),
+                None => o.flag.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn schemes() {
+        for (url, expected) in [
+            ("rtmp://live.example.com/app/key", true),
+            ("srt://10.0.0.1:9000", true),
+            ("rtp+udp://host:1234", true),
+            ("icecast-stream.v2://host", true),
+            ("C:\\videos\\in.mp4", false),
+            ("C://videos/in.mp4", false),
+            ("file:in.mp4", false),
+            ("/videos/in.mp4", false),
+            ("://host", false),
+            ("my videos://x", false),
+        ] {
+            assert_eq!(has_scheme(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn outputs_go_to_the_temporary_directory() {
+        let cmd = dry("ffmpeg -i in.mp4 -c:v libx264 /videos/out/clip.mp4 -f null - -f nut pipe:1");
+        let urls: Vec<&str> = cmd.outputs.iter().map(|o| o.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                redirected("0_clip.mp4"),
+                redirected("1_pipe"),
+                redirected("2_pipe")
+            ]
+        );
+        assert_eq!(cmd.inputs[0].url, "in.mp4");
+
+        // A drive letter is no scheme, the output still goes to a file
+        let cmd = dry(r"ffmpeg -i in.mp4 C:\videos\out.mp4");
+        assert!(cmd.outputs[0].url.starts_with(&redirected("0_")));
+        assert_eq!(flags(&cmd.outputs[0].options), ["-frames 0"]);
+    }
+
+    #[test]
+    fn limits_and_passes_are_dropped() {
+        let cmd = dry(
+            "ffmpeg -i in.mp4 -t 10 -to 20 -frames:v 5 -vframes 5 -fs 1M -pass 2 \
+             -passlogfile log -c:v libx264 -b:v 2M out.mp4",
+        );
+        assert_eq!(
+            flags(&cmd.outputs[0].options),
+            ["-c:v libx264", "-b:v 2M", "-frames 0"]
+        );
+    }
+
+    #[test]
+    fn globals_are_replaced() {
+        let cmd = dry("ffmpeg -y -v info -stats_period 1 -progress pipe:2 -i in.mp4 out.mp4");
+        assert_eq!(
+            flags(&cmd.globals),
+            ["-n", "-hide_banner", "-nostdin", "-nostats", "-v error"]
+        );
+        assert_eq!(
+            cmd.to_args()[..7],
+            [
+                "-n",
+                "-hide_banner",
+                "-nostdin",
+                "-nostats",
+                "-v",
+                "error",
+                "-i"
+            ]
+        );
+    }
+
+    #[test]
+    fn network_outputs_use_the_null_muxer() {
+        let cmd = dry("ffmpeg -re -i in.mp4 -c copy rtmp://live/app -f mpegts srt://host:9000");
+        assert_eq!(cmd.outputs[0].url, "-");
+        assert_eq!(
+            flags(&cmd.outputs[0].options),
+            ["-c copy", "-frames 0", "-f null"]
+        );
+        // Its own muxer is still checked, writing to a file
+        assert_eq!(cmd.outputs[1].url, redirected("1_pipe"));
+        assert_eq!(flags(&cmd.outputs[1].options), ["-f mpegts", "-frames 0"]);
+    }
+}
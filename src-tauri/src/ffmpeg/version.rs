@@ -1,10 +1,123 @@
-use super::parser::{apply_env, parse_env_map};
+use super::parser::{apply_catalog_env, apply_env, parse_env_map};
 use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::process::{Command, Stdio};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
+static SEMVER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^n?(\d+)\.(\d+)(?:\.(\d+))?").unwrap());
+static GIT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|-)g([0-9a-f]{7,40})\b").unwrap());
+static LIBRARY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(lib\w+)\s+(\d+)\.\s*(\d+)\.\s*(\d+)").unwrap());
+
+/// What `ffmpeg -version` says about a build.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FfmpegBuild {
+    /// As printed, e.g. "6.1.1-3ubuntu5" or "N-113445-g1234abcd-20240101"
+    pub version: String,
+    /// major.minor.patch of release builds
+    pub semver: Option<[u32; 3]>,
+    /// Commit of git builds
+    pub git_revision: Option<String>,
+    pub compiler: Option<String>,
+    /// `configuration:` flags, e.g. "--enable-libx265"
+    pub configuration: Vec<String>,
+    /// libavcodec, libavformat, ... mapped to "60.31.102"
+    pub libraries: BTreeMap<String, String>,
+}
+
+impl FfmpegBuild {
+    /// Libraries enabled with `--enable-lib*`, without the prefix.
+    pub fn enabled_libs(&self) -> Vec<&str> {
+        self.configuration
+            .iter()
+            .filter_map(|f| f.strip_prefix("--enable-"))
+            .filter(|f| f.starts_with("lib"))
+            .collect()
+    }
+
+    /// Case-insensitive match against the version, configuration flags and libraries,
+    /// so "libfdk_aac" finds `--enable-libfdk-aac` too.
+    pub fn matches(&self, query: &str) -> bool {
+        let normalize = |s: &str| s.to_lowercase().replace('_', "-");
+        let query = normalize(query.trim());
+        if query.is_empty() {
+            return true;
+        }
+        normalize(&self.version).contains(&query)
+            || self
+                .configuration
+                .iter()
+                .any(|f| normalize(f).contains(&query))
+            || self.libraries.keys().any(|l| normalize(l).contains(&query))
+    }
+}
+
+/// Parses the lines returned by [`get_ffmpeg_version`].
+pub fn parse_build(lines: &[String]) -> FfmpegBuild {
+    let mut build = FfmpegBuild::default();
+
+    for line in lines {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("ffmpeg version ") {
+            build.version = rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            build.semver = SEMVER_RE.captures(&build.version).map(|c| {
+                let part = |i: usize| c.get(i).and_then(|m| m.as_str().parse().ok()).unwrap_or(0);
+                [part(1), part(2), part(3)]
+            });
+            build.git_revision = GIT_RE.captures(&build.version).map(|c| c[1].to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("built with ") {
+            build.compiler = Some(rest.to_string());
+        } else if let Some(rest) = trimmed.strip_prefix("configuration:") {
+            build.configuration = split_configuration(rest);
+        } else if let Some(c) = LIBRARY_RE.captures(line) {
+            build
+                .libraries
+                .insert(c[1].to_string(), format!("{}.{}.{}", &c[2], &c[3], &c[4]));
+        }
+    }
+    build
+}
+
+/// Splits the configure line on whitespace, keeping quoted values such as
+/// `--extra-cflags='-O2 -g'` together.
+fn split_configuration(line: &str) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    flags.push(std::mem::take(&mut current));
+                }
+            }
+            (None, c) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        flags.push(current);
+    }
+    flags
+}
+
+pub fn get_ffmpeg_build(name: &str, env_str: &str) -> Result<FfmpegBuild> {
+    get_ffmpeg_version(name, env_str).map(|lines| parse_build(&lines))
+}
+
 pub fn get_mediainfo(name: &str, ffmpeg: &str, env_str: &str) -> Result<Vec<String>> {
     let env_map = parse_env_map(env_str);
     let mut cmd = Command::new(ffmpeg);
@@ -53,7 +166,7 @@ pub fn get_ffmpeg_version(name: &str, env_str: &str) -> Result<Vec<String>> {
     cmd.arg("-version")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
-    apply_env(&mut cmd, &env_map);
+    apply_catalog_env(&mut cmd, &env_map);
     let out = cmd
         .output()
         .with_context(|| format!("spawning {name} -version"))?;
@@ -70,3 +183,119 @@ pub fn get_ffmpeg_version(name: &str, env_str: &str) -> Result<Vec<String>> {
 
     Ok(lines)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    /// A distribution build, in the layout of Ubuntu's 6.1 package.
+    const DISTRO: &str = "\
+ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023 the FFmpeg developers
+built with gcc 13 (Ubuntu 13.2.0-23ubuntu3)
+configuration: --prefix=/usr --extra-version=3ubuntu5 --toolchain=hardened --enable-gpl --enable-libfdk-aac --enable-libx264 --enable-libx265 --enable-shared --disable-stripping
+libavutil      58. 29.100 / 58. 29.100
+libavcodec     60. 31.102 / 60. 31.102
+libavformat    60. 16.100 / 60. 16.100
+libswscale      7.  5.100 /  7.  5.100
+libpostproc    57.  3.100 / 57.  3.100
+";
+
+    /// A static git build with quoted configure arguments.
+    const GIT: &str = "\
+ffmpeg version N-113445-g1234abcd5e-20240101 Copyright (c) 2000-2024 the FFmpeg developers
+built with gcc 13.2.0 (crosstool-NG 1.26.0.65_ecc5e41)
+configuration: --prefix=/ffbuild/prefix --pkg-config-flags=--static --extra-cflags='-O2 -I/ffbuild/prefix/include' --extra-ldflags=\"-L/ffbuild/prefix/lib -static\" --extra-version='built by \"ci\"' --enable-libsvtav1
+libavutil      59.  1.100 / 59.  1.100
+";
+
+    #[test]
+    fn distribution_build() {
+        let build = parse_build(&lines(DISTRO));
+        assert_eq!(build.version, "6.1.1-3ubuntu5");
+        assert_eq!(build.semver, Some([6, 1, 1]));
+        assert_eq!(build.git_revision, None);
+        assert_eq!(
+            build.compiler.as_deref(),
+            Some("gcc 13 (Ubuntu 13.2.0-23ubuntu3)")
+        );
+        assert_eq!(build.configuration.len(), 9);
+        assert_eq!(build.configuration[1], "--extra-version=3ubuntu5");
+        assert_eq!(build.libraries.len(), 5);
+        assert_eq!(build.libraries["libavcodec"], "60.31.102");
+        assert_eq!(build.libraries["libswscale"], "7.5.100");
+        assert_eq!(build.enabled_libs(), ["libfdk-aac", "libx264", "libx265"]);
+    }
+
+    #[test]
+    fn git_build_with_quoted_configuration() {
+        let build = parse_build(&lines(GIT));
+        assert_eq!(build.version, "N-113445-g1234abcd5e-20240101");
+        assert_eq!(build.semver, None);
+        assert_eq!(build.git_revision.as_deref(), Some("1234abcd5e"));
+        assert_eq!(
+            build.configuration,
+            [
+                "--prefix=/ffbuild/prefix",
+                "--pkg-config-flags=--static",
+                "--extra-cflags=-O2 -I/ffbuild/prefix/include",
+                "--extra-ldflags=-L/ffbuild/prefix/lib -static",
+                "--extra-version=built by \"ci\"",
+                "--enable-libsvtav1",
+            ]
+        );
+        assert_eq!(build.libraries["libavutil"], "59.1.100");
+    }
+
+    #[test]
+    fn release_versions() {
+        for (line, semver) in [
+            (
+                "ffmpeg version 7.1 Copyright (c) 2000-2024",
+                Some([7, 1, 0]),
+            ),
+            (
+                "ffmpeg version n7.0.2 Copyright (c) 2000-2024",
+                Some([7, 0, 2]),
+            ),
+            (
+                "ffmpeg version 4.4.2-0ubuntu0.22.04.1 Copyright",
+                Some([4, 4, 2]),
+            ),
+            // Builds named after their date have no release version
+            (
+                "ffmpeg version 2024-01-01-git-5c6e4e0f22-full_build-www.gyan.dev",
+                None,
+            ),
+            ("ffmpeg version git-2024-01-01-5c6e4e0", None),
+        ] {
+            assert_eq!(parse_build(&lines(line)).semver, semver, "{line}");
+        }
+    }
+
+    #[test]
+    fn split_configuration_quotes() {
+        assert_eq!(
+            split_configuration("  --a  --b='x  y' --c=\"'q'\" --d=''  "),
+            ["--a", "--b=x  y", "--c='q'", "--d="]
+        );
+        assert!(split_configuration("").is_empty());
+        // An unterminated quote runs to the end
+        assert_eq!(split_configuration("--a='x y"), ["--a=x y"]);
+    }
+
+    #[test]
+    fn matches_configuration_and_libraries() {
+        let build = parse_build(&lines(DISTRO));
+        assert!(build.matches("libfdk_aac"));
+        assert!(build.matches("LIBX265"));
+        assert!(build.matches("libpostproc"));
+        assert!(build.matches("ubuntu"));
+        assert!(build.matches(" "));
+        assert!(!build.matches("libsvtav1"));
+        assert!(!build.matches("nonfree"));
+    }
+}
@@ -0,0 +1,458 @@
+use super::command::{CmdInput, CmdOption, CmdOutput, FfmpegCommand};
+use super::parser::{apply_env, parse_env_map};
+use super::preview_cache::{InputIdentity, PreviewCache};
+use crate::utils::hash::short_hash;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::State;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// Mono rate the audio is decoded at for peaks. A timeline never shows buckets shorter
+/// than a millisecond, so anything higher only costs decoding time.
+const PEAK_SAMPLE_RATE: u32 = 8000;
+const DEFAULT_BUCKETS_PER_SECOND: u32 = 50;
+const MAX_BUCKETS_PER_SECOND: u32 = 1000;
+const DEFAULT_SPECTRUM_SIZE: (u32, u32) = (2048, 256);
+
+/// Min/max sample of every bucket of a file's first audio stream, downmixed to mono.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WaveformPeaks {
+    pub sample_rate: u32,
+    /// Samples per bucket, the last bucket may hold fewer
+    pub bucket_size: u32,
+    /// Seconds of audio decoded
+    pub duration: f64,
+    /// Signed 16-bit sample values
+    pub min: Vec<i16>,
+    pub max: Vec<i16>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Waveform {
+    pub peaks: WaveformPeaks,
+    /// `showspectrumpic` png spanning the whole file, when requested
+    pub spectrum: Option<String>,
+    /// Whether everything came from the preview cache
+    pub cached: bool,
+}
+
+/// Reduces raw s16le mono PCM to per-bucket min/max peaks.
+pub fn peaks_from_pcm(
+    mut pcm: impl Read,
+    sample_rate: u32,
+    bucket_size: u32,
+) -> Result<WaveformPeaks> {
+    let bucket_size = bucket_size.max(1);
+    let mut peaks = WaveformPeaks {
+        sample_rate,
+        bucket_size,
+        ..WaveformPeaks::default()
+    };
+
+    let mut samples: u64 = 0;
+    let (mut low, mut high) = (i16::MAX, i16::MIN);
+    let mut in_bucket = 0u32;
+    let mut push = |sample: i16| {
+        low = low.min(sample);
+        high = high.max(sample);
+        in_bucket += 1;
+        samples += 1;
+        if in_bucket == bucket_size {
+            peaks.min.push(low);
+            peaks.max.push(high);
+            (low, high, in_bucket) = (i16::MAX, i16::MIN, 0);
+        }
+    };
+
+    let mut buffer = vec![0u8; 64 * 1024];
+    // First byte of a sample split across two reads
+    let mut carry: Option<u8> = None;
+    loop {
+        let read = pcm.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let mut bytes = &buffer[..read];
+        if let Some(first) = carry.take() {
+            push(i16::from_le_bytes([first, bytes[0]]));
+            bytes = &bytes[1..];
+        }
+        let mut pairs = bytes.chunks_exact(2);
+        for pair in &mut pairs {
+            push(i16::from_le_bytes([pair[0], pair[1]]));
+        }
+        carry = pairs.remainder().first().copied();
+    }
+    if in_bucket > 0 {
+        peaks.min.push(low);
+        peaks.max.push(high);
+    }
+    peaks.duration = samples as f64 / sample_rate as f64;
+    Ok(peaks)
+}
+
+fn process(command: &FfmpegCommand, env: &str) -> Command {
+    let mut c = Command::new(&command.program);
+    #[cfg(windows)]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        c.creation_flags(CREATE_NO_WINDOW);
+    }
+    c.args(command.to_args());
+    apply_env(&mut c, &parse_env_map(env));
+    c
+}
+
+/// Reads `path`'s first audio stream with `output` options and `filter`.
+fn audio_command(
+    ffmpeg: &str,
+    path: &str,
+    filter: Option<String>,
+    output: CmdOutput,
+) -> FfmpegCommand {
+    let mut command = FfmpegCommand {
+        program: ffmpeg.to_string(),
+        inputs: vec![CmdInput {
+            options: Vec::new(),
+            url: path.to_string(),
+        }],
+        filter_complex: filter
+            .iter()
+            .map(|f| CmdOption::new("-filter_complex", Some(f)))
+            .collect(),
+        outputs: vec![output],
+        ..FfmpegCommand::default()
+    };
+    command.set_global("-hide_banner", None);
+    command.set_global("-nostdin", None);
+    command.set_global("-v", Some("error"));
+    command
+}
+
+/// Last line ffmpeg printed before failing.
+fn last_error(stderr: &str) -> &str {
+    stderr
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or("ffmpeg failed")
+}
+
+/// Decodes the first audio stream of `path` at [`PEAK_SAMPLE_RATE`] into a pipe and
+/// reduces it to peaks on the fly, so long files never sit in memory as samples.
+pub fn decode_peaks(
+    ffmpeg: &str,
+    env: &str,
+    path: &str,
+    buckets_per_second: u32,
+) -> Result<WaveformPeaks> {
+    let command = audio_command(
+        ffmpeg,
+        path,
+        None,
+        CmdOutput {
+            options: vec![
+                CmdOption::new("-map", Some("0:a:0")),
+                CmdOption::new("-ac", Some("1")),
+                CmdOption::new("-ar", Some(&PEAK_SAMPLE_RATE.to_string())),
+                CmdOption::new("-c:a", Some("pcm_s16le")),
+                CmdOption::new("-f", Some("s16le")),
+            ],
+            url: "pipe:1".to_string(),
+        },
+    );
+
+    let mut child = process(&command, env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to start ffmpeg")?;
+    let stdout = child.stdout.take().context("No ffmpeg output")?;
+    let stderr = child.stderr.take();
+    let reader = std::thread::spawn(move || {
+        let mut text = String::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_string(&mut text);
+        }
+        text
+    });
+
+    let peaks = peaks_from_pcm(
+        stdout,
+        PEAK_SAMPLE_RATE,
+        PEAK_SAMPLE_RATE / buckets_per_second,
+    );
+    let status = child.wait()?;
+    let errors = reader.join().unwrap_or_default();
+    if !status.success() {
+        bail!("Failed to decode audio: {}", last_error(&errors));
+    }
+    peaks
+}
+
+/// Renders a `showspectrumpic` of the first audio stream of `path` to `target`.
+pub fn render_spectrum(
+    ffmpeg: &str,
+    env: &str,
+    path: &str,
+    size: (u32, u32),
+    target: &Path,
+) -> Result<()> {
+    let (width, height) = size;
+    let mut command = audio_command(
+        ffmpeg,
+        path,
+        Some(format!(
+            "[0:a:0]showspectrumpic=s={width}x{height}:legend=0"
+        )),
+        CmdOutput {
+            options: vec![
+                CmdOption::new("-frames:v", Some("1")),
+                CmdOption::new("-update", Some("1")),
+            ],
+            url: target.to_string_lossy().into_owned(),
+        },
+    );
+    command.set_global("-y", None);
+
+    let output = process(&command, env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .context("Failed to start ffmpeg")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to render spectrum: {}",
+            last_error(&String::from_utf8_lossy(&output.stderr))
+        );
+    }
+    Ok(())
+}
+
+/// Cached peaks of `path`, decoded again when missing, unreadable or stale.
+fn cached_peaks(
+    cache: &PreviewCache,
+    dir: &Path,
+    ffmpeg: &str,
+    env: &str,
+    path: &str,
+    buckets_per_second: u32,
+    inputs: &[InputIdentity],
+) -> Result<(WaveformPeaks, bool)> {
+    let key = short_hash(&format!("waveform{path}{buckets_per_second}"));
+    if let Some(file) = cache.lookup(&key, inputs) {
+        match fs::read_to_string(&file).map(|text| serde_json::from_str(&text)) {
+            Ok(Ok(peaks)) => return Ok((peaks, true)),
+            _ => cache.discard(&file),
+        }
+    }
+
+    let peaks = decode_peaks(ffmpeg, env, path, buckets_per_second)?;
+    let file = dir.join(format!("{key}.json"));
+    fs::write(&file, serde_json::to_string(&peaks)?)
+        .with_context(|| format!("Failed to write {file:?}"))?;
+    cache.insert(&key, &file, inputs.to_vec())?;
+    Ok((peaks, false))
+}
+
+/// Cached spectrum of `path`, rendered again when missing or stale.
+fn cached_spectrum(
+    cache: &PreviewCache,
+    dir: &Path,
+    ffmpeg: &str,
+    env: &str,
+    path: &str,
+    size: (u32, u32),
+    inputs: &[InputIdentity],
+) -> Result<(PathBuf, bool)> {
+    let key = short_hash(&format!("spectrum{path}{}x{}", size.0, size.1));
+    if let Some(file) = cache.lookup(&key, inputs) {
+        return Ok((file, true));
+    }
+
+    let file = dir.join(format!("{key}.png"));
+    if let Err(e) = render_spectrum(ffmpeg, env, path, size, &file) {
+        cache.discard(&file);
+        return Err(e);
+    }
+    cache.insert(&key, &file, inputs.to_vec())?;
+    Ok((file, false))
+}
+
+/// Waveform peaks of `path`'s first audio stream and, with `spectrum`, a spectrogram of
+/// it. Both are kept in the preview cache until the file changes.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_waveform(
+    path: String,
+    ffmpeg: String,
+    env: String,
+    buckets_per_second: Option<u32>,
+    spectrum: Option<bool>,
+    spectrum_width: Option<u32>,
+    spectrum_height: Option<u32>,
+    cache: State<'_, PreviewCache>,
+) -> Result<Waveform, String> {
+    if path.trim().is_empty() {
+        return Err("Empty path provided".to_string());
+    }
+    let buckets_per_second = buckets_per_second
+        .unwrap_or(DEFAULT_BUCKETS_PER_SECOND)
+        .clamp(1, MAX_BUCKETS_PER_SECOND);
+    let size = (
+        spectrum_width
+            .filter(|&w| w > 0)
+            .unwrap_or(DEFAULT_SPECTRUM_SIZE.0),
+        spectrum_height
+            .filter(|&h| h > 0)
+            .unwrap_or(DEFAULT_SPECTRUM_SIZE.1),
+    );
+    let cache = (*cache).clone();
+
+    tokio::task::spawn_blocking(move || {
+        let dir = cache.dir()?;
+        let inputs: Vec<InputIdentity> = InputIdentity::of(&path).into_iter().collect();
+
+        let (peaks, peaks_cached) = cached_peaks(
+            &cache,
+            &dir,
+            &ffmpeg,
+            &env,
+            &path,
+            buckets_per_second,
+            &inputs,
+        )?;
+        let (spectrum, spectrum_cached) = if spectrum.unwrap_or(false) {
+            let (file, cached) =
+                cached_spectrum(&cache, &dir, &ffmpeg, &env, &path, size, &inputs)?;
+            (Some(file.to_string_lossy().into_owned()), cached)
+        } else {
+            (None, true)
+        };
+
+        anyhow::Ok(Waveform {
+            peaks,
+            spectrum,
+            cached: peaks_cached && spectrum_cached,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcm(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    /// Hands out at most `chunk` bytes per read, like a pipe that splits samples.
+    struct Chunked<'a> {
+        bytes: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn buckets_with_partial_last() {
+        let samples = [0, 100, -200, 50, i16::MIN, i16::MAX, 7];
+        let peaks = peaks_from_pcm(pcm(&samples).as_slice(), 4, 3).unwrap();
+        assert_eq!(peaks.min, [-200, i16::MIN, 7]);
+        assert_eq!(peaks.max, [100, i16::MAX, 7]);
+        assert_eq!((peaks.sample_rate, peaks.bucket_size), (4, 3));
+        assert_eq!(peaks.duration, 1.75);
+    }
+
+    #[test]
+    fn samples_split_across_reads() {
+        let samples: Vec<i16> = (0..1000).map(|i| (i * 37 % 2001 - 1000) as i16).collect();
+        let bytes = pcm(&samples);
+        let whole = peaks_from_pcm(bytes.as_slice(), 8000, 16).unwrap();
+        for chunk in [1, 3, 7, 1023] {
+            let peaks = peaks_from_pcm(
+                Chunked {
+                    bytes: &bytes,
+                    chunk,
+                },
+                8000,
+                16,
+            )
+            .unwrap();
+            assert_eq!(peaks.min, whole.min, "chunk {chunk}");
+            assert_eq!(peaks.max, whole.max, "chunk {chunk}");
+        }
+        assert_eq!(whole.min.len(), 63);
+    }
+
+    #[test]
+    fn empty_and_odd_input() {
+        let peaks = peaks_from_pcm(&[][..], 8000, 160).unwrap();
+        assert!(peaks.min.is_empty() && peaks.max.is_empty());
+        assert_eq!(peaks.duration, 0.0);
+
+        // A trailing half sample is dropped
+        let mut bytes = pcm(&[-3, 9]);
+        bytes.push(0x7f);
+        let peaks = peaks_from_pcm(bytes.as_slice(), 2, 0).unwrap();
+        assert_eq!(peaks.bucket_size, 1);
+        assert_eq!((peaks.min, peaks.max), (vec![-3, 9], vec![-3, 9]));
+        assert_eq!(peaks.duration, 1.0);
+    }
+
+    #[test]
+    fn last_error_line() {
+        assert_eq!(
+            last_error("Input #0\nclip.mp4: Invalid data found\n\n"),
+            "clip.mp4: Invalid data found"
+        );
+        assert_eq!(last_error("  \n"), "ffmpeg failed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn decode_peaks_reads_the_pipe() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let ffmpeg = dir.path().join("ffmpeg");
+        let script = |body: &str| {
+            fs::write(&ffmpeg, format!("#!/bin/sh\n{body}")).unwrap();
+            fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        let program = ffmpeg.to_str().unwrap();
+
+        // 320 silent samples and one of -1, in buckets of 160 at 50 per second
+        script("head -c 640 /dev/zero\nprintf '\\377\\377'\n");
+        let peaks = decode_peaks(program, "", "clip.wav", 50).unwrap();
+        assert_eq!(peaks.bucket_size, PEAK_SAMPLE_RATE / 50);
+        assert_eq!(peaks.min, [0, 0, -1]);
+        assert_eq!(peaks.max, [0, 0, -1]);
+
+        script("echo 'Stream map 0:a:0 matches no streams.' >&2\nexit 1\n");
+        let error = decode_peaks(program, "", "clip.mp4", 50)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Failed to decode audio: Stream map 0:a:0 matches no streams."
+        );
+    }
+}
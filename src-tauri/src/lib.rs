@@ -7,6 +7,7 @@ mod server;
 mod tray_manager;
 mod utils;
 mod watch_queue;
+mod watch_rules;
 mod workflow;
 
 use std::net::SocketAddr;
@@ -51,6 +52,7 @@ pub fn run() {
     tauri::Builder::default()
         .manage(ffmpeg::executor::TranscodeQueue::default())
         .manage(watch_queue::WatchFolderQueue::default())
+        .manage(ffmpeg::preview_cache::PreviewCache::default())
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 let _ = window.hide();
@@ -66,6 +68,20 @@ pub fn run() {
                 MenuItem::with_id(app, "wf_status", "Watchfolder: idle", false, None::<&str>)?;
             let cancel_all =
                 MenuItem::with_id(app, "cancel_all", "Cancel All Jobs", true, None::<&str>)?;
+            let pause_wf = MenuItem::with_id(
+                app,
+                "pause_wf",
+                "Pause All Watch Folders",
+                true,
+                None::<&str>,
+            )?;
+            let resume_wf = MenuItem::with_id(
+                app,
+                "resume_wf",
+                "Resume All Watch Folders",
+                true,
+                None::<&str>,
+            )?;
             let stop_wf =
                 MenuItem::with_id(app, "stop_wf", "Stop All Watch Folders", true, None::<&str>)?;
             let sep2 = PredefinedMenuItem::separator(app)?;
@@ -77,6 +93,8 @@ pub fn run() {
             menu.append(&queue_status)?;
             menu.append(&wf_status)?;
             menu.append(&cancel_all)?;
+            menu.append(&pause_wf)?;
+            menu.append(&resume_wf)?;
             menu.append(&stop_wf)?;
             menu.append(&sep2)?;
             menu.append(&quit)?;
@@ -95,6 +113,24 @@ pub fn run() {
                     "cancel_all" => {
                         let _ = app.emit("tray_cancel_all", ());
                     }
+                    "pause_wf" => {
+                        if let Some(wq) = app.try_state::<watch_queue::WatchFolderQueue>() {
+                            let ids: Vec<u64> = wq.get_info_list().iter().map(|w| w.id).collect();
+                            for id in ids {
+                                wq.pause_entry(id);
+                            }
+                            let _ = app.emit("watch_status_changed", wq.get_info_list());
+                        }
+                    }
+                    "resume_wf" => {
+                        if let Some(wq) = app.try_state::<watch_queue::WatchFolderQueue>() {
+                            let ids: Vec<u64> = wq.get_info_list().iter().map(|w| w.id).collect();
+                            for id in ids {
+                                wq.resume_entry(id);
+                            }
+                            let _ = app.emit("watch_status_changed", wq.get_info_list());
+                        }
+                    }
                     "stop_wf" => {
                         if let Some(wq) = app.try_state::<watch_queue::WatchFolderQueue>() {
                             let ids: Vec<u64> = wq.get_info_list().iter().map(|w| w.id).collect();
@@ -137,7 +173,11 @@ pub fn run() {
             commands::file_ops::pick_file,
             commands::file_ops::file_exists,
             commands::file_ops::get_file_info,
+            commands::file_ops::copy_file,
+            commands::file_ops::render_output_path,
+            commands::file_ops::expand_job,
             ffmpeg::executor::queue_transcode,
+            ffmpeg::executor::validate_job,
             ffmpeg::executor::set_max_concurrent,
             ffmpeg::executor::get_max_concurrent,
             ffmpeg::executor::get_queue_status,
@@ -145,12 +185,20 @@ pub fn run() {
             ffmpeg::executor::cancel_all_jobs,
             ffmpeg::executor::render_preview_request,
             ffmpeg::executor::render_preview_request,
+            ffmpeg::preview_cache::get_preview_cache_stats,
+            ffmpeg::preview_cache::set_preview_cache_limit,
+            ffmpeg::compare::render_ab_preview,
+            ffmpeg::waveform::get_waveform,
             commands::workflow_ops::save_graph,
             commands::workflow_ops::get_workflow,
             commands::workflow_ops::edit_workflow,
             commands::workflow_ops::create_workflow,
             commands::workflow_ops::delete_workflow,
             commands::workflow_ops::get_nodes_request,
+            commands::workflow_ops::find_workflows_by_build,
+            commands::workflow_ops::diff_ffmpeg_capabilities,
+            commands::workflow_ops::check_workflow_compat,
+            commands::workflow_ops::validate_command,
             commands::media_ops::get_mediainfo_request,
             commands::media_ops::delete_cache_request,
             workflow::manager::get_workflow_list,
@@ -161,6 +209,8 @@ pub fn run() {
             get_server_port,
             watch_queue::start_watchfolder,
             watch_queue::stop_watchfolder,
+            watch_queue::pause_watchfolder,
+            watch_queue::resume_watchfolder,
             watch_queue::get_watchfolders,
         ])
         .run(tauri::generate_context!())
@@ -1,12 +1,34 @@
 use glob::glob;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 use super::hash::short_hash;
-use crate::workflow::types::Node;
+use crate::ffmpeg::version::get_ffmpeg_version;
+use crate::workflow::types::Catalog;
 use crate::{FFStudioError, Result};
 use directories::ProjectDirs;
 
+/// Bump whenever the cached node format or the parser output changes.
+pub const CACHE_SCHEMA_VERSION: u32 = 7;
+
+/// Identifies the exact ffmpeg build a node cache was parsed from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BinaryFingerprint {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64,
+    pub configuration: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NodeCache {
+    schema: u32,
+    fingerprint: BinaryFingerprint,
+    catalog: Catalog,
+}
+
 pub fn get_data_dir() -> Result<PathBuf> {
     if let Some(proj_dirs) = ProjectDirs::from("com", "FAI", "FFStudio") {
         let dir = proj_dirs.data_dir().to_path_buf();
@@ -54,15 +76,72 @@ pub fn init_workdir() -> Result<()> {
     Ok(())
 }
 
-pub fn load_nodes(ffmpeg_path: &str) -> Result<Vec<Node>> {
-    if ffmpeg_path.trim().is_empty() {
-        return Err(FFStudioError::file_system("FFmpeg path cannot be empty"));
+/// Finds the file a binary name refers to, searching `PATH` for bare names.
+fn resolve_binary(ffmpeg_path: &str) -> Option<PathBuf> {
+    let path = Path::new(ffmpeg_path);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf());
     }
 
+    let exe_names: Vec<String> = if cfg!(windows) && path.extension().is_none() {
+        vec![format!("{ffmpeg_path}.exe"), ffmpeg_path.to_string()]
+    } else {
+        vec![ffmpeg_path.to_string()]
+    };
+    let search = std::env::var_os("PATH")?;
+    std::env::split_paths(&search)
+        .flat_map(|dir| exe_names.iter().map(move |n| dir.join(n)))
+        .find(|p| p.is_file())
+}
+
+pub fn binary_fingerprint(ffmpeg_path: &str, env_str: &str) -> Result<BinaryFingerprint> {
+    let resolved = resolve_binary(ffmpeg_path).ok_or_else(|| {
+        FFStudioError::file_system(format!("FFmpeg binary not found: {ffmpeg_path}"))
+    })?;
+    let metadata = fs::metadata(&resolved).map_err(|e| {
+        FFStudioError::file_system(format!(
+            "Failed to read metadata for {}: {e}",
+            resolved.display()
+        ))
+    })?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let version = get_ffmpeg_version(ffmpeg_path, env_str)?;
+    let configuration = version
+        .iter()
+        .find(|l| l.trim_start().starts_with("configuration:"))
+        .or(version.first())
+        .cloned()
+        .unwrap_or_default();
+
+    Ok(BinaryFingerprint {
+        path: resolved.to_string_lossy().to_string(),
+        size: metadata.len(),
+        modified,
+        configuration,
+    })
+}
+
+fn nodes_cache_path(ffmpeg_path: &str) -> Result<PathBuf> {
     let filename = short_hash(ffmpeg_path);
     let data_path = get_data_dir()?;
-    let cache_path = data_path.join("cache");
-    let full_path = cache_path.join(filename + ".json");
+    Ok(data_path.join("cache").join(filename + ".json"))
+}
+
+/// Loads the cached catalog for `ffmpeg_path`. Fails if there is no cache, or if it was
+/// written by another schema version or for a binary that has since been replaced. A
+/// catalog where some category failed to parse is returned as is, see its report.
+pub fn load_nodes(ffmpeg_path: &str, env_str: &str) -> Result<Catalog> {
+    if ffmpeg_path.trim().is_empty() {
+        return Err(FFStudioError::file_system("FFmpeg path cannot be empty"));
+    }
+
+    let full_path = nodes_cache_path(ffmpeg_path)?;
 
     if !full_path.exists() {
         return Err(FFStudioError::file_system(format!(
@@ -73,28 +152,43 @@ pub fn load_nodes(ffmpeg_path: &str) -> Result<Vec<Node>> {
     let data = std::fs::read_to_string(&full_path)
         .map_err(|e| FFStudioError::file_system(format!("Failed to read cache file: {e}")))?;
 
-    let nodes: Vec<Node> = serde_json::from_str(&data)
+    let cache: NodeCache = serde_json::from_str(&data)
         .map_err(|e| FFStudioError::json(format!("Failed to parse cached nodes: {e}")))?;
 
+    if cache.schema != CACHE_SCHEMA_VERSION {
+        return Err(FFStudioError::file_system(format!(
+            "Cache schema {} is outdated for FFmpeg path: {ffmpeg_path}",
+            cache.schema
+        )));
+    }
+
+    if cache.fingerprint != binary_fingerprint(ffmpeg_path, env_str)? {
+        return Err(FFStudioError::file_system(format!(
+            "FFmpeg binary changed since it was cached: {ffmpeg_path}"
+        )));
+    }
+
     log::debug!(
         "Loaded {} nodes from cache for FFmpeg path: {}",
-        nodes.len(),
+        cache.catalog.nodes.len(),
         ffmpeg_path
     );
-    Ok(nodes)
+    Ok(cache.catalog)
 }
 
-pub fn save_nodes(ffmpeg_path: &str, nodes: &Vec<Node>) -> Result<()> {
+pub fn save_nodes(ffmpeg_path: &str, env_str: &str, catalog: &Catalog) -> Result<()> {
     if ffmpeg_path.trim().is_empty() {
         return Err(FFStudioError::file_system("FFmpeg path cannot be empty"));
     }
 
-    let filename = short_hash(ffmpeg_path);
-    let data_path = get_data_dir()?;
-    let cache_path = data_path.join("cache");
-    let full_path = cache_path.join(filename + ".json");
+    let full_path = nodes_cache_path(ffmpeg_path)?;
 
-    let data = serde_json::to_string_pretty(nodes)
+    let cache = NodeCache {
+        schema: CACHE_SCHEMA_VERSION,
+        fingerprint: binary_fingerprint(ffmpeg_path, env_str)?,
+        catalog: catalog.clone(),
+    };
+    let data = serde_json::to_string_pretty(&cache)
         .map_err(|e| FFStudioError::json(format!("Failed to serialize nodes: {e}")))?;
 
     std::fs::write(&full_path, data)
@@ -102,7 +196,7 @@ pub fn save_nodes(ffmpeg_path: &str, nodes: &Vec<Node>) -> Result<()> {
 
     log::debug!(
         "Saved {} nodes to cache for FFmpeg path: {}",
-        nodes.len(),
+        catalog.nodes.len(),
         ffmpeg_path
     );
     Ok(())
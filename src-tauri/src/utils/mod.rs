@@ -1,3 +1,4 @@
 pub mod filesystem;
 pub mod hash;
+pub mod template;
 pub mod version;
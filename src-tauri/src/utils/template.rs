@@ -0,0 +1,400 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::hash::short_hash;
+use crate::ffmpeg::command::quote_arg;
+use crate::ffmpeg::probe::MediaProbe;
+use crate::{FFStudioError, Result};
+
+const PROBE_FIELDS: [&str; 4] = ["width", "height", "duration", "codec"];
+
+/// Values available to `{placeholder}` and `{{variable}}` expansion.
+#[derive(Clone, Debug, Default)]
+pub struct TemplateContext {
+    pub input: PathBuf,
+    /// Further inputs of a batch job, `{name}` and `{hash}` combine them with `input`
+    pub extra_inputs: Vec<PathBuf>,
+    pub output: Option<PathBuf>,
+    /// Root `{relpath}` is computed against, usually the watch directory
+    pub base_dir: Option<PathBuf>,
+    pub workflow: String,
+    pub counter: u64,
+    pub probe: Option<MediaProbe>,
+    pub variables: HashMap<String, String>,
+}
+
+/// What to do when a rendered output path is already taken.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Collision {
+    #[default]
+    Skip,
+    Suffix,
+    Overwrite,
+}
+
+/// Returns true if `template` uses fields that require probing the input.
+pub fn needs_probe(template: &str) -> bool {
+    PROBE_FIELDS
+        .iter()
+        .any(|f| template.contains(&format!("{{{f}}}")) || template.contains(&format!("{{{f}:")))
+}
+
+/// Expands placeholders in `template`.
+///
+/// Supported: `{input}`, `{output}` (both quoted when needed), `{name}`, `{ext}`, `{parent}`, `{relpath}`,
+/// `{hash}`, `{date}`/`{date:%Y%m%d}`, `{counter}`/`{counter:04}`, `{index}` (zero-based
+/// counter), `{workflow}`, `{width}`, `{height}`, `{duration}`, `{codec}` and `{{variable}}`.
+/// Anything else is kept as is, so filter expressions such as `%{pts}` survive.
+pub fn render(template: &str, ctx: &TemplateContext) -> Result<String> {
+    render_with(template, ctx, true)
+}
+
+/// Like [`render`] for a single, already split argument: `{input}` and `{output}` are not
+/// quoted.
+pub fn render_arg(template: &str, ctx: &TemplateContext) -> Result<String> {
+    render_with(template, ctx, false)
+}
+
+fn render_with(template: &str, ctx: &TemplateContext, quote_paths: bool) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(body) = rest.strip_prefix("{{") {
+            if let Some(end) = body.find("}}") {
+                let key = body[..end].trim();
+                match ctx.variables.get(key) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[..end + 4]),
+                }
+                rest = &body[end + 2..];
+                continue;
+            }
+        }
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let placeholder = &rest[1..end];
+        if placeholder.contains('{') {
+            out.push('{');
+            rest = &rest[1..];
+            continue;
+        }
+        let (key, arg) = match placeholder.split_once(':') {
+            Some((k, a)) => (k, Some(a)),
+            None => (placeholder, None),
+        };
+        match expand(key, arg, ctx, quote_paths)? {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Substitutes only `{{variable}}`, leaving every other placeholder alone.
+pub fn render_variables(template: &str, variables: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let body = &rest[start + 2..];
+        let Some(end) = body.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        match variables.get(body[..end].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &body[end + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn pad(value: u64, width: Option<&str>) -> Result<String> {
+    match width {
+        Some(width) => {
+            let width: usize = width
+                .parse()
+                .map_err(|_| FFStudioError::parse(format!("Invalid counter width: {width}")))?;
+            Ok(format!("{value:0width$}"))
+        }
+        None => Ok(value.to_string()),
+    }
+}
+
+fn expand(
+    key: &str,
+    arg: Option<&str>,
+    ctx: &TemplateContext,
+    quote_paths: bool,
+) -> Result<Option<String>> {
+    let lossy = |s: Option<&std::ffi::OsStr>| {
+        s.map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    let quote = |path: &Path| {
+        if quote_paths {
+            quote_arg(&path.to_string_lossy())
+        } else {
+            path.to_string_lossy().to_string()
+        }
+    };
+    let probe = ctx.probe.as_ref();
+    let inputs = || std::iter::once(&ctx.input).chain(&ctx.extra_inputs);
+
+    let value = match key {
+        "input" => quote(&ctx.input),
+        "output" => match &ctx.output {
+            Some(output) => quote(output),
+            None => return Ok(None),
+        },
+        "name" => inputs()
+            .map(|i| lossy(i.file_stem()))
+            .collect::<Vec<_>>()
+            .join("_"),
+        "ext" => lossy(ctx.input.extension()),
+        "parent" => lossy(ctx.input.parent().and_then(|p| p.file_name())),
+        "relpath" => {
+            let parent = ctx.input.parent().unwrap_or(Path::new(""));
+            ctx.base_dir
+                .as_ref()
+                .and_then(|base| parent.strip_prefix(base).ok())
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default()
+        }
+        "hash" => short_hash(
+            &inputs()
+                .map(|i| i.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("|"),
+        ),
+        "date" => {
+            let fmt = arg.unwrap_or("%Y%m%d");
+            let items: Vec<Item> = StrftimeItems::new(fmt).collect();
+            if items.iter().any(|i| matches!(i, Item::Error)) {
+                return Err(FFStudioError::parse(format!("Invalid date format: {fmt}")));
+            }
+            Local::now()
+                .format_with_items(items.into_iter())
+                .to_string()
+        }
+        "counter" => pad(ctx.counter, arg)?,
+        "index" => pad(ctx.counter.saturating_sub(1), arg)?,
+        "workflow" => ctx.workflow.clone(),
+        "width" => probe
+            .and_then(|p| p.width)
+            .map(|w| w.to_string())
+            .unwrap_or_default(),
+        "height" => probe
+            .and_then(|p| p.height)
+            .map(|h| h.to_string())
+            .unwrap_or_default(),
+        "duration" => probe
+            .and_then(|p| p.duration)
+            .map(|d| format!("{}", d.round() as u64))
+            .unwrap_or_default(),
+        "codec" => probe
+            .and_then(|p| p.video_codec.clone().or_else(|| p.audio_codec.clone()))
+            .unwrap_or_default(),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+/// Applies the collision policy to `path`. `taken` lists paths already claimed by jobs that
+/// have not written their output yet. Returns `None` when the file should be skipped.
+pub fn resolve_collision(
+    path: PathBuf,
+    mode: &Collision,
+    taken: &HashSet<PathBuf>,
+) -> Option<PathBuf> {
+    let is_taken = |p: &Path| p.exists() || taken.contains(p);
+    if !is_taken(&path) {
+        return Some(path);
+    }
+
+    match mode {
+        Collision::Skip => None,
+        Collision::Overwrite => Some(path),
+        Collision::Suffix => {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let ext = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            (1..)
+                .map(|n| path.with_file_name(format!("{stem}_{n}{ext}")))
+                .find(|p| !is_taken(p))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::command::split_command_line;
+
+    fn context(input: &str) -> TemplateContext {
+        TemplateContext {
+            input: PathBuf::from(input),
+            counter: 7,
+            workflow: "web".to_string(),
+            ..TemplateContext::default()
+        }
+    }
+
+    /// Empty directory under the system temp dir, unique to `name`.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ff-studio-template-{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn render_path_fields() {
+        let mut ctx = context("/videos/show/ep 01.mkv");
+        ctx.base_dir = Some(PathBuf::from("/videos"));
+        let out = render("{name}|{ext}|{parent}|{relpath}|{workflow}", &ctx).unwrap();
+        assert_eq!(out, "ep 01|mkv|show|show|web");
+    }
+
+    #[test]
+    fn render_counters() {
+        let ctx = context("a.mp4");
+        assert_eq!(render("{counter}", &ctx).unwrap(), "7");
+        assert_eq!(render("{counter:04}", &ctx).unwrap(), "0007");
+        assert_eq!(render("{index:03}", &ctx).unwrap(), "006");
+        assert!(render("{counter:x}", &ctx).is_err());
+    }
+
+    #[test]
+    fn render_quotes_paths_only_when_needed() {
+        let mut ctx = context("/in/plain.mp4");
+        ctx.output = Some(PathBuf::from("/out/with space.mp4"));
+        let line = render("ffmpeg -i {input} {output}", &ctx).unwrap();
+        assert_eq!(line, "ffmpeg -i /in/plain.mp4 \"/out/with space.mp4\"");
+        assert_eq!(
+            split_command_line(&line).unwrap(),
+            ["ffmpeg", "-i", "/in/plain.mp4", "/out/with space.mp4"]
+        );
+    }
+
+    #[test]
+    fn render_quoted_windows_path_round_trips() {
+        let ctx = context(r#"C:\My Videos\say "hi".mp4"#);
+        let line = render("-i {input}", &ctx).unwrap();
+        assert_eq!(
+            split_command_line(&line).unwrap(),
+            ["-i", r#"C:\My Videos\say "hi".mp4"#]
+        );
+    }
+
+    #[test]
+    fn render_arg_does_not_quote() {
+        let ctx = context("/in/with space.mp4");
+        assert_eq!(render_arg("{input}", &ctx).unwrap(), "/in/with space.mp4");
+    }
+
+    #[test]
+    fn render_keeps_unknown_placeholders() {
+        let ctx = context("a.mp4");
+        let filter = "drawtext=text='%{pts}':x={unknown}";
+        assert_eq!(render(filter, &ctx).unwrap(), filter);
+        assert_eq!(render("{output}", &ctx).unwrap(), "{output}");
+        assert_eq!(render("{{missing}}", &ctx).unwrap(), "{{missing}}");
+        assert_eq!(render("open {", &ctx).unwrap(), "open {");
+    }
+
+    #[test]
+    fn render_substitutes_variables() {
+        let mut ctx = context("a.mp4");
+        ctx.variables.insert("crf".to_string(), "23".to_string());
+        assert_eq!(render("-crf {{ crf }} {name}", &ctx).unwrap(), "-crf 23 a");
+    }
+
+    #[test]
+    fn render_variables_leaves_placeholders() {
+        let variables = HashMap::from([("preset".to_string(), "slow".to_string())]);
+        assert_eq!(
+            render_variables("-preset {{preset}} {input} {{other}}", &variables),
+            "-preset slow {input} {{other}}"
+        );
+        assert_eq!(render_variables("{{preset", &variables), "{{preset");
+    }
+
+    #[test]
+    fn needs_probe_fields() {
+        assert!(needs_probe("{width}x{height}"));
+        assert!(needs_probe("{duration:s}"));
+        assert!(!needs_probe("{name}_{counter}"));
+    }
+
+    #[test]
+    fn resolve_collision_free_path() {
+        let dir = scratch_dir("free");
+        let path = dir.join("out.mp4");
+        for mode in [Collision::Skip, Collision::Suffix, Collision::Overwrite] {
+            assert_eq!(
+                resolve_collision(path.clone(), &mode, &HashSet::new()),
+                Some(path.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_collision_existing_file() {
+        let dir = scratch_dir("existing");
+        let path = dir.join("out.mp4");
+        std::fs::write(&path, b"").unwrap();
+        std::fs::write(dir.join("out_1.mp4"), b"").unwrap();
+
+        let none = HashSet::new();
+        assert_eq!(
+            resolve_collision(path.clone(), &Collision::Skip, &none),
+            None
+        );
+        assert_eq!(
+            resolve_collision(path.clone(), &Collision::Overwrite, &none),
+            Some(path.clone())
+        );
+        assert_eq!(
+            resolve_collision(path.clone(), &Collision::Suffix, &none),
+            Some(dir.join("out_2.mp4"))
+        );
+    }
+
+    #[test]
+    fn resolve_collision_reserved_paths() {
+        let dir = scratch_dir("reserved");
+        let path = dir.join("out");
+        let taken = HashSet::from([path.clone(), dir.join("out_1")]);
+        assert_eq!(
+            resolve_collision(path.clone(), &Collision::Skip, &taken),
+            None
+        );
+        assert_eq!(
+            resolve_collision(path.clone(), &Collision::Suffix, &taken),
+            Some(dir.join("out_2"))
+        );
+    }
+}
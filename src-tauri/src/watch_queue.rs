@@ -1,12 +1,16 @@
 use crate::ffmpeg::executor::TranscodeQueue;
-use crate::utils;
+use crate::ffmpeg::expand::{expand_job, JobTemplate};
+use crate::utils::template::Collision;
+use crate::watch_rules::{self, RouteRule};
+use crate::workflow::manager::get_workflow_list;
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
 use glob::Pattern;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tauri::Emitter;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Listener};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum WatchStatus {
@@ -14,6 +18,81 @@ pub enum WatchStatus {
     Paused,
 }
 
+/// What to do on resume with files that arrived while a folder was paused.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ResumePolicy {
+    #[default]
+    Process,
+    Skip,
+}
+
+/// What to do with a source file once its job has finished.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PostAction {
+    #[default]
+    Keep,
+    Delete,
+    /// Move into a directory. Relative paths are resolved against the watch directory.
+    Move(String),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcess {
+    pub on_success: PostAction,
+    pub on_failure: PostAction,
+    /// Write a `<output>.json` report next to the output file
+    pub write_report: bool,
+}
+
+/// Time of day a watch folder is allowed to queue files, e.g. 20:00-07:00 on weekdays.
+/// Windows that end before they start run past midnight and belong to the day they start on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleWindow {
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
+    /// "Mon" to "Sun"; empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+impl ScheduleWindow {
+    fn parse(&self) -> Result<(NaiveTime, NaiveTime, Vec<Weekday>), String> {
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s.trim(), "%H:%M")
+                .map_err(|_| format!("Invalid schedule time '{s}', expected HH:MM"))
+        };
+        let days = self
+            .days
+            .iter()
+            .map(|d| {
+                d.trim()
+                    .parse::<Weekday>()
+                    .map_err(|_| format!("Invalid schedule day '{d}'"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((time(&self.start)?, time(&self.end)?, days))
+    }
+
+    /// Whether the window is open at local time `now`.
+    pub fn is_open(&self, now: NaiveDateTime) -> bool {
+        let Ok((start, end, days)) = self.parse() else {
+            return false;
+        };
+        let on_day = |day: Weekday| days.is_empty() || days.contains(&day);
+        let time = now.time();
+        let today = now.weekday();
+
+        if start <= end {
+            on_day(today) && time >= start && time < end
+        } else {
+            (on_day(today) && time >= start) || (on_day(today.pred()) && time < end)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WatchFolderInfo {
     pub id: u64,
@@ -24,6 +103,13 @@ pub struct WatchFolderInfo {
     pub status: WatchStatus,
     pub files_queued: u64,
     pub workflow: String,
+    pub post_process: PostProcess,
+    pub resume_policy: ResumePolicy,
+    pub rules: Vec<RouteRule>,
+    pub collision: Collision,
+    pub max_in_flight: Option<usize>,
+    pub schedule: Option<ScheduleWindow>,
+    pub in_window: bool,
 }
 
 pub struct WatchFolderEntry {
@@ -39,6 +125,29 @@ pub struct WatchFolderEntry {
     pub status: WatchStatus,
     pub seen_files: HashSet<PathBuf>,
     pub files_queued: u64,
+    pub post_process: PostProcess,
+    pub resume_policy: ResumePolicy,
+    /// Checked in order; files no rule matches use `ffmpeg_template`
+    pub rules: Vec<RouteRule>,
+    /// Graph `{{variables}}` available to output names and templates
+    pub variables: HashMap<String, String>,
+    pub collision: Collision,
+    /// Outputs of queued jobs that may not exist on disk yet
+    pub reserved_outputs: HashSet<PathBuf>,
+    /// Cap on this folder's queued plus running jobs
+    pub max_in_flight: Option<usize>,
+    /// Files matched outside the window stay pending until it opens
+    pub schedule: Option<ScheduleWindow>,
+}
+
+/// A file picked up by a poll, ready to be queued.
+struct MatchedFile {
+    input: String,
+    output: String,
+    /// Every output the job writes, released from `reserved_outputs` when it ends
+    outputs: Vec<PathBuf>,
+    cmd: String,
+    workflow: String,
 }
 
 #[derive(Clone)]
@@ -74,8 +183,25 @@ impl WatchFolderQueue {
         ffmpeg_bin: String,
         envs: String,
         workflow: String,
+        post_process: PostProcess,
+        resume_policy: ResumePolicy,
+        rules: Vec<RouteRule>,
+        variables: HashMap<String, String>,
+        collision: Collision,
+        max_in_flight: Option<usize>,
+        schedule: Option<ScheduleWindow>,
     ) -> Result<u64, String> {
         let pattern = Pattern::new(&pattern_str).map_err(|e| format!("Invalid pattern: {e}"))?;
+        for rule in &rules {
+            if let Some(glob) = &rule.matches.glob {
+                Pattern::new(glob)
+                    .map_err(|e| format!("Invalid pattern in rule '{}': {e}", rule.name))?;
+            }
+        }
+
+        if let Some(schedule) = &schedule {
+            schedule.parse()?;
+        }
 
         if !watch_dir.is_dir() {
             return Err("Watch directory does not exist".to_string());
@@ -95,6 +221,14 @@ impl WatchFolderQueue {
             status: WatchStatus::Watching,
             seen_files: HashSet::new(),
             files_queued: 0,
+            post_process,
+            resume_policy,
+            rules,
+            variables,
+            collision,
+            reserved_outputs: HashSet::new(),
+            max_in_flight: max_in_flight.map(|m| m.max(1)),
+            schedule,
         };
 
         let mut entries = self.entries.lock().unwrap();
@@ -109,6 +243,33 @@ impl WatchFolderQueue {
         entries.len() < len
     }
 
+    pub fn pause_entry(&self, id: u64) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.status = WatchStatus::Paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn resume_entry(&self, id: u64) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                if entry.status == WatchStatus::Paused && entry.resume_policy == ResumePolicy::Skip
+                {
+                    let pending = list_matches(entry);
+                    entry.seen_files.extend(pending);
+                }
+                entry.status = WatchStatus::Watching;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn get_info_list(&self) -> Vec<WatchFolderInfo> {
         let entries = self.entries.lock().unwrap();
         entries.iter().map(|e| e.clone_info()).collect()
@@ -116,6 +277,12 @@ impl WatchFolderQueue {
 }
 
 impl WatchFolderEntry {
+    fn in_window(&self) -> bool {
+        self.schedule
+            .as_ref()
+            .map_or(true, |s| s.is_open(Local::now().naive_local()))
+    }
+
     fn clone_info(&self) -> WatchFolderInfo {
         WatchFolderInfo {
             id: self.id,
@@ -126,72 +293,349 @@ impl WatchFolderEntry {
             status: self.status.clone(),
             files_queued: self.files_queued,
             workflow: self.workflow.clone(),
+            post_process: self.post_process.clone(),
+            resume_policy: self.resume_policy.clone(),
+            rules: self.rules.clone(),
+            collision: self.collision.clone(),
+            max_in_flight: self.max_in_flight,
+            schedule: self.schedule.clone(),
+            in_window: self.in_window(),
         }
     }
 }
 
-fn resolve_placeholders(template: &str, input_file: &str, output_path: &str) -> String {
-    template
-        .replace("{input}", &format!("\"{}\"", input_file))
-        .replace("{output}", &format!("\"{}\"", output_path))
-}
-
-fn build_output_path(output_dir: &Path, output_name: &str, input_file: &Path) -> PathBuf {
-    let stem = input_file
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let hash = utils::hash::short_hash(&input_file.to_string_lossy());
-
-    let name = output_name
-        .replace("{name}", &stem)
-        .replace("{hash}", &hash);
-
-    output_dir.join(name)
-}
-
-fn poll_folder(entry: &mut WatchFolderEntry) -> Vec<(String, String, String)> {
+fn list_matches(entry: &WatchFolderEntry) -> Vec<PathBuf> {
     let pattern_str = format!(
         "{}/{}",
         entry.watch_dir.to_string_lossy(),
         entry.pattern.as_str()
     );
 
-    let mut results = Vec::new();
-
-    let glob_paths: Vec<PathBuf> = match glob::glob(&pattern_str) {
+    match glob::glob(&pattern_str) {
         Ok(entries) => entries.flatten().collect(),
-        Err(_) => return results,
-    };
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Number of this folder's jobs that are queued or running.
+fn count_in_flight(queue: &TranscodeQueue, watch_id: u64) -> usize {
+    let tag = format!("W-{watch_id}");
+    queue
+        .get_queue_status()
+        .iter()
+        .filter(|job| {
+            serde_json::from_str::<serde_json::Value>(&job.desc)
+                .ok()
+                .and_then(|desc| desc.get("tags").cloned())
+                .and_then(|tags| tags.as_array().cloned())
+                .is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(&tag)))
+        })
+        .count()
+}
+
+/// What a poll needs from an entry, copied so rules can probe and templates expand
+/// without holding the entries lock.
+struct PollContext {
+    watch_dir: PathBuf,
+    output_dir: PathBuf,
+    output_name: String,
+    ffmpeg_template: String,
+    ffmpeg_bin: String,
+    envs: String,
+    workflow: String,
+    rules: Vec<RouteRule>,
+    variables: HashMap<String, String>,
+    collision: Collision,
+    reserved_outputs: HashSet<PathBuf>,
+    files_queued: u64,
+    max_in_flight: Option<usize>,
+}
 
-    for path in glob_paths {
+impl PollContext {
+    fn of(entry: &WatchFolderEntry) -> Self {
+        Self {
+            watch_dir: entry.watch_dir.clone(),
+            output_dir: entry.output_dir.clone(),
+            output_name: entry.output_name.clone(),
+            ffmpeg_template: entry.ffmpeg_template.clone(),
+            ffmpeg_bin: entry.ffmpeg_bin.clone(),
+            envs: entry.envs.clone(),
+            workflow: entry.workflow.clone(),
+            rules: entry.rules.clone(),
+            variables: entry.variables.clone(),
+            collision: entry.collision.clone(),
+            reserved_outputs: entry.reserved_outputs.clone(),
+            files_queued: entry.files_queued,
+            max_in_flight: entry.max_in_flight,
+        }
+    }
+}
+
+/// New files of `entry`, in glob order. Anything that isn't a file is marked seen.
+fn unseen_files(entry: &mut WatchFolderEntry) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in list_matches(entry) {
         if entry.seen_files.contains(&path) {
             continue;
         }
-
-        if !path.is_file() {
+        if path.is_file() {
+            files.push(path);
+        } else {
             entry.seen_files.insert(path);
-            continue;
+        }
+    }
+    files
+}
+
+/// Picks up to `limit` of `files`. Returns the jobs and every file it is done with; the
+/// rest stay unseen for a later poll.
+fn poll_folder(
+    ctx: &mut PollContext,
+    files: Vec<PathBuf>,
+    limit: usize,
+) -> (Vec<MatchedFile>, Vec<PathBuf>) {
+    let mut results = Vec::new();
+    let mut handled = Vec::new();
+
+    for path in files {
+        if results.len() >= limit {
+            break;
         }
 
-        let output_path = build_output_path(&entry.output_dir, &entry.output_name, &path);
-        if output_path.exists() {
-            entry.seen_files.insert(path);
+        let rule = watch_rules::find_rule(
+            &ctx.rules,
+            &path,
+            &ctx.watch_dir,
+            &ctx.ffmpeg_bin,
+            &ctx.envs,
+        );
+        let (template, ffmpeg_bin, output_dir, output_name, workflow) = match rule {
+            Some(rule) => (
+                &rule.ffmpeg_template,
+                rule.ffmpeg_bin.as_ref().unwrap_or(&ctx.ffmpeg_bin),
+                rule.output_dir
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| ctx.output_dir.clone()),
+                rule.output_name.as_ref().unwrap_or(&ctx.output_name),
+                &rule.workflow,
+            ),
+            None => (
+                &ctx.ffmpeg_template,
+                &ctx.ffmpeg_bin,
+                ctx.output_dir.clone(),
+                &ctx.output_name,
+                &ctx.workflow,
+            ),
+        };
+
+        // Nothing to route this file to
+        if template.trim().is_empty() {
+            handled.push(path);
             continue;
         }
 
-        let input_str = path.to_string_lossy();
-        let output_str = output_path.to_string_lossy();
+        let request = JobTemplate {
+            template: template.clone(),
+            ffmpeg: Some(ffmpeg_bin.clone()),
+            env: ctx.envs.clone(),
+            variables: ctx.variables.clone(),
+            inputs: vec![Pattern::escape(&path.to_string_lossy())],
+            output: Some(output_dir.join(output_name).to_string_lossy().into_owned()),
+            base_dir: Some(ctx.watch_dir.clone()),
+            workflow: workflow.clone(),
+            counter: Some(ctx.files_queued + results.len() as u64 + 1),
+            collision: Some(ctx.collision.clone()),
+        };
+        let job = match expand_job(&request, &ctx.reserved_outputs) {
+            Ok(expansion) => {
+                for skipped in &expansion.skipped {
+                    log::info!("Skipping {}: {}", path.display(), skipped.reason);
+                }
+                expansion.jobs.into_iter().next()
+            }
+            Err(e) => {
+                log::warn!("Skipping {}: {e}", path.display());
+                None
+            }
+        };
+        let Some(job) = job else {
+            handled.push(path);
+            continue;
+        };
+
+        for output in &job.outputs {
+            if let Some(parent) = Path::new(output).parent() {
+                let _ = std::fs::create_dir_all(parent);
+            }
+        }
+        let outputs: Vec<PathBuf> = job.outputs.iter().map(PathBuf::from).collect();
+        ctx.reserved_outputs.extend(outputs.iter().cloned());
+        results.push(MatchedFile {
+            input: path.to_string_lossy().to_string(),
+            output: job.outputs.first().cloned().unwrap_or_default(),
+            outputs,
+            cmd: job.cmd,
+            workflow: workflow.clone(),
+        });
+        handled.push(path);
+    }
 
-        let cmd = resolve_placeholders(&entry.ffmpeg_template, &input_str, &output_str);
+    (results, handled)
+}
 
-        let full_cmd = format!("{} {}", entry.ffmpeg_bin, cmd);
+/// Applies a post-processing action to `source` and returns where the file ended up.
+fn apply_post_action(
+    action: &PostAction,
+    source: &Path,
+    watch_dir: &Path,
+) -> std::io::Result<Option<PathBuf>> {
+    match action {
+        PostAction::Keep => Ok(Some(source.to_path_buf())),
+        PostAction::Delete => {
+            std::fs::remove_file(source)?;
+            Ok(None)
+        }
+        PostAction::Move(dir) => {
+            let dir = watch_dir.join(dir);
+            std::fs::create_dir_all(&dir)?;
+
+            let file_name = source.file_name().unwrap_or_default();
+            let mut target = dir.join(file_name);
+            let mut n = 1;
+            while target.exists() {
+                let stem = source
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let name = match source.extension() {
+                    Some(ext) => format!("{stem}_{n}.{}", ext.to_string_lossy()),
+                    None => format!("{stem}_{n}"),
+                };
+                target = dir.join(name);
+                n += 1;
+            }
 
-        entry.seen_files.insert(path.clone());
-        results.push((input_str.to_string(), output_str.to_string(), full_cmd));
+            // rename fails across filesystems, fall back to copy + remove
+            if std::fs::rename(source, &target).is_err() {
+                std::fs::copy(source, &target)?;
+                std::fs::remove_file(source)?;
+            }
+            Ok(Some(target))
+        }
     }
+}
+
+fn write_report(
+    output: &Path,
+    source: &Path,
+    cmd: &str,
+    workflow: &str,
+    success: bool,
+    duration: Option<Duration>,
+) -> std::io::Result<()> {
+    let mut report_name = output.file_name().unwrap_or_default().to_os_string();
+    report_name.push(".json");
+    let finished_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
 
-    results
+    let report = serde_json::json!({
+        "source": source.to_string_lossy(),
+        "output": output.to_string_lossy(),
+        "command": cmd,
+        "workflow": workflow,
+        "status": if success { "completed" } else { "failed" },
+        "duration_secs": duration.map(|d| d.as_secs_f64()),
+        "finished_at": finished_at,
+    });
+    let data = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+    std::fs::write(output.with_file_name(report_name), data)
+}
+
+/// Listens for the end of a watch folder job and runs the folder's post-processing.
+fn watch_job_completion(
+    window: &tauri::Window,
+    entries: Arc<Mutex<Vec<WatchFolderEntry>>>,
+    watch_id: u64,
+    job_id: &str,
+    file: &MatchedFile,
+) {
+    let (post_process, watch_dir) = {
+        let e = entries.lock().unwrap();
+        match e.iter().find(|e| e.id == watch_id) {
+            Some(entry) => (entry.post_process.clone(), entry.watch_dir.clone()),
+            None => return,
+        }
+    };
+    let source = PathBuf::from(&file.input);
+    let output = PathBuf::from(&file.output);
+    let outputs = file.outputs.clone();
+    let cmd = file.cmd.clone();
+    let workflow = file.workflow.clone();
+
+    let win = window.clone();
+    let started: Mutex<Option<Instant>> = Mutex::new(None);
+
+    window.listen(format!("transcode_{job_id}"), move |event| {
+        let success = match event.payload() {
+            "\"Pipeline started\"" => {
+                *started.lock().unwrap() = Some(Instant::now());
+                return;
+            }
+            "\"EOT\"" => true,
+            // A cancelled job gets the failure handling, its output is incomplete
+            "\"EOT_FAILED\"" | "\"EOT_CANCELLED\"" => false,
+            _ => return,
+        };
+        win.unlisten(event.id());
+
+        // Written or abandoned, the file on disk now speaks for itself
+        if let Some(entry) = entries
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|e| e.id == watch_id)
+        {
+            for path in &outputs {
+                entry.reserved_outputs.remove(path);
+            }
+        }
+
+        let duration = started.lock().unwrap().map(|s| s.elapsed());
+        if post_process.write_report {
+            if let Err(e) = write_report(&output, &source, &cmd, &workflow, success, duration) {
+                log::warn!("Failed to write report for {}: {e}", output.display());
+            }
+        }
+
+        let action = if success {
+            &post_process.on_success
+        } else {
+            &post_process.on_failure
+        };
+        if *action == PostAction::Keep {
+            return;
+        }
+
+        match apply_post_action(action, &source, &watch_dir) {
+            Ok(new_path) => {
+                // Forget the old path so a new file with the same name gets picked up,
+                // but never re-queue a file we moved somewhere the pattern still matches.
+                let mut e = entries.lock().unwrap();
+                if let Some(entry) = e.iter_mut().find(|e| e.id == watch_id) {
+                    entry.seen_files.remove(&source);
+                    if let Some(path) = new_path {
+                        entry.seen_files.insert(path);
+                    }
+                }
+            }
+            Err(e) => {
+                log::warn!("Post-processing failed for {}: {e}", source.display());
+            }
+        }
+    });
 }
 
 fn start_watchdog_thread(
@@ -211,31 +655,56 @@ fn start_watchdog_thread(
         };
 
         for id in watch_ids {
-            let (matched, workflow) = {
+            // Only glob under the lock, rules probe files and expansion may too
+            let polled = {
+                let mut e = entries.lock().unwrap();
+                match e.iter_mut().find(|e| e.id == id) {
+                    Some(entry) if entry.in_window() => {
+                        Some((PollContext::of(entry), unseen_files(entry)))
+                    }
+                    _ => None,
+                }
+            };
+            let Some((mut ctx, files)) = polled else {
+                continue;
+            };
+            if files.is_empty() {
+                continue;
+            }
+            let limit = ctx.max_in_flight.map_or(usize::MAX, |max| {
+                max.saturating_sub(count_in_flight(&queue, id))
+            });
+            let (matched, handled) = poll_folder(&mut ctx, files, limit);
+
+            // Drop the results if the folder was stopped or paused meanwhile
+            let matched = {
                 let mut e = entries.lock().unwrap();
-                let entry = e.iter_mut().find(|e| e.id == id);
-                match entry {
-                    Some(entry) => {
-                        let matched = poll_folder(entry);
+                match e.iter_mut().find(|e| e.id == id) {
+                    Some(entry) if entry.status == WatchStatus::Watching => {
+                        entry.seen_files.extend(handled);
+                        for file in &matched {
+                            entry.reserved_outputs.extend(file.outputs.iter().cloned());
+                        }
                         entry.files_queued += matched.len() as u64;
-                        (Some(matched), entry.workflow.clone())
+                        Some(matched)
                     }
-                    None => (None, String::new()),
+                    _ => None,
                 }
             };
 
             if let Some(matched) = matched {
-                for (input_file, output_file, cmd) in matched {
+                for file in matched {
                     let wf_tag = format!("W-{}", id);
                     let desc = serde_json::json!({
                         "tags": ["single transcode", wf_tag],
-                        "cmd": cmd,
-                        "workflow": workflow,
-                        "source": input_file,
-                        "output": output_file,
+                        "cmd": file.cmd,
+                        "workflow": file.workflow,
+                        "source": file.input,
+                        "output": file.output,
                     })
                     .to_string();
-                    queue.add_job(vec![cmd], vec![String::new()], desc);
+                    let job_id = queue.add_job(vec![file.cmd.clone()], vec![String::new()], desc);
+                    watch_job_completion(&window, entries.clone(), id, &job_id, &file);
                     queue.process_queue(window.clone());
                     let _ = window.emit("queue_status_changed", queue.get_queue_status());
                 }
@@ -263,6 +732,13 @@ pub fn start_watchfolder(
     ffmpeg_bin: String,
     envs: String,
     workflow: String,
+    post_process: Option<PostProcess>,
+    resume_policy: Option<ResumePolicy>,
+    rules: Option<Vec<RouteRule>>,
+    variables: Option<HashMap<String, String>>,
+    collision: Option<Collision>,
+    max_in_flight: Option<usize>,
+    schedule: Option<ScheduleWindow>,
     window: tauri::Window,
     watch_queue: tauri::State<WatchFolderQueue>,
     queue: tauri::State<TranscodeQueue>,
@@ -270,6 +746,11 @@ pub fn start_watchfolder(
     let wd = PathBuf::from(&watch_dir);
     let od = PathBuf::from(&output_dir);
 
+    let mut rules = rules.unwrap_or_default();
+    if !rules.is_empty() {
+        watch_rules::resolve_workflows(&mut rules, &get_workflow_list())?;
+    }
+
     let id = watch_queue.add_entry(
         wd,
         pattern,
@@ -279,6 +760,13 @@ pub fn start_watchfolder(
         ffmpeg_bin,
         envs,
         workflow,
+        post_process.unwrap_or_default(),
+        resume_policy.unwrap_or_default(),
+        rules,
+        variables.unwrap_or_default(),
+        collision.unwrap_or_default(),
+        max_in_flight,
+        schedule,
     )?;
 
     // Start watchdog on first entry
@@ -310,7 +798,222 @@ pub fn stop_watchfolder(
     result
 }
 
+#[tauri::command]
+pub fn pause_watchfolder(
+    id: u64,
+    window: tauri::Window,
+    watch_queue: tauri::State<WatchFolderQueue>,
+) -> bool {
+    let result = watch_queue.pause_entry(id);
+    if result {
+        let _ = window.emit("watch_status_changed", watch_queue.get_info_list());
+    }
+    result
+}
+
+#[tauri::command]
+pub fn resume_watchfolder(
+    id: u64,
+    window: tauri::Window,
+    watch_queue: tauri::State<WatchFolderQueue>,
+) -> bool {
+    let result = watch_queue.resume_entry(id);
+    if result {
+        let _ = window.emit("watch_status_changed", watch_queue.get_info_list());
+    }
+    result
+}
+
 #[tauri::command]
 pub fn get_watchfolders(watch_queue: tauri::State<WatchFolderQueue>) -> Vec<WatchFolderInfo> {
     watch_queue.get_info_list()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::fs;
+
+    fn window(start: &str, end: &str, days: &[&str]) -> ScheduleWindow {
+        ScheduleWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    /// `time` on the given day of the week starting Monday 2026-10-12.
+    fn at(day: Weekday, time: &str) -> NaiveDateTime {
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+        assert_eq!(monday.weekday(), Weekday::Mon);
+        let date = monday + chrono::Days::new(day.num_days_from_monday().into());
+        date.and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    #[test]
+    fn window_within_a_day() {
+        let office = window("09:00", "17:30", &[]);
+        for (day, time, open) in [
+            (Weekday::Mon, "08:59", false),
+            (Weekday::Mon, "09:00", true),
+            (Weekday::Wed, "12:00", true),
+            (Weekday::Sun, "17:29", true),
+            (Weekday::Sun, "17:30", false),
+            (Weekday::Fri, "23:00", false),
+        ] {
+            assert_eq!(office.is_open(at(day, time)), open, "{day} {time}");
+        }
+    }
+
+    #[test]
+    fn window_across_midnight() {
+        let night = window("23:00", "06:00", &[]);
+        for (day, time, open) in [
+            (Weekday::Mon, "22:59", false),
+            (Weekday::Mon, "23:00", true),
+            (Weekday::Tue, "00:00", true),
+            (Weekday::Tue, "05:59", true),
+            (Weekday::Tue, "06:00", false),
+            (Weekday::Tue, "12:00", false),
+        ] {
+            assert_eq!(night.is_open(at(day, time)), open, "{day} {time}");
+        }
+    }
+
+    #[test]
+    fn days_are_the_day_the_window_starts() {
+        // Friday and Saturday nights, so until Sunday 06:00
+        let weekend_nights = window("23:00", "06:00", &["Fri", "Sat"]);
+        for (day, time, open) in [
+            (Weekday::Thu, "23:30", false),
+            (Weekday::Fri, "03:00", false),
+            (Weekday::Fri, "23:30", true),
+            (Weekday::Sat, "03:00", true),
+            (Weekday::Sat, "12:00", false),
+            (Weekday::Sat, "23:30", true),
+            (Weekday::Sun, "03:00", true),
+            (Weekday::Sun, "23:30", false),
+            (Weekday::Mon, "03:00", false),
+        ] {
+            assert_eq!(weekend_nights.is_open(at(day, time)), open, "{day} {time}");
+        }
+
+        let mondays = window("08:00", "10:00", &[" mon ", "Monday"]);
+        assert!(mondays.is_open(at(Weekday::Mon, "09:00")));
+        assert!(!mondays.is_open(at(Weekday::Tue, "09:00")));
+    }
+
+    #[test]
+    fn invalid_windows_never_open() {
+        for bad in [
+            window("9am", "17:00", &[]),
+            window("09:00", "24:30", &[]),
+            window("09:00", "17:00", &["Someday"]),
+        ] {
+            assert!(bad.parse().is_err(), "{bad:?}");
+            assert!(!bad.is_open(at(Weekday::Mon, "12:00")));
+        }
+    }
+
+    #[test]
+    fn keep_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("clip.mp4");
+        fs::write(&source, "in").unwrap();
+
+        let kept = apply_post_action(&PostAction::Keep, &source, dir.path()).unwrap();
+        assert_eq!(kept, Some(source.clone()));
+        assert!(source.exists());
+
+        assert_eq!(
+            apply_post_action(&PostAction::Delete, &source, dir.path()).unwrap(),
+            None
+        );
+        assert!(!source.exists());
+        assert!(apply_post_action(&PostAction::Delete, &source, dir.path()).is_err());
+    }
+
+    #[test]
+    fn move_keeps_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let watch = dir.path();
+        let done = watch.join("done");
+        fs::create_dir(&done).unwrap();
+        fs::write(done.join("clip.mp4"), "earlier").unwrap();
+        fs::write(done.join("clip_1.mp4"), "earlier too").unwrap();
+
+        let source = watch.join("clip.mp4");
+        fs::write(&source, "new").unwrap();
+        let moved = apply_post_action(&PostAction::Move("done".to_string()), &source, watch)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moved, done.join("clip_2.mp4"));
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(done.join("clip.mp4")).unwrap(),
+            "earlier"
+        );
+        assert!(!source.exists());
+
+        // Absolute directories are created as needed, files without extension too
+        let elsewhere = tempfile::tempdir().unwrap();
+        let target = elsewhere.path().join("failed");
+        let source = watch.join("README");
+        fs::write(&source, "").unwrap();
+        let action = PostAction::Move(target.to_string_lossy().into_owned());
+        let moved = apply_post_action(&action, &source, watch).unwrap().unwrap();
+        assert_eq!(moved, target.join("README"));
+
+        fs::write(&source, "").unwrap();
+        let moved = apply_post_action(&action, &source, watch).unwrap().unwrap();
+        assert_eq!(moved, target.join("README_1"));
+    }
+
+    fn read_report(path: &Path) -> serde_json::Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reports_next_to_the_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.mkv");
+        let source = Path::new("/watch/in.mp4");
+
+        write_report(
+            &output,
+            source,
+            "ffmpeg -i /watch/in.mp4 out.mkv",
+            "web",
+            true,
+            Some(Duration::from_millis(2500)),
+        )
+        .unwrap();
+        let report = read_report(&dir.path().join("out.mkv.json"));
+        assert_eq!(report["status"], "completed");
+        assert_eq!(report["source"], "/watch/in.mp4");
+        assert_eq!(report["output"], output.to_string_lossy().as_ref());
+        assert_eq!(report["command"], "ffmpeg -i /watch/in.mp4 out.mkv");
+        assert_eq!(report["workflow"], "web");
+        assert_eq!(report["duration_secs"], 2.5);
+        assert!(report["finished_at"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn reports_failed_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        // The job failed before writing anything, the report is written all the same
+        let output = dir.path().join("never written.mp4");
+        write_report(&output, Path::new("in.mp4"), "ffmpeg", "", false, None).unwrap();
+
+        let report = read_report(&dir.path().join("never written.mp4.json"));
+        assert_eq!(report["status"], "failed");
+        assert!(report["duration_secs"].is_null());
+        assert!(!output.exists());
+
+        // Written again, over the last one
+        write_report(&output, Path::new("in.mp4"), "ffmpeg", "", true, None).unwrap();
+        let report = read_report(&dir.path().join("never written.mp4.json"));
+        assert_eq!(report["status"], "completed");
+    }
+}
@@ -0,0 +1,407 @@
+use crate::ffmpeg::probe::{probe_media, MediaProbe};
+use crate::workflow::types::WorkflowStructure;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Conditions a file has to meet for a rule to apply. Every condition that is set must hold.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleMatch {
+    /// Glob matched against the path relative to the watch directory
+    pub glob: Option<String>,
+    /// Extensions without the dot, compared case-insensitively
+    pub extensions: Vec<String>,
+    pub audio_only: Option<bool>,
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+}
+
+/// Routes matching files to a workflow's template and naming.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteRule {
+    pub name: String,
+    pub matches: RuleMatch,
+    /// Saved workflow the rule runs, see [`resolve_workflows`]
+    pub workflow: String,
+    /// Empty to run the template saved with `workflow`
+    pub ffmpeg_template: String,
+    /// Binary to run the template with; falls back to `workflow`'s, then to the watch
+    /// folder's binary
+    pub ffmpeg_bin: Option<String>,
+    pub output_dir: Option<String>,
+    pub output_name: Option<String>,
+}
+
+impl RuleMatch {
+    pub fn needs_probe(&self) -> bool {
+        self.audio_only.is_some()
+            || self.min_width.is_some()
+            || self.max_width.is_some()
+            || self.min_height.is_some()
+            || self.max_height.is_some()
+            || self.video_codec.is_some()
+            || self.audio_codec.is_some()
+    }
+
+    fn matches_path(&self, path: &Path, watch_dir: &Path) -> bool {
+        if let Some(glob) = &self.glob {
+            let rel = path.strip_prefix(watch_dir).unwrap_or(path);
+            match Pattern::new(glob) {
+                Ok(p) if p.matches_path(rel) => {}
+                _ => return false,
+            }
+        }
+
+        if !self.extensions.is_empty() {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            if !self
+                .extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn matches_probe(&self, probe: &MediaProbe) -> bool {
+        let within = |value: Option<u32>, min: Option<u32>, max: Option<u32>| {
+            if min.is_none() && max.is_none() {
+                return true;
+            }
+            match value {
+                Some(v) => min.map_or(true, |m| v >= m) && max.map_or(true, |m| v <= m),
+                None => false,
+            }
+        };
+        let same_codec = |want: &Option<String>, have: &Option<String>| match want {
+            Some(w) => have.as_deref().is_some_and(|h| h.eq_ignore_ascii_case(w)),
+            None => true,
+        };
+
+        self.audio_only.map_or(true, |a| a == probe.is_audio_only())
+            && within(probe.width, self.min_width, self.max_width)
+            && within(probe.height, self.min_height, self.max_height)
+            && same_codec(&self.video_codec, &probe.video_codec)
+            && same_codec(&self.audio_codec, &probe.audio_codec)
+    }
+}
+
+/// Returns the first rule matching `path`. The file is probed at most once, and only when
+/// a path-matching rule asks for stream properties.
+pub fn find_rule<'a>(
+    rules: &'a [RouteRule],
+    path: &Path,
+    watch_dir: &Path,
+    ffmpeg_bin: &str,
+    envs: &str,
+) -> Option<&'a RouteRule> {
+    let mut probe: Option<Option<MediaProbe>> = None;
+
+    rules.iter().find(|rule| {
+        if !rule.matches.matches_path(path, watch_dir) {
+            return false;
+        }
+        if !rule.matches.needs_probe() {
+            return true;
+        }
+
+        let probe = probe.get_or_insert_with(|| {
+            probe_media(&path.to_string_lossy(), ffmpeg_bin, envs)
+                .map_err(|e| log::warn!("Failed to probe {}: {e}", path.display()))
+                .ok()
+        });
+        probe
+            .as_ref()
+            .is_some_and(|p| rule.matches.matches_probe(p))
+    })
+}
+
+/// Fills in what rules take from their saved workflow: the binary, unless the rule sets
+/// one, and the template of rules without an inline one.
+pub fn resolve_workflows(
+    rules: &mut [RouteRule],
+    workflows: &[WorkflowStructure],
+) -> Result<(), String> {
+    for rule in rules.iter_mut() {
+        let workflow = workflows.iter().find(|wf| wf.name == rule.workflow);
+        if rule.ffmpeg_bin.is_none() {
+            rule.ffmpeg_bin = workflow.map(|wf| wf.path.clone());
+        }
+        if !rule.ffmpeg_template.trim().is_empty() {
+            continue;
+        }
+        match workflow {
+            Some(wf) if !wf.template.trim().is_empty() => {
+                rule.ffmpeg_template = wf.template.clone();
+            }
+            Some(_) => {
+                return Err(format!(
+                    "Rule '{}': workflow '{}' has no saved template, save its graph with an output first",
+                    rule.name, rule.workflow
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "Rule '{}' has no template and workflow '{}' doesn't exist",
+                    rule.name, rule.workflow
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn rule(name: &str, matches: RuleMatch) -> RouteRule {
+        RouteRule {
+            name: name.to_string(),
+            matches,
+            ffmpeg_template: "-i {input} {output}".to_string(),
+            ..RouteRule::default()
+        }
+    }
+
+    fn extensions(exts: &[&str]) -> RuleMatch {
+        RuleMatch {
+            extensions: exts.iter().map(|e| e.to_string()).collect(),
+            ..RuleMatch::default()
+        }
+    }
+
+    /// An `ffmpeg` that prints a fixture's `-i` output and counts its runs in `runs`.
+    #[cfg(unix)]
+    fn fake_ffmpeg(dir: &Path, fixture: &str) -> (String, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let runs = dir.join("runs");
+        let fixture: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests/fixtures/ffmpeg", fixture]
+            .iter()
+            .collect();
+        let ffmpeg = dir.join("ffmpeg");
+        fs::write(
+            &ffmpeg,
+            format!(
+                "#!/bin/sh\necho run >> '{}'\ncat '{}' >&2\nexit 1\n",
+                runs.display(),
+                fixture.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&ffmpeg, fs::Permissions::from_mode(0o755)).unwrap();
+        (ffmpeg.to_string_lossy().into_owned(), runs)
+    }
+
+    fn run_count(runs: &Path) -> usize {
+        fs::read_to_string(runs).map_or(0, |text| text.lines().count())
+    }
+
+    #[test]
+    fn path_conditions() {
+        let watch = Path::new("/watch");
+        let glob = RuleMatch {
+            glob: Some("camera/**/*.mov".to_string()),
+            ..RuleMatch::default()
+        };
+        assert!(glob.matches_path(Path::new("/watch/camera/day1/a.mov"), watch));
+        assert!(!glob.matches_path(Path::new("/watch/other/a.mov"), watch));
+
+        let exts = extensions(&[".MKV", "mp4"]);
+        assert!(exts.matches_path(Path::new("/watch/a.mkv"), watch));
+        assert!(exts.matches_path(Path::new("/watch/a.MP4"), watch));
+        assert!(!exts.matches_path(Path::new("/watch/a.mov"), watch));
+        assert!(!exts.matches_path(Path::new("/watch/mkv"), watch));
+        assert!(RuleMatch::default().matches_path(Path::new("/watch/a"), watch));
+    }
+
+    #[test]
+    fn probe_conditions() {
+        let probe = MediaProbe {
+            has_video: true,
+            has_audio: true,
+            video_codec: Some("hevc".to_string()),
+            audio_codec: Some("aac".to_string()),
+            width: Some(3840),
+            height: Some(2160),
+            ..MediaProbe::default()
+        };
+        let check = |matches: RuleMatch| matches.matches_probe(&probe);
+
+        assert!(check(RuleMatch {
+            min_width: Some(3840),
+            max_height: Some(2160),
+            video_codec: Some("HEVC".to_string()),
+            audio_only: Some(false),
+            ..RuleMatch::default()
+        }));
+        assert!(!check(RuleMatch {
+            max_width: Some(1920),
+            ..RuleMatch::default()
+        }));
+        assert!(!check(RuleMatch {
+            audio_codec: Some("opus".to_string()),
+            ..RuleMatch::default()
+        }));
+        assert!(!check(RuleMatch {
+            audio_only: Some(true),
+            ..RuleMatch::default()
+        }));
+
+        // A size condition fails when the size is unknown
+        let audio = MediaProbe {
+            has_audio: true,
+            ..MediaProbe::default()
+        };
+        let min_height = RuleMatch {
+            min_height: Some(1),
+            ..RuleMatch::default()
+        };
+        assert!(!min_height.matches_probe(&audio));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn first_matching_rule_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ffmpeg, runs) = fake_ffmpeg(dir.path(), "mediainfo_mp4.txt");
+        let rules = [
+            rule("mov", extensions(&["mov"])),
+            rule("mp4", extensions(&["mp4"])),
+            rule("any", RuleMatch::default()),
+        ];
+        let find = |path: &str| {
+            find_rule(&rules, Path::new(path), dir.path(), &ffmpeg, "").map(|r| r.name.as_str())
+        };
+        assert_eq!(find("/watch/a.mp4"), Some("mp4"));
+        assert_eq!(find("/watch/a.mkv"), Some("any"));
+        let mp4_or_mov = &rules[..2];
+        assert!(find_rule(
+            mp4_or_mov,
+            Path::new("/watch/a.mkv"),
+            dir.path(),
+            &ffmpeg,
+            ""
+        )
+        .is_none());
+        // Nothing asked for stream properties
+        assert_eq!(run_count(&runs), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_are_probed_once_and_only_when_needed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ffmpeg, runs) = fake_ffmpeg(dir.path(), "mediainfo_mp4.txt");
+        let uhd = RuleMatch {
+            min_width: Some(3840),
+            ..RuleMatch::default()
+        };
+        let h264 = RuleMatch {
+            video_codec: Some("h264".to_string()),
+            ..RuleMatch::default()
+        };
+        let mkv_audio = RuleMatch {
+            audio_only: Some(true),
+            ..extensions(&["mkv"])
+        };
+        let rules = [
+            rule("mkv audio", mkv_audio),
+            rule("uhd", uhd),
+            rule("h264", h264),
+        ];
+
+        let found = find_rule(&rules, Path::new("/watch/a.mp4"), dir.path(), &ffmpeg, "");
+        assert_eq!(found.map(|r| r.name.as_str()), Some("h264"));
+        // The mkv rule didn't match the path so it didn't probe, the other two shared one
+        assert_eq!(run_count(&runs), 1);
+
+        // A path rule ahead of the probing ones doesn't probe at all
+        let rules = [rule("mp4", extensions(&["mp4"])), rules[2].clone()];
+        find_rule(&rules, Path::new("/watch/a.mp4"), dir.path(), &ffmpeg, "");
+        assert_eq!(run_count(&runs), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_probe_skips_probing_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ffmpeg, runs) = fake_ffmpeg(dir.path(), "does_not_exist.txt");
+        let rules = [
+            rule(
+                "video",
+                RuleMatch {
+                    audio_only: Some(false),
+                    ..RuleMatch::default()
+                },
+            ),
+            rule(
+                "audio",
+                RuleMatch {
+                    audio_only: Some(true),
+                    ..RuleMatch::default()
+                },
+            ),
+            rule("fallback", RuleMatch::default()),
+        ];
+        let found = find_rule(&rules, Path::new("/watch/a.mp4"), dir.path(), &ffmpeg, "");
+        assert_eq!(found.map(|r| r.name.as_str()), Some("fallback"));
+        assert_eq!(run_count(&runs), 1);
+    }
+
+    #[test]
+    fn rules_take_binary_and_template_from_their_workflow() {
+        let workflows = [
+            WorkflowStructure {
+                name: "web".to_string(),
+                path: "/opt/ffmpeg7/ffmpeg".to_string(),
+                template: "-i {input} -c:v libx264 {output}".to_string(),
+                ..WorkflowStructure::default()
+            },
+            WorkflowStructure {
+                name: "unsaved".to_string(),
+                path: "ffmpeg".to_string(),
+                ..WorkflowStructure::default()
+            },
+        ];
+        let by_workflow = |workflow: &str, template: &str, bin: Option<&str>| RouteRule {
+            name: workflow.to_string(),
+            workflow: workflow.to_string(),
+            ffmpeg_template: template.to_string(),
+            ffmpeg_bin: bin.map(str::to_string),
+            ..RouteRule::default()
+        };
+
+        let mut rules = [
+            by_workflow("web", "", None),
+            by_workflow("web", "-i {input} {output}", Some("/usr/bin/ffmpeg")),
+            by_workflow("unsaved", "-i {input} -an {output}", None),
+        ];
+        resolve_workflows(&mut rules, &workflows).unwrap();
+        assert_eq!(rules[0].ffmpeg_template, "-i {input} -c:v libx264 {output}");
+        assert_eq!(rules[0].ffmpeg_bin.as_deref(), Some("/opt/ffmpeg7/ffmpeg"));
+        assert_eq!(rules[1].ffmpeg_template, "-i {input} {output}");
+        assert_eq!(rules[1].ffmpeg_bin.as_deref(), Some("/usr/bin/ffmpeg"));
+        assert_eq!(rules[2].ffmpeg_bin.as_deref(), Some("ffmpeg"));
+
+        let err = resolve_workflows(&mut [by_workflow("unsaved", " ", None)], &workflows);
+        assert!(err.unwrap_err().contains("no saved template"));
+        let err = resolve_workflows(&mut [by_workflow("gone", "", None)], &workflows);
+        assert!(err.unwrap_err().contains("doesn't exist"));
+    }
+}
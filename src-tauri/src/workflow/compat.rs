@@ -0,0 +1,174 @@
+use super::types::Node;
+use crate::{FFStudioError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+/// Identifies a node across catalogs, e.g. `filters`/`scale`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeKey {
+    pub pcategory: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeDiff {
+    pub pcategory: String,
+    pub name: String,
+    pub added_options: Vec<String>,
+    pub removed_options: Vec<String>,
+    /// Options present in both whose type or values differ
+    pub changed_options: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CatalogDiff {
+    pub added: Vec<NodeKey>,
+    pub removed: Vec<NodeKey>,
+    pub changed: Vec<NodeDiff>,
+}
+
+/// A node, or one of its options, used by a graph but missing from a catalog.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MissingCapability {
+    pub node_id: i64,
+    pub node_type: String,
+    /// `None` when the whole node is missing
+    pub option: Option<String>,
+}
+
+fn index(nodes: &[Node]) -> BTreeMap<NodeKey, &Node> {
+    let mut map = BTreeMap::new();
+    for node in nodes {
+        let key = NodeKey {
+            pcategory: node.pcategory.clone(),
+            name: node.name.clone(),
+        };
+        // Keep the first one, extra AVOptions sections may repeat a name
+        map.entry(key).or_insert(node);
+    }
+    map
+}
+
+/// Lists nodes and options added, removed or changed going from `from` to `to`.
+pub fn diff_catalogs(from: &[Node], to: &[Node]) -> CatalogDiff {
+    let from = index(from);
+    let to = index(to);
+    let mut diff = CatalogDiff::default();
+
+    for (key, old) in &from {
+        let Some(new) = to.get(key) else {
+            diff.removed.push(key.clone());
+            continue;
+        };
+
+        let old_opts: BTreeMap<&str, _> =
+            old.options.iter().map(|o| (o.flag.as_str(), o)).collect();
+        let new_opts: BTreeMap<&str, _> =
+            new.options.iter().map(|o| (o.flag.as_str(), o)).collect();
+
+        let mut node_diff = NodeDiff {
+            pcategory: key.pcategory.clone(),
+            name: key.name.clone(),
+            added_options: new_opts
+                .keys()
+                .filter(|f| !old_opts.contains_key(*f))
+                .map(|f| f.to_string())
+                .collect(),
+            removed_options: Vec::new(),
+            changed_options: Vec::new(),
+        };
+        for (flag, old_opt) in &old_opts {
+            match new_opts.get(flag) {
+                None => node_diff.removed_options.push(flag.to_string()),
+                Some(new_opt)
+                    if old_opt.r#type != new_opt.r#type
+                        || old_opt.enum_vals != new_opt.enum_vals =>
+                {
+                    node_diff.changed_options.push(flag.to_string())
+                }
+                Some(_) => {}
+            }
+        }
+
+        if !node_diff.added_options.is_empty()
+            || !node_diff.removed_options.is_empty()
+            || !node_diff.changed_options.is_empty()
+        {
+            diff.changed.push(node_diff);
+        }
+    }
+
+    diff.added = to
+        .keys()
+        .filter(|k| !from.contains_key(*k))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Checks a saved LiteGraph graph against `nodes` and lists every ffmpeg node or
+/// option with a value that the catalog lacks.
+///
+/// Node types follow `make_nodes`: `ffmpeg/<pcategory>/.../<name>` for codecs, filters
+/// and formats, `ffmpeg/general/<group>/<flag>` for global options. Built-in nodes such
+/// as `ffmpeg/input` are skipped.
+pub fn check_graph(graph: &str, nodes: &[Node]) -> Result<Vec<MissingCapability>> {
+    if graph.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let graph: Value = serde_json::from_str(graph)
+        .map_err(|e| FFStudioError::json(format!("Failed to parse graph: {e}")))?;
+    let catalog = index(nodes);
+
+    let mut missing = Vec::new();
+    for node in graph["nodes"].as_array().into_iter().flatten() {
+        let node_id = node["id"].as_i64().unwrap_or_default();
+        let node_type = node["type"].as_str().unwrap_or_default();
+        let parts: Vec<&str> = node_type.split('/').collect();
+        if parts.len() < 3 || parts[0] != "ffmpeg" {
+            continue;
+        }
+        let mut report = |option: Option<String>| {
+            missing.push(MissingCapability {
+                node_id,
+                node_type: node_type.to_string(),
+                option,
+            })
+        };
+
+        if parts[1] == "general" {
+            let (group, flag) = (parts[2], parts[parts.len() - 1]);
+            let found = nodes
+                .iter()
+                .filter(|n| !n.is_av_option && n.name == group)
+                .any(|n| n.options.iter().any(|o| o.flag == flag));
+            if !found {
+                report(Some(flag.to_string()));
+            }
+            continue;
+        }
+
+        let key = NodeKey {
+            pcategory: parts[1].to_string(),
+            name: parts[parts.len() - 1].to_string(),
+        };
+        let Some(entry) = catalog.get(&key) else {
+            report(None);
+            continue;
+        };
+
+        let flags: HashSet<&str> = entry.options.iter().map(|o| o.flag.as_str()).collect();
+        for (prop, value) in node["properties"].as_object().into_iter().flatten() {
+            let is_set = match value {
+                Value::Null => false,
+                Value::String(s) => !s.is_empty(),
+                _ => true,
+            };
+            if is_set && !flags.contains(prop.as_str()) {
+                report(Some(prop.clone()));
+            }
+        }
+    }
+    Ok(missing)
+}
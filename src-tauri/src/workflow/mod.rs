@@ -1,2 +1,3 @@
+pub mod compat;
 pub mod manager;
 pub mod types;
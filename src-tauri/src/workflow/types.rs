@@ -1,5 +1,7 @@
+use crate::ffmpeg::version::{parse_build, FfmpegBuild};
 use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct OptionEntry {
     pub flag: String,
     pub r#type: Option<String>,
@@ -7,6 +9,53 @@ pub struct OptionEntry {
     pub desc: Option<String>,
     pub enum_vals: Vec<String>,
     pub no_args: bool,
+    /// Parsed from "(default VAL)"
+    pub default: Option<String>,
+    /// Parsed from "(from MIN to MAX)"
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// AVOption flag columns (E, D, F, V, A, S, T)
+    pub encoding: bool,
+    pub decoding: bool,
+    pub filtering: bool,
+    pub video: bool,
+    pub audio: bool,
+    pub subtitle: bool,
+    pub runtime_param: bool,
+}
+
+impl OptionEntry {
+    /// Checks `value` against the option's range and enum values. Values that cannot be
+    /// judged here, such as expressions or named constants, are accepted.
+    pub fn check_value(&self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if let Ok(number) = value.parse::<f64>() {
+            let below = self.min.is_some_and(|min| number < min);
+            let above = self.max.is_some_and(|max| number > max);
+            if below || above {
+                return Err(format!(
+                    "{}: {value} is out of range (from {} to {})",
+                    self.flag,
+                    self.min.map_or("-inf".to_string(), |m| m.to_string()),
+                    self.max.map_or("inf".to_string(), |m| m.to_string()),
+                ));
+            }
+            return Ok(());
+        }
+
+        if self.r#type.as_deref() == Some("<enum>")
+            && !self.enum_vals.is_empty()
+            && !self.enum_vals.iter().any(|v| v == value)
+        {
+            return Err(format!(
+                "{}: '{value}' is not one of {}",
+                self.flag,
+                self.enum_vals.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -18,6 +67,122 @@ pub struct Node {
     pub desc: String,
     pub full_desc: Vec<String>,
     pub options: Vec<OptionEntry>,
+    /// Pads and capabilities, only set for filters
+    #[serde(default)]
+    pub filter: Option<FilterInfo>,
+    /// Only set for input/output devices
+    #[serde(default)]
+    pub device: Option<DeviceInfo>,
+    /// Only set for encoders and decoders
+    #[serde(default)]
+    pub codec: Option<CodecInfo>,
+}
+
+/// The "Supported ..." and capability lines of `-h encoder=NAME` / `-h decoder=NAME`.
+/// An empty list means ffmpeg didn't restrict it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodecInfo {
+    pub capabilities: Vec<String>,
+    /// e.g. "frame and slice", "other" or "none"
+    pub threading: Option<String>,
+    pub pix_fmts: Vec<String>,
+    pub sample_fmts: Vec<String>,
+    pub sample_rates: Vec<u32>,
+    pub channel_layouts: Vec<String>,
+    pub framerates: Vec<String>,
+    pub hw_devices: Vec<String>,
+}
+
+impl CodecInfo {
+    pub fn supports_pix_fmt(&self, fmt: &str) -> bool {
+        self.pix_fmts.is_empty() || self.pix_fmts.iter().any(|f| f == fmt)
+    }
+
+    pub fn supports_sample_fmt(&self, fmt: &str) -> bool {
+        self.sample_fmts.is_empty() || self.sample_fmts.iter().any(|f| f == fmt)
+    }
+
+    pub fn supports_sample_rate(&self, rate: u32) -> bool {
+        self.sample_rates.is_empty() || self.sample_rates.contains(&rate)
+    }
+
+    pub fn supports_channel_layout(&self, layout: &str) -> bool {
+        self.channel_layouts.is_empty() || self.channel_layouts.iter().any(|l| l == layout)
+    }
+}
+
+/// An input or output device and what `-sources`/`-sinks` detected for it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceInfo {
+    pub input: bool,
+    pub output: bool,
+    pub sources: Vec<String>,
+    pub sinks: Vec<String>,
+}
+
+/// Outcome of parsing one catalog category, e.g. "encoders".
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CategoryReport {
+    pub category: String,
+    pub ok: bool,
+    pub count: usize,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CatalogReport {
+    pub categories: Vec<CategoryReport>,
+}
+
+impl CatalogReport {
+    pub fn failed(&self) -> impl Iterator<Item = &CategoryReport> {
+        self.categories.iter().filter(|c| !c.ok)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+/// Nodes parsed from an ffmpeg binary and how each category went.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Catalog {
+    pub nodes: Vec<Node>,
+    pub report: CatalogReport,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MediaType {
+    Video,
+    Audio,
+    #[default]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FilterPad {
+    pub name: String,
+    pub media: MediaType,
+}
+
+/// What `ffmpeg -filters` and `-h filter=NAME` report about a filter's pads.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterInfo {
+    pub inputs: Vec<FilterPad>,
+    pub outputs: Vec<FilterPad>,
+    /// Pad count depends on the options, e.g. amix or split
+    pub dynamic_inputs: bool,
+    pub dynamic_outputs: bool,
+    pub timeline: bool,
+    pub slice_threading: bool,
+    pub command: bool,
 }
 #[derive(Serialize, Deserialize)]
 pub struct MIResponse {
@@ -42,6 +207,8 @@ pub struct Response {
     pub build: String,
     pub version: String,
     pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub report: CatalogReport,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -52,4 +219,20 @@ pub struct WorkflowStructure {
     pub env: String,
     pub desc: String,
     pub version: Vec<String>,
+    #[serde(default)]
+    pub build: Option<FfmpegBuild>,
+    /// Watch folder command generated from `graph` when it was saved, run by rules that
+    /// refer to the workflow
+    #[serde(default)]
+    pub template: String,
+}
+
+impl WorkflowStructure {
+    /// The stored build info, or one parsed from `version` for workflows saved
+    /// before it was persisted.
+    pub fn ffmpeg_build(&self) -> FfmpegBuild {
+        self.build
+            .clone()
+            .unwrap_or_else(|| parse_build(&self.version))
+    }
 }
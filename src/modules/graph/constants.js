@@ -4,6 +4,8 @@ export const N_STREAMS = "streams";
 export const MAP_STREAM = "maps";
 export const DECODER = "dec";
 export const ENCODER = "enc";
+export const BSF = "bsf";
+export const PROTOCOL = "proto";
 
 // Node type constants
 export const N_FILTER = 0;
@@ -11,6 +13,8 @@ export const N_ENCODER = 1;
 export const N_DECODER = 2;
 export const N_FORMAT = 3;
 export const N_INPUT = 4;
+export const N_BSF = 5;
+export const N_PROTOCOL = 6;
 
 // Stream type constants
 export const ST_RAW = 0;
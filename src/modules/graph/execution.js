@@ -4,7 +4,6 @@
 const { invoke } = window.__TAURI__.core;
 const { listen } = window.__TAURI__.event;
 import { addLogEntry } from '../logs/logs.js';
-import { expandFfmpegCommand } from './utils.js';
 import * as core from './core.js';
 
 // Execution state
@@ -25,28 +24,6 @@ const removeChainElement = document.getElementById('remove-chain-element');
 const canvasContainer = document.getElementById('canvas-container');
 const chain_canvas = document.getElementById('chainCanvas');
 
-function replaceVariables(command) {
-    if (!window.graph_variables) {
-        return command;
-    }
-
-    let result = command;
-
-    function escapeRegExp(str) {
-        return String(str).replace(/[.*+?^${}()|[\]\\]/g, "\\$&");
-    }
-
-    // Replace variables in format {{variable_name}}
-    Object.keys(window.graph_variables).forEach(key => {
-        const value = window.graph_variables[key];
-        const safeKey = escapeRegExp(key);
-        const regex = new RegExp(`\\{\\{${safeKey}\\}\\}`, 'g');
-        result = result.replace(regex, value);
-    });
-
-    return result;
-}
-
 function _collect_ffmpeg_parts(selected_only = false) {
     window.global_ffmpeg = {
         selected_only,
@@ -75,10 +52,35 @@ function get_ffmpeg_command(selected_only = false) {
         return;
     }
 
-    return replaceVariables(result_cmd);
+    return result_cmd;
+}
+
+// Logs filtergraph and format problems of each command, returns false if any of them is an error
+async function validateCommands(cmds, envs) {
+    let ok = true;
+    for (let i = 0; i < cmds.length; i++) {
+        let issues;
+        try {
+            issues = await invoke('validate_command', { command: cmds[i], env: envs[i] });
+        } catch (err) {
+            addLogEntry("warning", `Skipped command validation: ${err}`);
+            continue;
+        }
+        issues.forEach(issue => {
+            const where = issue.span ? ` (at ${issue.span.start})` : '';
+            const isError = issue.severity === "Error";
+            addLogEntry(isError ? "error" : "warning", `${issue.option}${where}: ${issue.message}`);
+            if (isError) ok = false;
+        });
+    }
+    return ok;
 }
 
 async function startTranscding(cmds, envs) {
+    if (!await validateCommands(cmds, envs)) {
+        addLogEntry("error", "Caught error: Command validation failed, job was not queued");
+        return;
+    }
     invoke('queue_transcode', { cmds: cmds,  envs: envs,
         desc: JSON.stringify({
             "tag": cmds.length > 1 ? "chain transcode" : "single transcode", 
@@ -87,7 +89,9 @@ async function startTranscding(cmds, envs) {
         });
 }
 
-function get_ffmpeg_template() {
+// Command for watch folders, with {input} and {output} placeholders. `quiet` skips the
+// error log when the graph has no output
+function get_ffmpeg_template(quiet = false) {
     const parts = _collect_ffmpeg_parts(false);
 
     let result_cmd = "";
@@ -104,11 +108,11 @@ function get_ffmpeg_template() {
     });
 
     if (!parts.outputs.length) {
-        addLogEntry("error", "Caught error: Failed to create ffmpeg template! At least one Output node must be specified!");
+        if (!quiet) addLogEntry("error", "Caught error: Failed to create ffmpeg template! At least one Output node must be specified!");
         return null;
     }
 
-    return replaceVariables(result_cmd).trim();
+    return result_cmd.trim();
 }
 
 async function startWatch() {
@@ -130,6 +134,7 @@ async function startWatch() {
             ffmpegBin: window.FFMPEG_BIN,
             envs: window.FFMPEG_ENV,
             workflow: window.selectedWorkflow || '',
+            variables: window.graph_variables,
         });
         addLogEntry("success", `Watch folder started (ID: ${id}): ${watchDir} (${pattern})`);
     } catch (err) {
@@ -210,6 +215,7 @@ function initializeExecution() {
             command: get_ffmpeg_command(true),
             ffmpeg: window.FFMPEG_BIN,
             envs: window.FFMPEG_ENV,
+            variables: { ...window.graph_variables },
             x: commandChain.length * (BLOCK_WIDTH + BLOCK_SPACING) + 20,
             y: BLOCK_Y
         };
@@ -240,6 +246,7 @@ function initializeExecution() {
             selectedBlock.command = get_ffmpeg_command(true);
             selectedBlock.ffmpeg = window.FFMPEG_BIN,
             selectedBlock.envs = window.FFMPEG_ENV,
+            selectedBlock.variables = { ...window.graph_variables };
             redrawCanvas();
         }
     });
@@ -251,24 +258,44 @@ function initializeExecution() {
             return;
         }
 
-        let cmds = [];
-        let envs = [];
         if (proModeToggle.classList.contains('active')) {
             if (commandChain.length === 0) { addLogEntry("error", "Caught error: No commands in chain"); return; }
-            cmds = commandChain.map(b => `${b.ffmpeg} ${b.command}`);
-            envs = commandChain.map(b => b.envs);
-            addLogEntry("info", `Executing ffmpeg commands chain: ${cmds.join(" | ")}`);
-            startTranscding(cmds, envs);
+            const expansions = commandChain.map(b => invoke('expand_job', {
+                request: { template: b.command, ffmpeg: b.ffmpeg, env: b.envs, variables: b.variables || {} }
+            }));
+            Promise.all(expansions).then(results => {
+                const empty = results.find(r => r.jobs.length === 0);
+                if (empty) {
+                    const reason = empty.skipped.map(s => s.reason).join(", ") || "no command";
+                    addLogEntry("error", `Caught error: A chain element didn't expand to a command: ${reason}`);
+                    return;
+                }
+                const cmds = results.map(r => r.jobs[0].cmd);
+                const envs = commandChain.map(b => b.envs);
+                addLogEntry("info", `Executing ffmpeg commands chain: ${cmds.join(" | ")}`);
+                startTranscding(cmds, envs);
+            }).catch(err => addLogEntry("error", `Caught error: ${err}`));
         }
         else {
-            expandFfmpegCommand(`${window.FFMPEG_BIN} ${get_ffmpeg_command()}`).then(list => {
-                list.forEach(cmd => {
-                    cmds = [cmd];
-                    envs = [window.FFMPEG_ENV];
-                    startTranscding(cmds, envs);
-                    addLogEntry("info", `Expanded ffmpeg command: ${cmd}`);
+            const template = get_ffmpeg_command();
+            if (!template) return;
+            invoke('expand_job', {
+                request: {
+                    template,
+                    ffmpeg: window.FFMPEG_BIN,
+                    env: window.FFMPEG_ENV,
+                    variables: window.graph_variables || {},
+                    workflow: window.selectedWorkflow || '',
+                }
+            }).then(expansion => {
+                expansion.skipped.forEach(s => {
+                    addLogEntry("warning", `Skipped ${s.inputs.join(", ")}: ${s.reason}`);
                 });
-            });
+                expansion.jobs.forEach(job => {
+                    startTranscding([job.cmd], [window.FFMPEG_ENV]);
+                    addLogEntry("info", `Expanded ffmpeg command: ${job.cmd}`);
+                });
+            }).catch(err => addLogEntry("error", `Caught error: ${err}`));
         }
     });
 
@@ -521,4 +548,4 @@ function initializeExecution() {
 }
 
 
-export { startTranscding, get_ffmpeg_command, initializeExecution };
\ No newline at end of file
+export { startTranscding, get_ffmpeg_command, get_ffmpeg_template, initializeExecution };
\ No newline at end of file
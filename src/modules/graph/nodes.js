@@ -60,8 +60,8 @@ const ffmpegTypeMap = {
     },
 
     "<pix_fmt>": (node, opt) => {
-        if(opt.values) {
-            let values = [""].concat(opt.values);
+        if(opt.enum_vals && opt.enum_vals.length) {
+            let values = [""].concat(opt.enum_vals);
             node.addProperty(opt.flag, "", "enum", { values });
             node.addWidget("combo", opt.flag, "", { property: opt.flag, values });
         }
@@ -69,8 +69,8 @@ const ffmpegTypeMap = {
     },
 
     "<sample_fmt>": (node, opt) => {
-        if(opt.values) {
-            let values = [""].concat(opt.values);
+        if(opt.enum_vals && opt.enum_vals.length) {
+            let values = [""].concat(opt.enum_vals);
             node.addProperty(opt.flag, "", "enum", { values });
             node.addWidget("combo", opt.flag, "", { property: opt.flag, values });
         }
@@ -83,8 +83,8 @@ const ffmpegTypeMap = {
     },
 
     "<channel_layout>": (node, opt) => {
-        if(opt.values) {
-            let values = [""].concat(opt.values);
+        if(opt.enum_vals && opt.enum_vals.length) {
+            let values = [""].concat(opt.enum_vals);
             node.addProperty(opt.flag, "", "enum", { values });
             node.addWidget("combo", opt.flag, "", { property: opt.flag, values });
         }
@@ -246,6 +246,7 @@ function make_io_nodes() {
         this.addInput("dec:v",graph_consts.DECODER);
         this.addInput("dec:a",graph_consts.DECODER);
         this.addInput("demuxer", graph_consts.FORMAT);
+        this.addInput("protocol", graph_consts.PROTOCOL);
         this.addOutput("n-streams",graph_consts.N_STREAMS);
 
         this.addProperty("src_path", "");
@@ -306,6 +307,7 @@ function make_io_nodes() {
         this.desc = "Input source node.<br>\
         Supports file paths or wildcards (*) for batch processing.<br>\
         Each matching file becomes a separate input (-i).<br>\
+        Connect a protocol node to set options for network inputs.<br>\
         Use 'Get media info' to inspect streams and formats.";
 
         // Force initial size in LiteGraph
@@ -336,6 +338,7 @@ function make_io_nodes() {
 
         const g = this.getInputOrProperty("globals");
         const d = this.getInputOrProperty("demuxer");
+        const p = this.getInputOrProperty("protocol");
         const str = this.getInputOrProperty("src_path");
         const dec_a = this.getInputOrProperty("dec:a");
         const dec_v = this.getInputOrProperty("dec:v");
@@ -345,6 +348,7 @@ function make_io_nodes() {
             (dec_a ? "-c:a " + dec_a + " " : "") +
             (dec_v ? "-c:v " + dec_v + " " : "") +
             (d ? d + " " : "") +
+            (p ? p + " " : "") +
             (g ? g + " " : "") +
             "-i " + ensureQuoted(str)
         );
@@ -439,6 +443,9 @@ function make_io_nodes() {
         this.addInput("enc:v",graph_consts.ENCODER);
         this.addInput("enc:a",graph_consts.ENCODER);
         this.addInput("muxer", graph_consts.FORMAT);
+        this.addInput("bsf:v", graph_consts.BSF);
+        this.addInput("bsf:a", graph_consts.BSF);
+        this.addInput("protocol", graph_consts.PROTOCOL);
         this.addInput("stream",graph_consts.MAP_STREAM);
 
         this.addProperty("dst_path", "");
@@ -456,7 +463,7 @@ function make_io_nodes() {
         });
         this.title = "OUT";
         this.desc = "Output destination node.<br>\
-        Combines streams, encoders, muxer, and global options into one output file.<br>\
+        Combines streams, encoders, bitstream filters, muxer, protocol and global options into one output file.<br>\
         Supports placeholders: {name}, {index}, {hash}, or * in filenames.<br>\
         If no placeholder is used, {name} is auto-injected before extension.";
     }
@@ -481,12 +488,18 @@ function make_io_nodes() {
         var m = this.getInputOrProperty("muxer")
         var enc_a = this.getInputOrProperty("enc:a");
         var enc_v = this.getInputOrProperty("enc:v");
+        var bsf_a = this.getInputOrProperty("bsf:a");
+        var bsf_v = this.getInputOrProperty("bsf:v");
+        var p = this.getInputOrProperty("protocol");
         var path = this.getInputOrProperty("dst_path");
         window.global_ffmpeg.outputs = window.global_ffmpeg.outputs ? window.global_ffmpeg.outputs : [];
         window.global_ffmpeg.outputs.push(streams + " " + (m ? m + " " : "") +
+            (p ? p + " " : "") +
             (g ? g + " " : "") +
             (enc_a ? "-c:a " + enc_a + " " : "") +
             (enc_v ? "-c:v " + enc_v + " " : "") +
+            (bsf_a ? "-bsf:a " + ensureQuoted(bsf_a) + " " : "") +
+            (bsf_v ? "-bsf:v " + ensureQuoted(bsf_v) + " " : "") +
             ensureQuoted(path));
     };
     ffoutput.prototype.onDrawForeground = function (ctx) {
@@ -611,8 +624,15 @@ function make_nodes(nodes) {
                         this.node_type =graph_consts.N_ENCODER;
                     }
                     else if (item["pcategory"] == 'filters') {
-                        this.addInput("stream",graph_consts.MAP_STREAM); 
-                        this.addOutput("n-streams",graph_consts.N_STREAMS);
+                        // Catalogs without pads only know "any number of streams"
+                        const pads = item["filter"];
+                        this.dynamic_inputs = !pads || pads.dynamic_inputs;
+                        if (this.dynamic_inputs) this.addInput("stream",graph_consts.MAP_STREAM);
+                        else pads.inputs.forEach(pad => this.addInput(pad_name(pad), graph_consts.MAP_STREAM));
+
+                        // Sinks such as nullsink have nothing to select from
+                        if (!pads || pads.dynamic_outputs || pads.outputs.length > 0)
+                            this.addOutput("n-streams",graph_consts.N_STREAMS);
                         this.node_type =graph_consts.N_FILTER;
                     }
                     else if (item["pcategory"] == 'demuxers') {
@@ -623,6 +643,18 @@ function make_nodes(nodes) {
                         this.addOutput("muxer", graph_consts.FORMAT);
                         this.node_type =graph_consts.N_FORMAT;
                     }
+                    else if (item["pcategory"] == 'devices') {
+                        this.addOutput("device", graph_consts.FORMAT);
+                        this.node_type =graph_consts.N_FORMAT;
+                    }
+                    else if (item["pcategory"] == 'bsfs') {
+                        this.addOutput("bsf", graph_consts.BSF);
+                        this.node_type =graph_consts.N_BSF;
+                    }
+                    else if (item["pcategory"] == 'protocols') {
+                        this.addOutput("protocol", graph_consts.PROTOCOL);
+                        this.node_type =graph_consts.N_PROTOCOL;
+                    }
 
                     if (item["category"].includes("V")) category = category + "video/";
                     if (item["category"].includes("A")) category = category + "audio/";
@@ -653,9 +685,12 @@ function make_nodes(nodes) {
                 if (window.global_ffmpeg.selected_only && !this.is_selected) { return; }
                 
                 let connected = false;
-                for (var i = 0; i < this.outputs.length; i++) {
-                    if(this.outputs[i].links) connected = true;
+                if (this.outputs && this.outputs.length) {
+                    for (var i = 0; i < this.outputs.length; i++) {
+                        if(this.outputs[i].links) connected = true;
+                    }
                 }
+                else if (this.inputs) connected = this.inputs.some(input => input.link != null);
                 if(!connected) return;
 
                 switch (this.node_type) {
@@ -672,11 +707,19 @@ function make_nodes(nodes) {
                         exec_format(this);
                         break;
                     }
+                    case graph_consts.N_BSF: {
+                        exec_bsf(this);
+                        break;
+                    }
+                    case graph_consts.N_PROTOCOL: {
+                        exec_protocol(this);
+                        break;
+                    }
                 }
             };
 
             fn.prototype.onDrawForeground = function (ctx) {
-                if (item["pcategory"] == 'filters') {
+                if (item["pcategory"] == 'filters' && this.dynamic_inputs) {
                     var free_slots = 0;
                     this.inputs.forEach(element => {
                         if (element.type ==graph_consts.MAP_STREAM && element.link == null)
@@ -697,7 +740,7 @@ function make_nodes(nodes) {
 
             LiteGraph.registerNodeType(category + item["name"], fn);
         }
-        else { 
+        else if (item["pcategory"] !== "colors") { // named colors only describe values, they have no flag
             category = "ffmpeg/general/" + item["name"] + "/";
             item["options"].forEach(opt => {
 
@@ -763,9 +806,14 @@ function make_nodes(nodes) {
     });
 }
 
+// Input pad label, "default" pads keep the generic name
+function pad_name(pad) {
+    return pad.name && pad.name !== "default" ? pad.name : "stream";
+}
+
 function exec_filter(node) {
     var result = "";
-    for (var i = 0; i < node.inputs.length; i++) {
+    for (var i = 0; node.inputs && i < node.inputs.length; i++) {
         var str = node.getInputData(i);
         if (str != undefined) result += "[" + str.data + "]";
     }
@@ -796,6 +844,27 @@ function exec_format(node) {
     node.setOutputData(0, result);
 }
 
+// "name=opt=value:opt=value", as -bsf:v takes it
+function exec_bsf(node) {
+    var opt = "";
+    if (node.widgets) node.widgets.forEach(item => {
+        if (item.value) opt += item.name.replace(/^-/, "") + "=" + item.value + ":";
+    });
+    if (opt.slice(-1) == ":") opt = opt.slice(0, -1);
+
+    node.setOutputData(0, opt !== "" ? node.name + "=" + opt : node.name);
+}
+
+// Protocol options are plain input/output options, there is no flag naming the protocol
+function exec_protocol(node) {
+    var result = "";
+    if (node.widgets) node.widgets.forEach(item => {
+        if (item.value) result += (result ? " " : "") + item.name + " " + item.value;
+    });
+
+    node.setOutputData(0, result);
+}
+
 function exec_codec(node) {
     var result = node.name;
     if (node.widgets) node.widgets.forEach(item => {
@@ -2,8 +2,10 @@ import { formatTime } from '../core/format.js';
 
 import { addLogEntry } from '../logs/logs.js';
 import { serverUrl } from '../core/server-port.js';
+import { get_ffmpeg_command } from '../graph/execution.js';
 
 const { open } = window.__TAURI__.dialog;
+const { invoke } = window.__TAURI__.core;
 
 export class ComparePlayer {
     constructor() {
@@ -27,6 +29,13 @@ export class ComparePlayer {
         this.timeDisplay = document.getElementById('compare-time-display');
         
         this.labelBContainer = document.getElementById('label-b-container');
+
+        this.captureA = document.getElementById('capture-a');
+        this.captureB = document.getElementById('capture-b');
+        this.renderAbBtn = document.getElementById('render-ab');
+        this.abStatus = document.getElementById('compare-ab-status');
+        this.metricsPanel = document.getElementById('compare-metrics');
+        this.variants = { a: null, b: null };
         
         this.splitPosition = 50; // percentage
         this.isDragging = false;
@@ -74,6 +83,7 @@ export class ComparePlayer {
         this.setupPlaybackControls();
         this.setupFileInputs();
         this.setupTimelineControls();
+        this.setupAbRender();
     }
 
     setupSplitDivider() {
@@ -183,6 +193,103 @@ export class ComparePlayer {
         });
     }
 
+    setupAbRender() {
+        const capture = (name, button) => {
+            const cmd = get_ffmpeg_command();
+            if (!cmd) {
+                addLogEntry("error", `Failed to capture variant ${name.toUpperCase()}! FFmpeg workflow graph is missing!`);
+                return;
+            }
+            this.variants[name] = cmd;
+            button.classList.add('captured');
+            addLogEntry("info", `Captured variant ${name.toUpperCase()}: ${cmd}`);
+        };
+        this.captureA.addEventListener('click', () => capture('a', this.captureA));
+        this.captureB.addEventListener('click', () => capture('b', this.captureB));
+        this.renderAbBtn.addEventListener('click', () => this.renderAb());
+    }
+
+    renderAb() {
+        if (!this.variants.a || !this.variants.b) {
+            addLogEntry("error", "Capture both variants A and B first");
+            return;
+        }
+        const selection = window.timeline && window.timeline.selection;
+        if (!selection || selection.start === selection.end) {
+            addLogEntry("error", "Select a segment on the timeline to compare");
+            return;
+        }
+
+        this.renderAbBtn.disabled = true;
+        this.abStatus.textContent = `Rendering ${formatTime(selection.start)} - ${formatTime(selection.end)}...`;
+        invoke('render_ab_preview', {
+            cmdA: window.FFMPEG_BIN + " " + this.variants.a,
+            cmdB: window.FFMPEG_BIN + " " + this.variants.b,
+            env: window.FFMPEG_ENV,
+            start: selection.start.toFixed(3),
+            end: selection.end.toFixed(3),
+            variables: window.graph_variables || {},
+            workflow: window.selectedWorkflow || null
+        }).then(result => {
+            this.loadComparison(result);
+            this.abStatus.textContent = `Measured against ${result.source}`;
+            addLogEntry("info", `A/B comparison ready. A: ${result.a} B: ${result.b}`);
+        }).catch(err => {
+            this.abStatus.textContent = 'Comparison failed';
+            addLogEntry("error", `Failed to compare variants: ${err}`);
+        }).finally(() => {
+            this.renderAbBtn.disabled = false;
+        });
+    }
+
+    loadComparison(result) {
+        this.videoPathA.value = result.a;
+        this.videoPathB.value = result.b;
+        this.videoA.src = serverUrl(result.a);
+        this.videoB.src = serverUrl(result.b);
+        this.videoA.load();
+        this.videoB.load();
+        this.setOffset(0);
+        this.updateEmptyState();
+        this.showMetrics(result);
+    }
+
+    showMetrics(result) {
+        const metrics = [
+            { key: 'psnr', label: 'PSNR (dB)', digits: 2 },
+            { key: 'ssim', label: 'SSIM', digits: 4 },
+            { key: 'vmaf', label: 'VMAF', digits: 2 }
+        ];
+        const cell = (text, cls) => {
+            const div = document.createElement('div');
+            if (cls) div.className = cls;
+            div.textContent = text;
+            return div;
+        };
+
+        this.metricsPanel.innerHTML = '';
+        this.metricsPanel.appendChild(cell('', 'metric-head'));
+        metrics.forEach(m => this.metricsPanel.appendChild(cell(m.label, 'metric-head')));
+
+        for (const name of ['a', 'b']) {
+            const own = result[`metrics_${name}`];
+            const other = result[`metrics_${name === 'a' ? 'b' : 'a'}`];
+            this.metricsPanel.appendChild(cell(name.toUpperCase(), 'metric-head'));
+            metrics.forEach(m => {
+                const summary = own[m.key];
+                if (!summary) {
+                    const reason = m.key === 'vmaf' && !result.vmaf ? 'no libvmaf' : '-';
+                    this.metricsPanel.appendChild(cell(reason));
+                    return;
+                }
+                const best = !other[m.key] || summary.mean >= other[m.key].mean;
+                const text = `${summary.mean.toFixed(m.digits)} (min ${summary.min.toFixed(m.digits)})`;
+                this.metricsPanel.appendChild(cell(text, best ? 'metric-best' : ''));
+            });
+        }
+        this.metricsPanel.classList.add('visible');
+    }
+
     setupTimelineControls() {
         // Listen for offset changes
         this.offsetInput.addEventListener('input', () => {
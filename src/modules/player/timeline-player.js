@@ -4,8 +4,15 @@ import { get_ffmpeg_command } from '../graph/execution.js';
 import { addLogEntry } from '../logs/logs.js';
 import { serverUrl } from '../core/server-port.js';
 
-const { once } = window.__TAURI__.event;
+const { listen } = window.__TAURI__.event;
 const { invoke } = window.__TAURI__.core;
+const { save } = window.__TAURI__.dialog;
+
+const EXPORT_FORMATS = {
+    gif: { name: "GIF", extension: "gif", rate: "fps" },
+    webp: { name: "WebP", extension: "webp", rate: "fps" },
+    sheet: { name: "Contact sheet", extension: "png", rate: "frames" }
+};
 
 export class TimelinePlayer {
     constructor() {
@@ -17,12 +24,20 @@ export class TimelinePlayer {
         this.generateBtn = document.getElementById("generate-btn");
         this.resetBtn = document.getElementById("reset-btn");
         this.resetSelectionBtn = document.getElementById("reset-selection-btn");
+        this.accurateFrameBtn = document.getElementById("accurate-frame-btn");
+        this.waveformBtn = document.getElementById("waveform-btn");
+        this.spectrumBtn = document.getElementById("spectrum-btn");
+        this.exportFormat = document.getElementById("export-format");
+        this.exportWidth = document.getElementById("export-width");
+        this.exportRate = document.getElementById("export-rate");
+        this.exportBtn = document.getElementById("export-btn");
         this.zoomInBtn = document.getElementById("zoom-in");
         this.zoomOutBtn = document.getElementById("zoom-out");
         this.timelineContainer = document.getElementById("timeline");
         
         this.isSyncing = false;
         this.timeline = null;
+        this.pendingFrame = null;
         
         this.init();
     }
@@ -121,10 +136,35 @@ export class TimelinePlayer {
             this.timeline.clearSelection();
         });
 
+        // Accurate single frame toggle
+        this.accurateFrameBtn.addEventListener("click", () => {
+            this.accurateFrameBtn.classList.toggle('active');
+        });
+
+        // Audio of the first input behind the segments
+        this.waveformBtn.addEventListener("click", () => {
+            this.toggleAudioView(this.waveformBtn, false);
+        });
+
+        this.spectrumBtn.addEventListener("click", () => {
+            this.toggleAudioView(this.spectrumBtn, true);
+        });
+
         // Generate button
         this.generateBtn.addEventListener("click", () => {
             this.handleGeneratePreview();
         });
+
+        // Export controls, contact sheets take a frame count instead of a frame rate
+        this.exportFormat.addEventListener("change", () => {
+            const frames = EXPORT_FORMATS[this.exportFormat.value].rate === "frames";
+            this.exportRate.placeholder = frames ? "Frames" : "FPS";
+            this.exportRate.title = frames ? "Number of frames in the sheet" : "Frames per second";
+        });
+
+        this.exportBtn.addEventListener("click", () => {
+            this.handleExport();
+        });
     }
 
     setupVideoEvents() {
@@ -174,9 +214,18 @@ export class TimelinePlayer {
             return;
         }
 
+        const isFrame = this.timeline.selection.start === this.timeline.selection.end;
+        // Frames share a channel, so a new one replaces a frame that is still rendering
+        if (isFrame && this.pendingFrame) {
+            this.pendingFrame.cancel();
+            this.pendingFrame = null;
+        }
         const segmentIndex = this.timeline.segments.length;
+        const accurate = isFrame && this.accurateFrameBtn.classList.contains('active');
+        // Accurate frames are requested to the millisecond, anything else to the second
+        const formatPreviewTime = (t) => accurate ? t.toFixed(3) : formatTime(t);
         
-        if (this.timeline.selection.start !== this.timeline.selection.end) {
+        if (!isFrame) {
             // Add segment with loading state
             this.timeline.addSegment({ 
                 start: this.timeline.selection.start, 
@@ -198,8 +247,8 @@ export class TimelinePlayer {
         } 
         
         // Listen for preview completion
-        once('render_preview_listener', (event) => { 
-            let data = event.payload;
+        const onPreviewReady = (data) => {
+            if (isFrame) this.pendingFrame = null;
             this.timeline.clearSelection();
 
             invoke('file_exists', {path: data}).then(exists => {
@@ -222,7 +271,28 @@ export class TimelinePlayer {
                 this.timeline.segments[segmentIndex].path = serverUrl(data);
                 addLogEntry("info", `${this.timeline.segments[segmentIndex].type} was created. Location: ${data}`);
             });
-        }); 
+        };
+        const dropSegment = () => {
+            if (isFrame) this.pendingFrame = null;
+            this.timeline.removeSegment(segmentIndex);
+        };
+        // Every preview reports on the same event, only react to the file this one renders
+        let expectedPath = null;
+        let stopCancelled = null;
+        let settled = false;
+        // Stops listening, false if the preview already ended some other way
+        const settle = () => {
+            if (settled) return false;
+            settled = true;
+            stopListening.then(unlisten => unlisten());
+            if (stopCancelled) stopCancelled.then(unlisten => unlisten());
+            return true;
+        };
+        const stopListening = listen('render_preview_listener', (event) => {
+            if (event.payload !== expectedPath || !settle()) return;
+            onPreviewReady(event.payload);
+        });
+        if (isFrame) this.pendingFrame = { cancel: () => { if (settle()) this.timeline.removeSegment(segmentIndex); } };
         
         addLogEntry("info", `Executing ffmpeg preview cmd: ${window.FFMPEG_BIN + " " + cmd}`);
         invoke('render_preview_request', { 
@@ -233,8 +303,166 @@ export class TimelinePlayer {
                 "cmd": window.FFMPEG_BIN + " " + cmd,
                 "workflow": window.selectedWorkflow
             }),
-            start: formatTime(this.timeline.selection.start), 
-            end: formatTime(this.timeline.selection.end) 
+            start: formatPreviewTime(this.timeline.selection.start), 
+            end: formatPreviewTime(this.timeline.selection.end),
+            variables: window.graph_variables || {},
+            accurate,
+            channel: isFrame ? 'timeline-frame' : null
+        }).then(ticket => {
+            if (!ticket.cached) {
+                expectedPath = ticket.path;
+                // A job cancelled from the queue never reports on the preview event
+                stopCancelled = listen(`transcode_${ticket.job_id}`, (event) => {
+                    if (event.payload !== 'EOT_CANCELLED' || !settle()) return;
+                    dropSegment();
+                    addLogEntry("info", `Preview cancelled`);
+                });
+                return;
+            }
+            if (!settle()) return;
+            addLogEntry("info", `Preview loaded from cache`);
+            onPreviewReady(ticket.path);
+        }).catch(err => {
+            if (!settle()) return;
+            dropSegment();
+            addLogEntry("error", `Failed to execute ffmpeg preview cmd: ${err}`);
+        });
+    }
+
+    toggleAudioView(button, spectrum) {
+        const clear = () => spectrum ? this.timeline.setSpectrum(null) : this.timeline.setWaveform(null);
+        if (button.classList.contains('active')) {
+            button.classList.remove('active');
+            clear();
+            return;
+        }
+
+        let cmd = get_ffmpeg_command();
+        if(!cmd) {
+            addLogEntry("error", `Failed to load audio! FFmpeg workflow graph is missing!`);
+            return;
+        }
+        const match = cmd.match(/-i\s+(?:"([^"]+)"|'([^']+)'|(\S+))/);
+        const path = match && (match[1] || match[2] || match[3]);
+        if (!path) {
+            addLogEntry("error", "No input file found in ffmpeg command");
+            return;
+        }
+
+        button.classList.add('active');
+        addLogEntry("info", `Analyzing audio of ${path}`);
+        invoke('get_waveform', {
+            path,
+            ffmpeg: window.FFMPEG_BIN,
+            env: window.FFMPEG_ENV,
+            spectrum
+        }).then(result => {
+            // Turned off while it was loading
+            if (!button.classList.contains('active')) return;
+            if (spectrum) {
+                this.timeline.setSpectrum(serverUrl(result.spectrum), result.peaks.duration);
+            } else {
+                this.timeline.setWaveform(result.peaks);
+            }
+            addLogEntry("info", `Audio ${spectrum ? "spectrogram" : "waveform"} ${result.cached ? "loaded from cache" : "ready"}`);
+        }).catch(err => {
+            button.classList.remove('active');
+            clear();
+            addLogEntry("error", `Failed to analyze audio: ${err}`);
+        });
+    }
+
+    handleExport() {
+        if(!window.selectedWorkflow) { 
+            addLogEntry("error", "Caught error: Please, create or select a workflow."); 
+            return; 
+        }
+
+        let cmd = get_ffmpeg_command();
+        if(!cmd) {
+            addLogEntry("error", `Failed to export! FFmpeg workflow graph is missing!`);
+            return;
+        }
+
+        const selection = this.timeline.selection;
+        if (!selection || selection.start === selection.end) {
+            addLogEntry("error", "Please select a segment to export"); 
+            return;
+        }
+
+        const format = EXPORT_FORMATS[this.exportFormat.value];
+        const width = parseInt(this.exportWidth.value) || null;
+        const target = { format: this.exportFormat.value, width };
+        if (format.rate === "frames") {
+            target.frames = parseInt(this.exportRate.value) || null;
+        } else {
+            target.fps = parseFloat(this.exportRate.value) || null;
+        }
+
+        const saveExport = (path) => {
+            save({
+                defaultPath: `export_${new Date().toISOString().replace(/[:.]/g, '-')}.${format.extension}`,
+                filters: [{ name: format.name, extensions: [format.extension] }]
+            }).then(destination => {
+                if (!destination) {
+                    addLogEntry('warning', `Export cancelled by user. It stays cached at ${path}`);
+                    return;
+                }
+                return invoke('copy_file', { source: path, destination }).then(() => {
+                    addLogEntry('success', `${format.name} exported: ${destination}`);
+                });
+            }).catch(err => {
+                addLogEntry('error', `Failed to save export: ${err}`);
+            });
+        };
+
+        let expectedPath = null;
+        let stopCancelled = null;
+        const stopAll = () => {
+            stopListening.then(unlisten => unlisten());
+            if (stopCancelled) stopCancelled.then(unlisten => unlisten());
+        };
+        const stopListening = listen('render_preview_listener', (event) => {
+            if (event.payload !== expectedPath) return;
+            stopAll();
+            invoke('file_exists', {path: event.payload}).then(exists => {
+                if (exists) {
+                    saveExport(event.payload);
+                } else {
+                    addLogEntry("error", `${format.name} export failed, see the queue logs`);
+                }
+            });
+        });
+
+        addLogEntry("info", `Exporting ${format.name} of ${formatTime(selection.start)} - ${formatTime(selection.end)}`);
+        invoke('render_preview_request', { 
+            cmd: window.FFMPEG_BIN + " " + cmd, 
+            env: window.FFMPEG_ENV, 
+            desc: JSON.stringify({
+                "tag": "export", 
+                "cmd": window.FFMPEG_BIN + " " + cmd,
+                "workflow": window.selectedWorkflow
+            }),
+            start: selection.start.toFixed(3), 
+            end: selection.end.toFixed(3),
+            variables: window.graph_variables || {},
+            channel: 'timeline-export',
+            target
+        }).then(ticket => {
+            if (!ticket.cached) {
+                expectedPath = ticket.path;
+                stopCancelled = listen(`transcode_${ticket.job_id}`, (event) => {
+                    if (event.payload !== 'EOT_CANCELLED') return;
+                    stopAll();
+                    addLogEntry("info", `${format.name} export cancelled`);
+                });
+                return;
+            }
+            stopAll();
+            saveExport(ticket.path);
+        }).catch(err => {
+            stopAll();
+            addLogEntry("error", `Failed to export: ${err}`);
         });
     }
 
@@ -67,6 +67,8 @@ export class Timeline {
         this.isPlaying = false;
         this.segments = options.segments ?? [];
         this.selection = null; // {start,end}
+        this.waveform = null; // peaks from get_waveform
+        this.spectrum = null; // loaded spectrogram image
         this.activeSegmentIndex = -1;
         // Animation state - ADD THIS
         this._animationInterval = null;
@@ -207,6 +209,20 @@ export class Timeline {
         return false;
     }
 
+    // Audio peaks drawn behind the segments, null to hide
+    setWaveform(peaks) {
+        this.waveform = peaks && peaks.min.length ? peaks : null;
+        this.invalidate();
+    }
+
+    // Spectrogram image spanning `duration` seconds, null to hide
+    setSpectrum(src, duration) {
+        if (!src) { this.spectrum = null; this.invalidate(); return; }
+        const image = new Image();
+        image.onload = () => { this.spectrum = { image, duration }; this.invalidate(); };
+        image.src = src;
+    }
+
     fitToSegments(padSec = 1) {
         if (!this.segments.length) { return; }
         let min = Infinity, max = -Infinity; for (const s of this.segments) { min = Math.min(min, s.start); max = Math.max(max, s.end); }
@@ -540,6 +556,45 @@ export class Timeline {
         }
     }
 
+    _drawSpectrum(top, height) {
+        const { image, duration } = this.spectrum;
+        const t0 = Math.max(0, this.xToTime(0));
+        const t1 = Math.min(duration, this.xToTime(this.width));
+        if (t1 <= t0) return;
+        const sx = t0 / duration * image.width;
+        const sw = (t1 - t0) / duration * image.width;
+        const x0 = this.timeToX(t0), x1 = this.timeToX(t1);
+        this.ctx.save();
+        this.ctx.globalAlpha = 0.5;
+        this.ctx.drawImage(image, sx, 0, sw, image.height, x0, top, x1 - x0, height);
+        this.ctx.restore();
+    }
+
+    _drawWaveform(top, height) {
+        const ctx = this.ctx;
+        const { min, max, bucket_size, sample_rate } = this.waveform;
+        const bucketSec = bucket_size / sample_rate;
+        const mid = top + height / 2;
+        const scale = height / 2 / 32768;
+        const x0 = Math.max(0, Math.floor(this.timeToX(0)));
+        const x1 = Math.min(this.width, Math.ceil(this.timeToX(min.length * bucketSec)));
+
+        ctx.save();
+        ctx.globalAlpha = 0.45;
+        ctx.fillStyle = getComputedStyle(document.documentElement).getPropertyValue('--accent');
+        for (let x = x0; x < x1; x++) {
+            // Every bucket under this pixel, at least one when zoomed in past a bucket per pixel
+            const b0 = Math.floor(this.xToTime(x) / bucketSec);
+            const b1 = Math.max(b0 + 1, Math.floor(this.xToTime(x + 1) / bucketSec));
+            let lo = 0, hi = 0;
+            for (let b = Math.max(0, b0); b < Math.min(min.length, b1); b++) {
+                lo = Math.min(lo, min[b]); hi = Math.max(hi, max[b]);
+            }
+            ctx.fillRect(x, mid - hi * scale, 1, Math.max(1, (hi - lo) * scale));
+        }
+        ctx.restore();
+    }
+
     _draw() {
         const ctx = this.ctx; 
         const w = this.width, h = this.height;
@@ -570,6 +625,10 @@ export class Timeline {
         const laneTop = 28;
         const laneHeight = h - laneTop - 8;
 
+        // Audio behind the segments
+        if (this.spectrum) this._drawSpectrum(laneTop, laneHeight);
+        if (this.waveform) this._drawWaveform(laneTop, laneHeight);
+
         // Segments
         for (let i = 0; i < this.segments.length; i++) {
             const s = this.segments[i];
@@ -35,6 +35,31 @@ export function initializeQueue() {
         }
     });
 
+    const previewCacheInput = document.getElementById('preview-cache-limit');
+    const previewCacheBtn = document.getElementById('preview-cache-set');
+
+    invoke('get_preview_cache_stats').then(stats => {
+        previewCacheInput.value = Math.round(stats.limit / MB);
+        updatePreviewCacheUsage(stats);
+    });
+
+    previewCacheBtn.addEventListener('click', (e) => {
+        const value = parseInt(previewCacheInput.value);
+        if (value >= 0) {
+            setPreviewCacheLimit(value);
+        }
+    });
+
+    document.getElementById('preview-cache-clear').addEventListener('click', async (e) => {
+        try {
+            await invoke('delete_cache_request');
+            updatePreviewCacheUsage(await invoke('get_preview_cache_stats'));
+            addLogEntry('success', 'Preview cache cleared');
+        } catch (err) {
+            addLogEntry('error', `Failed to clear preview cache: ${err}`);
+        }
+    });
+
     // Listen for queue status changes
     listen('queue_status_changed', (event) => {
         updateQueueDisplay(event.payload);
@@ -75,6 +100,7 @@ async function refreshQueueStatus() {
         // Silently ignore - watchfolder may not be available
     }
     updateTrayMenu(jobs, watches);
+    invoke('get_preview_cache_stats').then(updatePreviewCacheUsage).catch(() => {});
 }
 
 let lastQueueText = '';
@@ -252,12 +278,21 @@ function createWatchEntry(watch) {
             <span class="info-text">→ ${watch.output_dir}/${watch.output_name} | Files queued: ${watch.files_queued}</span>
         </div>
         <div class="queue-entry-actions">
+            <button class="watch-pause-btn" data-watch-id="${watch.id}">
+                ${pauseButtonHTML(watch)}
+            </button>
             <button class="watch-stop-btn" data-watch-id="${watch.id}">
                 <i class="fas fa-stop"></i> Stop
             </button>
         </div>
     `;
 
+    // Pause/resume button listener
+    const pauseBtn = entry.querySelector('.watch-pause-btn');
+    pauseBtn.addEventListener('click', () => {
+        togglePauseWatch(watch.id);
+    });
+
     // Stop button listener
     const stopBtn = entry.querySelector('.watch-stop-btn');
     stopBtn.addEventListener('click', () => {
@@ -268,6 +303,7 @@ function createWatchEntry(watch) {
         element: entry,
         id: watch.id,
         key: `watch-${watch.id}`,
+        status: watch.status,
     };
 }
 
@@ -279,6 +315,29 @@ function updateWatchEntry(entry, watch) {
 
     const infoText = entry.element.querySelector('.info-text');
     infoText.textContent = `${watch.output_dir}/${watch.output_name} | Files found: ${watch.files_queued}`;
+
+    const pauseBtn = entry.element.querySelector('.watch-pause-btn');
+    pauseBtn.innerHTML = pauseButtonHTML(watch);
+    entry.status = watch.status;
+}
+
+function pauseButtonHTML(watch) {
+    return watch.status === 'Paused'
+        ? '<i class="fas fa-play"></i> Resume'
+        : '<i class="fas fa-pause"></i> Pause';
+}
+
+async function togglePauseWatch(id) {
+    const watch = watchEntries.get(`watch-${id}`);
+    const paused = watch && watch.status === 'Paused';
+    try {
+        const result = await invoke(paused ? 'resume_watchfolder' : 'pause_watchfolder', { id });
+        if (result) {
+            addLogEntry('info', `Watch folder ${id} ${paused ? 'resumed' : 'paused'}`);
+        }
+    } catch (err) {
+        addLogEntry('error', `Failed to ${paused ? 'resume' : 'pause'} watch folder: ${err}`);
+    }
 }
 
 async function stopWatch(id) {
@@ -582,6 +641,16 @@ function handleJobProgress(jobId, payload) {
         return;
     }
 
+    if (payload === 'EOT_CANCELLED') {
+        // Cancelled, or a preview replaced by a newer one on the same channel
+        entry.status = 'Cancelled';
+        updateJobEntry(entry, { id: jobId, status: 'Cancelled' });
+        progressText.textContent = 'Cancelled';
+        progressFill.style.width = '100%';
+        progressFill.style.backgroundColor = 'var(--warning)';
+        return;
+    }
+
     if (payload === 'Pipeline started') {
         entry.startTime = Date.now(); // Reset start time when pipeline actually starts
         progressText.textContent = 'Processing...';
@@ -678,6 +747,23 @@ async function setMaxConcurrent(value) {
     }
 }
 
+const MB = 1024 * 1024;
+
+function updatePreviewCacheUsage(stats) {
+    const usage = document.getElementById('preview-cache-usage');
+    usage.textContent = `${(stats.bytes / MB).toFixed(1)} MB used, ${stats.entries} previews`;
+}
+
+async function setPreviewCacheLimit(value) {
+    try {
+        const stats = await invoke('set_preview_cache_limit', { bytes: value * MB });
+        updatePreviewCacheUsage(stats);
+        addLogEntry('success', `Preview cache limit set to ${value} MB`);
+    } catch (err) {
+        addLogEntry('error', `Failed to set preview cache limit: ${err}`);
+    }
+}
+
 async function cancelJob(jobId) {
     try {
         const success = await invoke('cancel_job', { jobId });
@@ -758,6 +844,11 @@ function forwardToLogs(jobId, payload) {
         return;
     }
     
+    if (payload === 'EOT_CANCELLED') {
+        addLogEntry('info', `[${jobId}] Cancelled`);
+        return;
+    }
+
     if (payload === 'Pipeline started') {
         addLogEntry('info', `[${jobId}] Pipeline started`);
         return;
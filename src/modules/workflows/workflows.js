@@ -5,6 +5,7 @@ import { exportGraph, importGraph } from '../graph/import_export.js';
 import { make_nodes, make_io_nodes, make_control_node } from '../graph/nodes.js';
 import { graph, canvas, updateCanvasVisibility } from '../graph/core.js';
 import { GraphUndoManager } from '../graph/undo_redo.js';
+import { get_ffmpeg_template } from '../graph/execution.js';
 
 const { listen, once } = window.__TAURI__.event;
 const { invoke } = window.__TAURI__.core;
@@ -30,6 +31,7 @@ export function addNewWorkflow(name, path, select = false) {
                 <button class="action-btn save" title="Save" style="display:none"><i class="fas fa-save"></i></button>
                 <button class="action-btn export" title="Export .ffgraph" style="display:none"><i class="fas fa-file-export"></i></button>
                 <button class="action-btn import" title="Import .ffgraph" style="display:none"><i class="fas fa-file-import"></i></button>
+                <button class="action-btn refresh" title="Parse failed FFmpeg categories again" style="display:none"><i class="fas fa-sync"></i></button>
                 <div> </div>
                 <button class="action-btn delete" title="Delete"><i class="fas fa-trash"></i></button>
             </div>
@@ -68,11 +70,18 @@ export function addNewWorkflow(name, path, select = false) {
         e.stopPropagation();
         importGraph();
     });
+    newWorkflow.querySelector('.action-btn.refresh').addEventListener('click', (e) => {
+        e.stopPropagation();
+        refreshWorkflow(name);
+    });
     if(select) selectWorkflow(name);
     return true;
 }
 
 
+// Graph to restore instead of the saved one when the workflow is reloaded in place
+let keptGraph = null;
+
 // listen
 listen('get_workflow_listener', (event) => {
     LiteGraph.clearRegisteredTypes();
@@ -92,6 +101,7 @@ listen('get_workflow_listener', (event) => {
     if(data.message !== "OK") {
         window.FFMPEG_BIN = "";
         window.FFMPEG_ENV = "";
+        keptGraph = null;
 
         addLogEntry('error', data.message);
         return;
@@ -100,10 +110,23 @@ listen('get_workflow_listener', (event) => {
     window.FFMPEG_BIN = data.path;
     window.FFMPEG_ENV = data.env;
 
+    let report = data["report"] ? data["report"]["categories"] : [];
+    let failed = report.filter(c => !c.ok);
+    failed.forEach(c => {
+        addLogEntry('warning', `FFmpeg ${c.category} could not be parsed: ${c.error}`);
+    });
+    // Failures are cached with the catalog, only the refresh button parses them again
+    document.querySelectorAll('.workflow-item').forEach(item => {
+        let selected = item.getAttribute('data-workflow') === window.selectedWorkflow;
+        item.querySelector('.action-btn.refresh').style.display = selected && failed.length ? "block" : "none";
+    });
+
     make_nodes(data["nodes"]);
     make_io_nodes();
     make_control_node();
-    if(data["graph"]) graph.configure(JSON.parse(data["graph"]));
+    let graphData = keptGraph ?? data["graph"];
+    keptGraph = null;
+    if(graphData) graph.configure(JSON.parse(graphData));
 
     // Reset undo history when loading new workflow
     if (window.__GRAPH_UNDO_MGR__) {
@@ -118,7 +141,6 @@ export async function selectWorkflow(name) {
         return;
     }
     if(window.timeline) window.timeline.reset();
-    invoke('delete_cache_request', {});
     let workflowItems = document.querySelectorAll('.workflow-item');
     let workflowIcons = document.querySelectorAll('.workflow-icon');
     window.selectedWorkflow = name;
@@ -134,6 +156,7 @@ export async function selectWorkflow(name) {
             item.querySelector('.action-btn.save').style.display = "none";
             item.querySelector('.action-btn.export').style.display = "none";
             item.querySelector('.action-btn.import').style.display = "none";
+            item.querySelector('.action-btn.refresh').style.display = "none";
             item.classList.remove('selected');
         }
     });
@@ -157,6 +180,16 @@ export async function selectWorkflow(name) {
     updateCanvasVisibility();
 }
 
+// Parses the catalog categories that failed for the selected workflow again, keeping
+// the graph as it is
+export function refreshWorkflow(name) {
+    if(name !== window.selectedWorkflow) return;
+    keptGraph = JSON.stringify(graph ? graph.serialize() : {});
+    showLoading("Parsing failed FFmpeg categories again!", false);
+    updateLoadingDetails('Initializing FFmpeg Graph...');
+    invoke('get_workflow', {name: name, refresh: true});
+}
+
 export function deleteWorkflow(name) {
     let workflowItems = document.querySelectorAll('.workflow-item');
     let workflowIcons = document.querySelectorAll('.workflow-icon');
@@ -187,7 +220,9 @@ export function saveWorkflow(name) {
         hideLoading();
     });
     let graph_str = JSON.stringify(graph ? graph.serialize() : {});
-    invoke('save_graph', {name: name, graph: graph_str});
+    // Watch folder rules that refer to this workflow run its saved template
+    let template = graph ? get_ffmpeg_template(true) : null;
+    invoke('save_graph', {name: name, graph: graph_str, template: template ?? ""});
     showLoading();
 }
 
@@ -222,7 +257,7 @@ export async function reconfig_graph(path) {
         hideLoading();
     });
     updateLoadingDetails("Trying as fast as possible!");
-    invoke('get_nodes_request', {ffmpeg_path: path});
+    invoke('get_nodes_request', {ffmpeg_path: path, env: window.FFMPEG_ENV});
 }
 
 export async function initWorkflows() {